@@ -0,0 +1,226 @@
+use color_eyre::Result;
+use pixels_graphics_lib::prelude::*;
+
+/// Combines `frames` into an animation
+///
+/// ICA files only store one duration for all frames so the first frame's is used
+pub fn create_animation(
+    frames: &[IndexedImage],
+    durations: &[u32],
+    play_type: PlayType,
+) -> Result<AnimatedIndexedImage> {
+    let pixels = frames
+        .iter()
+        .flat_map(|i| i.get_pixels().to_vec())
+        .collect();
+    Ok(AnimatedIndexedImage::new(
+        frames[0].width(),
+        frames[0].height(),
+        durations[0] as f64 / 1000.0,
+        frames.len() as u8,
+        frames[0].get_palette().to_vec(),
+        pixels,
+        play_type,
+    )?)
+}
+
+/// Reads an ICA file, returning the frames, their durations (in milliseconds), play type and
+/// palette
+pub fn read_animation(
+    bytes: &[u8],
+) -> Result<(Vec<IndexedImage>, Vec<u32>, PlayType, FilePalette)> {
+    let (image, palette) = AnimatedIndexedImage::from_file_contents(bytes)?;
+    let millis = (image.get_per_frame() * 1000.0).round() as u32;
+    Ok((
+        image.as_images(),
+        vec![millis; image.frame_count() as usize],
+        image.play_type(),
+        palette,
+    ))
+}
+
+/// Frame order for one play through of `play_type`, for formats that can only play forwards
+pub fn frame_order(play_type: PlayType, frame_count: usize) -> Vec<usize> {
+    match play_type {
+        PlayType::Once | PlayType::Loops => (0..frame_count).collect(),
+        PlayType::OnceReversed | PlayType::LoopsReversed => (0..frame_count).rev().collect(),
+        PlayType::LoopsBoth => (0..frame_count)
+            .chain((1..frame_count.saturating_sub(1)).rev())
+            .collect(),
+    }
+}
+
+pub fn is_looping(play_type: PlayType) -> bool {
+    matches!(
+        play_type,
+        PlayType::Loops | PlayType::LoopsReversed | PlayType::LoopsBoth
+    )
+}
+
+/// Tracks which frame is shown while an animation is playing
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Playback {
+    frame: usize,
+    /// Only changes for [PlayType::LoopsBoth]
+    forwards: bool,
+}
+
+impl Playback {
+    pub fn new(play_type: PlayType, frame_count: usize) -> Self {
+        let frame = match play_type {
+            PlayType::OnceReversed | PlayType::LoopsReversed => frame_count.saturating_sub(1),
+            _ => 0,
+        };
+        Self {
+            frame,
+            forwards: true,
+        }
+    }
+}
+
+impl Playback {
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Moves to the next frame for `play_type`
+    ///
+    /// Returns false if the animation has finished (only for Once and OnceReversed), the frame
+    /// is left unchanged
+    pub fn advance(&mut self, play_type: PlayType, frame_count: usize) -> bool {
+        let last = frame_count.saturating_sub(1);
+        match play_type {
+            PlayType::Once => {
+                if self.frame >= last {
+                    return false;
+                }
+                self.frame += 1;
+            }
+            PlayType::OnceReversed => {
+                if self.frame == 0 {
+                    return false;
+                }
+                self.frame -= 1;
+            }
+            PlayType::Loops => {
+                self.frame = if self.frame >= last {
+                    0
+                } else {
+                    self.frame + 1
+                };
+            }
+            PlayType::LoopsReversed => {
+                self.frame = if self.frame == 0 {
+                    last
+                } else {
+                    self.frame - 1
+                };
+            }
+            PlayType::LoopsBoth => {
+                if last == 0 {
+                    return true;
+                }
+                if (self.forwards && self.frame >= last) || (!self.forwards && self.frame == 0) {
+                    self.forwards = !self.forwards;
+                }
+                if self.forwards {
+                    self.frame += 1;
+                } else {
+                    self.frame -= 1;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::image::timing::{load_durations, save_durations};
+    use std::fs;
+
+    #[test]
+    fn animation_round_trip() {
+        let palette = vec![TRANSPARENT, BLUE, WHITE];
+        let frames = vec![
+            IndexedImage::new(3, 2, palette.clone(), vec![0, 1, 2, 0, 1, 2]).unwrap(),
+            IndexedImage::new(3, 2, palette.clone(), vec![1, 1, 1, 0, 0, 0]).unwrap(),
+            IndexedImage::new(3, 2, palette, vec![2, 0, 2, 0, 2, 0]).unwrap(),
+        ];
+        for play_type in [
+            PlayType::Once,
+            PlayType::OnceReversed,
+            PlayType::Loops,
+            PlayType::LoopsReversed,
+            PlayType::LoopsBoth,
+        ] {
+            let bytes = create_animation(&frames, &[250, 250, 250], play_type)
+                .unwrap()
+                .to_file_contents(&FilePalette::Colors)
+                .unwrap();
+            let (loaded, _) = AnimatedIndexedImage::from_file_contents(&bytes).unwrap();
+            assert_eq!(loaded.play_type(), play_type);
+            assert_eq!(loaded.get_per_frame(), 0.25);
+            assert_eq!(loaded.as_images(), frames);
+
+            let resaved = create_animation(&loaded.as_images(), &[250], loaded.play_type())
+                .unwrap()
+                .to_file_contents(&FilePalette::Colors)
+                .unwrap();
+            assert_eq!(resaved, bytes);
+        }
+    }
+
+    #[test]
+    fn save_and_reload() {
+        let dir =
+            std::env::temp_dir().join(format!("image_editor_animation_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("walk.ica");
+        let palette = vec![TRANSPARENT, BLUE];
+        let frames = vec![
+            IndexedImage::new(2, 1, palette.clone(), vec![0, 1]).unwrap(),
+            IndexedImage::new(2, 1, palette, vec![1, 0]).unwrap(),
+        ];
+        let durations = [120, 40];
+        let bytes = create_animation(&frames, &durations, PlayType::LoopsReversed)
+            .unwrap()
+            .to_file_contents(&FilePalette::Colors)
+            .unwrap();
+        fs::write(&path, bytes).unwrap();
+        save_durations(&path, &durations).unwrap();
+
+        let (loaded, file_durations, play_type, palette) =
+            read_animation(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(loaded, frames);
+        assert_eq!(file_durations, vec![120, 120]);
+        assert_eq!(play_type, PlayType::LoopsReversed);
+        assert_eq!(palette, FilePalette::Colors);
+        assert_eq!(
+            load_durations(&path, loaded.len()).unwrap(),
+            Some(durations.to_vec())
+        );
+    }
+
+    #[test]
+    fn playback_order() {
+        let frames = |play_type: PlayType, count: usize| {
+            let mut playback = Playback::new(play_type, 3);
+            let mut frames = vec![playback.frame()];
+            for _ in 0..count {
+                if !playback.advance(play_type, 3) {
+                    break;
+                }
+                frames.push(playback.frame());
+            }
+            frames
+        };
+        assert_eq!(frames(PlayType::Once, 6), vec![0, 1, 2]);
+        assert_eq!(frames(PlayType::OnceReversed, 6), vec![2, 1, 0]);
+        assert_eq!(frames(PlayType::Loops, 4), vec![0, 1, 2, 0, 1]);
+        assert_eq!(frames(PlayType::LoopsReversed, 4), vec![2, 1, 0, 2, 1]);
+        assert_eq!(frames(PlayType::LoopsBoth, 6), vec![0, 1, 2, 1, 0, 1, 2]);
+    }
+}
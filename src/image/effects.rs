@@ -0,0 +1,94 @@
+use crate::image::trim::get_transparent_colors;
+use pixels_graphics_lib::prelude::IndexedImage;
+
+const NEIGHBOURS_4: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const NEIGHBOURS_8: [(isize, isize); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+/// Returns the transparent pixels that touch a non-transparent pixel
+///
+/// `diagonals` selects 8-connectivity instead of 4-connectivity
+pub fn outline_outside(image: &IndexedImage, diagonals: bool) -> Vec<usize> {
+    let trans_idxs = get_transparent_colors(image);
+    let is_solid = |xy: Option<usize>| match xy {
+        Some(i) => !trans_idxs.contains(&image.get_pixels()[i]),
+        None => false,
+    };
+    edge_pixels(image, diagonals, |i, neighbours| {
+        !is_solid(Some(i)) && neighbours.into_iter().any(is_solid)
+    })
+}
+
+/// Returns the non-transparent pixels that touch a transparent pixel or the edge of the image
+///
+/// `diagonals` selects 8-connectivity instead of 4-connectivity
+pub fn outline_inside(image: &IndexedImage, diagonals: bool) -> Vec<usize> {
+    let trans_idxs = get_transparent_colors(image);
+    let is_solid = |xy: Option<usize>| match xy {
+        Some(i) => !trans_idxs.contains(&image.get_pixels()[i]),
+        None => false,
+    };
+    edge_pixels(image, diagonals, |i, neighbours| {
+        is_solid(Some(i)) && neighbours.into_iter().any(|n| !is_solid(n))
+    })
+}
+
+/// Returns the transparent pixels covered by the non-transparent pixels when moved by `offset`
+pub fn drop_shadow(image: &IndexedImage, offset: (isize, isize)) -> Vec<usize> {
+    let trans_idxs = get_transparent_colors(image);
+    let pixels = image.get_pixels();
+    let mut output = vec![];
+    for y in 0..image.height() as isize {
+        for x in 0..image.width() as isize {
+            if let Some(target) = pixel_idx(image, x + offset.0, y + offset.1) {
+                let source = pixel_idx(image, x, y).expect("pixel in image");
+                if !trans_idxs.contains(&pixels[source]) && trans_idxs.contains(&pixels[target]) {
+                    output.push(target);
+                }
+            }
+        }
+    }
+    output
+}
+
+fn edge_pixels<F: Fn(usize, Vec<Option<usize>>) -> bool>(
+    image: &IndexedImage,
+    diagonals: bool,
+    is_edge: F,
+) -> Vec<usize> {
+    let offsets: &[(isize, isize)] = if diagonals {
+        &NEIGHBOURS_8
+    } else {
+        &NEIGHBOURS_4
+    };
+    let mut output = vec![];
+    for y in 0..image.height() as isize {
+        for x in 0..image.width() as isize {
+            let i = pixel_idx(image, x, y).expect("pixel in image");
+            let neighbours = offsets
+                .iter()
+                .map(|(dx, dy)| pixel_idx(image, x + dx, y + dy))
+                .collect();
+            if is_edge(i, neighbours) {
+                output.push(i);
+            }
+        }
+    }
+    output
+}
+
+fn pixel_idx(image: &IndexedImage, x: isize, y: isize) -> Option<usize> {
+    if x < 0 || y < 0 || x >= image.width() as isize || y >= image.height() as isize {
+        None
+    } else {
+        Some(x as usize + y as usize * image.width() as usize)
+    }
+}
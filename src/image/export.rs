@@ -0,0 +1,297 @@
+use crate::image::animation::{frame_order, is_looping};
+use crate::image::trim::get_transparent_colors;
+use color_eyre::Result;
+use gif::{DisposalMethod, Encoder, Frame, Repeat};
+use image_lib::{save_buffer_with_format, ExtendedColorType, ImageFormat};
+use pixels_graphics_lib::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes `frames` as an animated GIF to `path`, see [write_gif]
+pub fn export_gif(
+    path: &Path,
+    frames: &[IndexedImage],
+    durations: &[u32],
+    play_type: PlayType,
+) -> Result<()> {
+    write_gif(File::create(path)?, frames, durations, play_type)
+}
+
+/// Writes `frames` as an animated GIF
+///
+/// The palette is used as is and every transparent color is replaced with the first one as GIFs
+/// only support a single transparent color (partially transparent colors become opaque)
+///
+/// Reversed and ping pong animations are stored as the frames in play order, and animations
+/// that don't loop are only played once
+pub fn write_gif<W: Write>(
+    writer: W,
+    frames: &[IndexedImage],
+    durations: &[u32],
+    play_type: PlayType,
+) -> Result<()> {
+    let first = &frames[0];
+    let palette: Vec<u8> = first
+        .get_palette()
+        .iter()
+        .flat_map(|c| [c.r, c.g, c.b])
+        .collect();
+    let trans_idxs = get_transparent_colors(first);
+    let transparent = trans_idxs.first().copied();
+    let mut encoder = Encoder::new(
+        writer,
+        first.width() as u16,
+        first.height() as u16,
+        &palette,
+    )?;
+    if is_looping(play_type) {
+        encoder.set_repeat(Repeat::Infinite)?;
+    }
+    for idx in frame_order(play_type, frames.len()) {
+        let pixels: Vec<u8> = frames[idx]
+            .get_pixels()
+            .iter()
+            .map(|px| match transparent {
+                Some(trans) if trans_idxs.contains(px) => trans,
+                _ => *px,
+            })
+            .collect();
+        let mut frame = Frame::from_indexed_pixels(
+            first.width() as u16,
+            first.height() as u16,
+            pixels,
+            transparent,
+        );
+        //delays are in 10ms
+        frame.delay = durations[idx].div_ceil(10).min(u16::MAX as u32) as u16;
+        frame.dispose = DisposalMethod::Background;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// Frame rects and durations for a sprite sheet, in the Aseprite/TexturePacker JSON array layout
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SheetData {
+    pub frames: Vec<SheetFrame>,
+    pub meta: SheetMeta,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetFrame {
+    pub filename: String,
+    pub frame: SheetRect,
+    pub rotated: bool,
+    pub trimmed: bool,
+    pub sprite_source_size: SheetRect,
+    pub source_size: SheetSize,
+    /// Milliseconds
+    pub duration: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SheetMeta {
+    pub app: String,
+    pub version: String,
+    pub image: String,
+    pub format: String,
+    pub size: SheetSize,
+    pub scale: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct SheetRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct SheetSize {
+    pub w: usize,
+    pub h: usize,
+}
+
+/// Writes `frames` to a PNG at `path` and the frame data as JSON next to it (with the extension
+/// changed to `json`), see [sprite_sheet]
+pub fn export_sprite_sheet(
+    path: &Path,
+    frames: &[IndexedImage],
+    durations: &[u32],
+    columns: usize,
+    padding: usize,
+) -> Result<()> {
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let image = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (pixels, data) = sprite_sheet(frames, durations, columns, padding, &name, &image);
+    save_buffer_with_format(
+        path,
+        &pixels,
+        data.meta.size.w as u32,
+        data.meta.size.h as u32,
+        ExtendedColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    fs::write(
+        path.with_extension("json"),
+        serde_json::to_string_pretty(&data)?,
+    )?;
+    Ok(())
+}
+
+/// Packs `frames` into one RGBA image, returning the pixels and where each frame is
+///
+/// Frames are placed left to right, top to bottom with `padding` pixels between them,
+/// a `columns` of 0 puts every frame on one row
+pub fn sprite_sheet(
+    frames: &[IndexedImage],
+    durations: &[u32],
+    columns: usize,
+    padding: usize,
+    name: &str,
+    image: &str,
+) -> (Vec<u8>, SheetData) {
+    let frame_w = frames[0].width() as usize;
+    let frame_h = frames[0].height() as usize;
+    let columns = if columns == 0 {
+        frames.len()
+    } else {
+        columns.min(frames.len())
+    };
+    let rows = frames.len().div_ceil(columns);
+    let width = columns * frame_w + (columns - 1) * padding;
+    let height = rows * frame_h + (rows - 1) * padding;
+    let mut pixels = vec![0; width * height * 4];
+    let mut sheet_frames = vec![];
+    for (i, frame) in frames.iter().enumerate() {
+        let left = (i % columns) * (frame_w + padding);
+        let top = (i / columns) * (frame_h + padding);
+        for (px_idx, color_idx) in frame.get_pixels().iter().enumerate() {
+            let color = frame.get_color(*color_idx).unwrap_or(TRANSPARENT);
+            let x = left + px_idx % frame_w;
+            let y = top + px_idx / frame_w;
+            let i = (x + y * width) * 4;
+            pixels[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        sheet_frames.push(SheetFrame {
+            filename: format!("{name} {i}"),
+            frame: SheetRect {
+                x: left,
+                y: top,
+                w: frame_w,
+                h: frame_h,
+            },
+            rotated: false,
+            trimmed: false,
+            sprite_source_size: SheetRect {
+                x: 0,
+                y: 0,
+                w: frame_w,
+                h: frame_h,
+            },
+            source_size: SheetSize {
+                w: frame_w,
+                h: frame_h,
+            },
+            duration: durations[i],
+        });
+    }
+    let data = SheetData {
+        frames: sheet_frames,
+        meta: SheetMeta {
+            app: env!("CARGO_PKG_HOMEPAGE").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            image: image.to_string(),
+            format: "RGBA8888".to_string(),
+            size: SheetSize {
+                w: width,
+                h: height,
+            },
+            scale: "1".to_string(),
+        },
+    };
+    (pixels, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gif_export() {
+        let palette = vec![TRANSPARENT, BLUE, Color::new(255, 0, 0, 0)];
+        let frames = vec![
+            IndexedImage::new(2, 1, palette.clone(), vec![1, 0]).unwrap(),
+            IndexedImage::new(2, 1, palette.clone(), vec![2, 1]).unwrap(),
+            IndexedImage::new(2, 1, palette, vec![1, 1]).unwrap(),
+        ];
+        let mut bytes = vec![];
+        write_gif(&mut bytes, &frames, &[100, 250, 55], PlayType::LoopsBoth).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+        assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+        assert_eq!(
+            &decoder.global_palette().unwrap()[..6],
+            &[0, 0, 0, 0, 0, 255]
+        );
+        let mut output = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.transparent, Some(0));
+            output.push((frame.delay, frame.buffer.to_vec()));
+        }
+        assert_eq!(
+            output,
+            vec![
+                (10, vec![1, 0]),
+                (25, vec![0, 1]),
+                (6, vec![1, 1]),
+                (25, vec![0, 1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sprite_sheet_layout() {
+        let palette = vec![TRANSPARENT, BLUE];
+        let frames = vec![
+            IndexedImage::new(2, 1, palette.clone(), vec![1, 0]).unwrap(),
+            IndexedImage::new(2, 1, palette.clone(), vec![0, 1]).unwrap(),
+            IndexedImage::new(2, 1, palette, vec![1, 1]).unwrap(),
+        ];
+        let rects = |columns: usize, padding: usize| {
+            let (_, data) = sprite_sheet(&frames, &[10, 20, 30], columns, padding, "a", "a.png");
+            let size = (data.meta.size.w, data.meta.size.h);
+            let rects = data
+                .frames
+                .iter()
+                .map(|frame| (frame.frame.x, frame.frame.y))
+                .collect::<Vec<_>>();
+            (size, rects)
+        };
+        assert_eq!(rects(0, 0), ((6, 1), vec![(0, 0), (2, 0), (4, 0)]));
+        assert_eq!(rects(1, 1), ((2, 5), vec![(0, 0), (0, 2), (0, 4)]));
+        assert_eq!(rects(2, 1), ((5, 3), vec![(0, 0), (3, 0), (0, 2)]));
+
+        let (pixels, data) = sprite_sheet(&frames, &[10, 20, 30], 2, 1, "a", "a.png");
+        assert_eq!(data.frames[1].duration, 20);
+        assert_eq!(data.frames[2].filename, "a 2");
+        let alpha: Vec<u8> = pixels.chunks_exact(4).map(|px| px[3]).collect();
+        assert_eq!(
+            alpha,
+            vec![255, 0, 0, 0, 255, 0, 0, 0, 0, 0, 255, 255, 0, 0, 0]
+        );
+    }
+}
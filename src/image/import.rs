@@ -0,0 +1,368 @@
+use crate::ui::edit_history::DEFAULT_FRAME_DURATION;
+use crate::ui::selection::nearest_color;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use image_lib::codecs::gif::GifDecoder;
+use image_lib::codecs::png::PngDecoder;
+use image_lib::{AnimationDecoder, Frames};
+use pixels_graphics_lib::prelude::palette::simplify_palette_to_fit;
+use pixels_graphics_lib::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// How a sprite sheet is divided into cells
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SheetCells {
+    /// Width and height of each cell in pixels
+    Size(usize, usize),
+    /// Number of columns and rows, the cell size is calculated from the sheet size
+    Count(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SheetSlicing {
+    pub cells: SheetCells,
+    /// Position of the top left cell
+    pub offset: (usize, usize),
+    /// Gap between cells horizontally and vertically
+    pub spacing: (usize, usize),
+}
+
+/// An image being imported as a sprite sheet, this can be any size
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpriteSheet {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+}
+
+impl SpriteSheet {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let image = image_lib::open(path)?.to_rgba8();
+        let pixels = image
+            .pixels()
+            .map(|px| Color::new(px[0], px[1], px[2], px[3]))
+            .collect();
+        Ok(Self::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+
+    /// Cell size, columns and rows for `slicing`
+    fn grid(&self, slicing: &SheetSlicing) -> Result<((usize, usize), (usize, usize))> {
+        let (offset_x, offset_y) = slicing.offset;
+        let (spacing_x, spacing_y) = slicing.spacing;
+        if offset_x >= self.width || offset_y >= self.height {
+            return Err(eyre!("Offset is outside of the sheet"));
+        }
+        let available_w = self.width - offset_x;
+        let available_h = self.height - offset_y;
+        let (cell_w, cell_h, columns, rows) = match slicing.cells {
+            SheetCells::Size(w, h) => {
+                if w == 0 || h == 0 {
+                    return Err(eyre!("Cell size must be at least 1x1"));
+                }
+                let columns = (available_w + spacing_x) / (w + spacing_x);
+                let rows = (available_h + spacing_y) / (h + spacing_y);
+                (w, h, columns, rows)
+            }
+            SheetCells::Count(columns, rows) => {
+                if columns == 0 || rows == 0 {
+                    return Err(eyre!("Sheet must have at least one column and row"));
+                }
+                let cell_w = available_w.saturating_sub(spacing_x * (columns - 1)) / columns;
+                let cell_h = available_h.saturating_sub(spacing_y * (rows - 1)) / rows;
+                (cell_w, cell_h, columns, rows)
+            }
+        };
+        if cell_w == 0 || cell_h == 0 || columns == 0 || rows == 0 {
+            return Err(eyre!("Cells don't fit in the sheet"));
+        }
+        if cell_w > 255 || cell_h > 255 {
+            return Err(eyre!("Cell size must be at most 255x255"));
+        }
+        Ok(((cell_w, cell_h), (columns, rows)))
+    }
+
+    /// Splits the sheet into cells (left to right, top to bottom) sharing one palette
+    ///
+    /// Empty cells at the end are dropped as partially filled sheets are common
+    pub fn slice(&self, slicing: &SheetSlicing) -> Result<Vec<IndexedImage>> {
+        let ((cell_w, cell_h), (columns, rows)) = self.grid(slicing)?;
+        let mut cells: Vec<Vec<Color>> = vec![];
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = slicing.offset.0 + column * (cell_w + slicing.spacing.0);
+                let y = slicing.offset.1 + row * (cell_h + slicing.spacing.1);
+                let cell = (y..y + cell_h)
+                    .flat_map(|y| {
+                        let start = y * self.width + x;
+                        self.pixels[start..start + cell_w].iter().copied()
+                    })
+                    .collect();
+                cells.push(cell);
+            }
+        }
+        while cells.len() > 1
+            && cells
+                .last()
+                .map(|cell| cell.iter().all(|c| c.is_transparent()))
+                .unwrap_or_default()
+        {
+            cells.pop();
+        }
+
+        shared_palette(cell_w, cell_h, cells)
+    }
+}
+
+/// Frames and durations (in milliseconds) of an animated GIF or PNG, `None` if the file is any
+/// other format or a PNG that isn't animated
+pub fn open_animation(path: &Path) -> Result<Option<(Vec<IndexedImage>, Vec<u32>)>> {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let reader = BufReader::new(File::open(path)?);
+    match ext.as_str() {
+        "gif" => Ok(Some(decode_animation(
+            GifDecoder::new(reader)?.into_frames(),
+        )?)),
+        "png" | "apng" => {
+            let decoder = PngDecoder::new(reader)?;
+            if decoder.is_apng()? {
+                Ok(Some(decode_animation(decoder.apng()?.into_frames())?))
+            } else {
+                Ok(None)
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Converts every frame to use one palette, frames without a delay are given the default duration
+pub fn decode_animation(frames: Frames) -> Result<(Vec<IndexedImage>, Vec<u32>)> {
+    let frames = frames.collect_frames()?;
+    let Some(first) = frames.first() else {
+        return Err(eyre!("Image has no frames"));
+    };
+    let (width, height) = first.buffer().dimensions();
+    if width > 255 || height > 255 {
+        return Err(eyre!("Image is too big (max 255x255)"));
+    }
+    let durations = frames
+        .iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            match numer / denom.max(1) {
+                0 => DEFAULT_FRAME_DURATION,
+                millis => millis,
+            }
+        })
+        .collect();
+    let pixels = frames
+        .into_iter()
+        .map(|frame| {
+            frame
+                .into_buffer()
+                .pixels()
+                .map(|px| Color::new(px[0], px[1], px[2], px[3]))
+                .collect()
+        })
+        .collect();
+    Ok((
+        shared_palette(width as usize, height as usize, pixels)?,
+        durations,
+    ))
+}
+
+/// Creates an image for each of `frames` (which must all be `width`x`height`) with one palette
+/// containing every color, if there are more than 255 colors the palette is simplified to fit
+fn shared_palette(
+    width: usize,
+    height: usize,
+    frames: Vec<Vec<Color>>,
+) -> Result<Vec<IndexedImage>> {
+    let mut seen = HashSet::new();
+    let mut colors = vec![];
+    for color in frames.iter().flatten() {
+        if seen.insert(*color) {
+            colors.push(*color);
+        }
+    }
+    let palette = if colors.len() > 255 {
+        //simplifying keeps merged colors as duplicates
+        let mut seen = HashSet::new();
+        simplify_palette_to_fit(&colors, 255)
+            .into_iter()
+            .filter(|color| seen.insert(*color))
+            .collect()
+    } else {
+        colors.clone()
+    };
+    let mapping: HashMap<Color, u8> = colors
+        .into_iter()
+        .map(|color| (color, nearest_color(&palette, color)))
+        .collect();
+
+    frames
+        .into_iter()
+        .map(|frame| {
+            let pixels = frame.iter().map(|color| mapping[color]).collect();
+            Ok(IndexedImage::new(
+                width as u8,
+                height as u8,
+                palette.clone(),
+                pixels,
+            )?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::image::export::write_gif;
+    use std::io::Cursor;
+
+    #[test]
+    fn sprite_sheet_import() {
+        let (t, r, b) = (TRANSPARENT, RED, BLUE);
+        #[rustfmt::skip]
+        let sheet = SpriteSheet::new(10, 2, vec![
+            t, r, r, t, b, t, t, t, t, t,
+            t, r, b, t, b, b, t, t, t, t,
+        ]);
+        let slicing = |cells: SheetCells| SheetSlicing {
+            cells,
+            offset: (1, 0),
+            spacing: (1, 0),
+        };
+        let by_size = sheet.slice(&slicing(SheetCells::Size(2, 2))).unwrap();
+        let by_count = sheet.slice(&slicing(SheetCells::Count(3, 1))).unwrap();
+        assert_eq!(by_size, by_count);
+        //the empty last cell is dropped and every frame shares a palette
+        assert_eq!(by_size.len(), 2);
+        assert_eq!(by_size[0].get_palette(), &[r, b, t]);
+        assert_eq!(by_size[0].get_pixels(), &[0, 0, 0, 1]);
+        assert_eq!(by_size[1].get_pixels(), &[1, 2, 1, 1]);
+    }
+
+    #[test]
+    fn sprite_sheet_grid_errors() {
+        let sheet = SpriteSheet::new(10, 2, vec![RED; 20]);
+        let slicing = |cells: SheetCells, offset: (usize, usize)| SheetSlicing {
+            cells,
+            offset,
+            spacing: (0, 0),
+        };
+        let error = |slicing: SheetSlicing| sheet.slice(&slicing).unwrap_err().to_string();
+        assert_eq!(
+            error(slicing(SheetCells::Size(2, 2), (10, 0))),
+            "Offset is outside of the sheet"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Size(2, 2), (0, 2))),
+            "Offset is outside of the sheet"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Size(0, 2), (0, 0))),
+            "Cell size must be at least 1x1"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Size(2, 0), (0, 0))),
+            "Cell size must be at least 1x1"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Count(0, 1), (0, 0))),
+            "Sheet must have at least one column and row"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Count(11, 1), (0, 0))),
+            "Cells don't fit in the sheet"
+        );
+        assert_eq!(
+            error(slicing(SheetCells::Size(11, 2), (0, 0))),
+            "Cells don't fit in the sheet"
+        );
+
+        let wide = SpriteSheet::new(300, 1, vec![RED; 300]);
+        assert_eq!(
+            wide.slice(&slicing(SheetCells::Size(256, 1), (0, 0)))
+                .unwrap_err()
+                .to_string(),
+            "Cell size must be at most 255x255"
+        );
+        assert_eq!(
+            wide.slice(&slicing(SheetCells::Count(1, 1), (0, 0)))
+                .unwrap_err()
+                .to_string(),
+            "Cell size must be at most 255x255"
+        );
+        assert_eq!(
+            wide.slice(&slicing(SheetCells::Size(255, 1), (0, 0)))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn animated_gif_import() {
+        let palette = vec![TRANSPARENT, RED, BLUE];
+        let frames = vec![
+            IndexedImage::new(2, 1, palette.clone(), vec![1, 2]).unwrap(),
+            IndexedImage::new(2, 1, palette, vec![0, 1]).unwrap(),
+        ];
+        let mut bytes = vec![];
+        write_gif(&mut bytes, &frames, &[100, 250], PlayType::Loops).unwrap();
+
+        let decoder = GifDecoder::new(Cursor::new(bytes)).unwrap();
+        let (imported, durations) = decode_animation(decoder.into_frames()).unwrap();
+        assert_eq!(durations, vec![100, 250]);
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].get_palette(), imported[1].get_palette());
+        let colors = |image: &IndexedImage| {
+            image
+                .get_pixels()
+                .iter()
+                .map(|idx| image.get_palette()[*idx as usize])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(colors(&imported[0]), vec![RED, BLUE]);
+        assert_eq!(colors(&imported[1]), vec![TRANSPARENT, RED]);
+    }
+
+    #[test]
+    fn shared_palette_simplified() {
+        //300 colors spread over two frames
+        let colors: Vec<Color> = (0..300)
+            .map(|i| Color::new((i % 256) as u8, (i / 256) as u8 * 128, 64, 255))
+            .collect();
+        let frames = vec![colors[..150].to_vec(), colors[150..].to_vec()];
+        let images = shared_palette(150, 1, frames).unwrap();
+        assert_eq!(images.len(), 2);
+        //merged colors are only in the palette once
+        let palette = images[0].get_palette();
+        assert!(palette.len() <= 255);
+        assert_eq!(palette.iter().collect::<HashSet<_>>().len(), palette.len());
+        assert_eq!(images[1].get_palette(), palette);
+        for (image, colors) in images.iter().zip(colors.chunks(150)) {
+            for (idx, color) in image.get_pixels().iter().zip(colors) {
+                assert_eq!(*idx, nearest_color(palette, *color));
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::image::trim::get_transparent_colors;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use pixels_graphics_lib::prelude::IndexedImage;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+}
+
+impl Layer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+        }
+    }
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Layer::new("Layer 1")
+    }
+}
+
+/// Layers and their pixels for every frame, saved next to the ICI/ICA file as these formats
+/// only support a single layer
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LayerFile {
+    pub width: u8,
+    pub height: u8,
+    pub layers: Vec<Layer>,
+    /// Pixels for each layer of each frame, ordered by frame then layer
+    pub cels: Vec<Vec<u8>>,
+}
+
+/// Path of the layer file for the image at `path`
+pub fn layer_file_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".layers");
+    PathBuf::from(path)
+}
+
+/// Writes the layers for the image at `path`, or deletes any existing layer file if there's
+/// only one layer as the image itself contains everything
+pub fn save_layers(path: &Path, cels: &[IndexedImage], layers: &[Layer]) -> Result<()> {
+    let layer_path = layer_file_path(path);
+    if layers.len() < 2 {
+        if layer_path.exists() {
+            fs::remove_file(layer_path)?;
+        }
+        return Ok(());
+    }
+    let file = LayerFile {
+        width: cels[0].width(),
+        height: cels[0].height(),
+        layers: layers.to_vec(),
+        cels: cels.iter().map(|cel| cel.get_pixels().to_vec()).collect(),
+    };
+    fs::write(layer_path, ron::to_string(&file)?)?;
+    Ok(())
+}
+
+/// Reads the layers for the image at `path`
+///
+/// Returns None if there's no layer file, and an error if it doesn't match `frames`
+/// (for example if the image was edited without the layer file)
+pub fn load_layers(
+    path: &Path,
+    frames: &[IndexedImage],
+) -> Result<Option<(Vec<IndexedImage>, Vec<Layer>)>> {
+    let layer_path = layer_file_path(path);
+    if !layer_path.exists() {
+        return Ok(None);
+    }
+    let file: LayerFile = ron::from_str(&fs::read_to_string(layer_path)?)?;
+    let frame = &frames[0];
+    if file.width != frame.width()
+        || file.height != frame.height()
+        || file.layers.is_empty()
+        || file.cels.len() != frames.len() * file.layers.len()
+    {
+        return Err(eyre!("Layer file doesn't match image size or frame count"));
+    }
+    let cels = file
+        .cels
+        .into_iter()
+        .map(|pixels| {
+            IndexedImage::new(
+                frame.width(),
+                frame.height(),
+                frame.get_palette().to_vec(),
+                pixels,
+            )
+        })
+        .collect::<Result<Vec<IndexedImage>, _>>()?;
+    for (frame, cels) in frames.iter().zip(cels.chunks(file.layers.len())) {
+        if flatten(cels, &file.layers).get_pixels() != frame.get_pixels() {
+            return Err(eyre!("Layer file doesn't match image content"));
+        }
+    }
+    Ok(Some((cels, file.layers)))
+}
+
+/// Combines the visible layers of a frame into one image, `cels` are ordered bottom to top
+///
+/// Transparent pixels show the layer below, if no layers are visible the image is transparent
+pub fn flatten(cels: &[IndexedImage], layers: &[Layer]) -> IndexedImage {
+    let mut visible = cels.iter().zip(layers).filter(|(_, layer)| layer.visible);
+    let Some((bottom, _)) = visible.next() else {
+        let trans = get_transparent_colors(&cels[0])
+            .first()
+            .copied()
+            .unwrap_or(0);
+        return with_pixels(
+            &cels[0],
+            vec![trans; cels[0].width() as usize * cels[0].height() as usize],
+        );
+    };
+    let mut pixels = bottom.get_pixels().to_vec();
+    for (cel, _) in visible {
+        overlay(&mut pixels, cel);
+    }
+    with_pixels(bottom, pixels)
+}
+
+/// Places the non transparent pixels of `top` over `pixels`
+pub fn overlay(pixels: &mut [u8], top: &IndexedImage) {
+    let trans_idxs = get_transparent_colors(top);
+    for (px, idx) in pixels.iter_mut().zip(top.get_pixels()) {
+        if !trans_idxs.contains(idx) {
+            *px = *idx;
+        }
+    }
+}
+
+fn with_pixels(image: &IndexedImage, pixels: Vec<u8>) -> IndexedImage {
+    IndexedImage::new(
+        image.width(),
+        image.height(),
+        image.get_palette().to_vec(),
+        pixels,
+    )
+    .expect("Creating flattened image")
+}
@@ -1,8 +1,17 @@
 use color_eyre::Result;
 use pixels_graphics_lib::prelude::IndexedImage;
 
+pub mod animation;
+pub mod effects;
+pub mod export;
+pub mod import;
+pub mod layers;
 pub mod resize;
+pub mod thumbnail;
+pub mod tilemap;
+pub mod timing;
 pub mod trim;
+pub mod tween;
 
 fn remove_first_row(image: IndexedImage) -> Result<IndexedImage> {
     let new_pixels = &image.get_pixels()[image.width() as usize..image.get_pixels().len()];
@@ -0,0 +1,30 @@
+use pixels_graphics_lib::prelude::IndexedImage;
+
+/// Shrinks `image` (using nearest neighbour) so it fits in `max_width`x`max_height`, keeping the aspect ratio
+///
+/// Images that already fit are returned unchanged
+pub fn scale_to_fit(image: &IndexedImage, max_width: usize, max_height: usize) -> IndexedImage {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    if (width <= max_width && height <= max_height) || max_width == 0 || max_height == 0 {
+        return image.clone();
+    }
+    let scale = (max_width as f32 / width as f32).min(max_height as f32 / height as f32);
+    let new_width = ((width as f32 * scale).floor() as usize).max(1);
+    let new_height = ((height as f32 * scale).floor() as usize).max(1);
+    let mut pixels = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        let src_y = y * height / new_height;
+        for x in 0..new_width {
+            let src_x = x * width / new_width;
+            pixels.push(image.get_pixels()[src_x + src_y * width]);
+        }
+    }
+    IndexedImage::new(
+        new_width as u8,
+        new_height as u8,
+        image.get_palette().to_vec(),
+        pixels,
+    )
+    .expect("scaled image is smaller than original")
+}
@@ -0,0 +1,506 @@
+use crate::image::trim::get_transparent_colors;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use pixels_graphics_lib::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Map cells are stored as u8 with 0 meaning empty, so only this many tiles can be used
+pub const MAX_TILES: usize = 255;
+
+/// Grid of tile indexes, saved as RON
+///
+/// The tiles come from an ICI (split into tiles left to right, top to bottom)
+/// or an ICA (each frame is a tile)
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Tilemap {
+    /// Relative to the tilemap file if the tileset is in the same directory
+    pub tileset: PathBuf,
+    pub tile_width: u8,
+    pub tile_height: u8,
+    /// Size in tiles
+    pub width: u8,
+    pub height: u8,
+    /// Tile for each cell, 0 is empty otherwise it's the tile index + 1
+    pub cells: Vec<u8>,
+}
+
+impl Tilemap {
+    pub fn new(tileset: PathBuf, tile_size: (u8, u8), (width, height): (u8, u8)) -> Self {
+        Self {
+            tileset,
+            tile_width: tile_size.0,
+            tile_height: tile_size.1,
+            width,
+            height,
+            cells: vec![0; width as usize * height as usize],
+        }
+    }
+
+    /// Reads the map at `path`, the tileset path is made absolute
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut map: Tilemap = ron::from_str(&fs::read_to_string(path)?)?;
+        if map.width == 0
+            || map.height == 0
+            || map.cells.len() != map.width as usize * map.height as usize
+        {
+            return Err(eyre!("Tilemap size doesn't match cell count"));
+        }
+        if map.tileset.is_relative() {
+            if let Some(dir) = path.parent() {
+                map.tileset = dir.join(&map.tileset);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Writes the map to `path`, the tileset path is made relative if possible
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut map = self.clone();
+        if let Some(relative) = path
+            .parent()
+            .and_then(|dir| map.tileset.strip_prefix(dir).ok())
+        {
+            map.tileset = relative.to_path_buf();
+        }
+        let config = PrettyConfig::default().compact_arrays(true);
+        fs::write(path, ron::ser::to_string_pretty(&map, config)?)?;
+        Ok(())
+    }
+
+    /// Loads the tiles for this map, failing if they aren't the size the map was made with
+    pub fn open_tileset(&self) -> Result<Tileset> {
+        let tileset = Tileset::open(&self.tileset, (self.tile_width, self.tile_height))?;
+        let tile = &tileset.tiles[0];
+        if tile.width() != self.tile_width || tile.height() != self.tile_height {
+            return Err(eyre!(
+                "Tileset frames are {}x{} but tilemap uses {}x{}",
+                tile.width(),
+                tile.height(),
+                self.tile_width,
+                self.tile_height
+            ));
+        }
+        Ok(tileset)
+    }
+
+    /// The cells as an image with a color per tile, so it can be edited like any other image
+    ///
+    /// Cells using tiles that no longer exist are emptied
+    pub fn as_image(&self, tile_count: usize) -> Result<IndexedImage> {
+        let palette = map_palette(tile_count);
+        let cells = self
+            .cells
+            .iter()
+            .map(|cell| {
+                if (*cell as usize) < palette.len() {
+                    *cell
+                } else {
+                    0
+                }
+            })
+            .collect();
+        Ok(IndexedImage::new(self.width, self.height, palette, cells)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TilesetSource {
+    /// Tile sheet, kept so pixels outside of the tiles are saved unchanged
+    Image(IndexedImage),
+    Animation {
+        per_frame: f64,
+        play_type: PlayType,
+    },
+}
+
+/// Tiles loaded from an ICI or ICA file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    pub tiles: Vec<IndexedImage>,
+    pub palette: FilePalette,
+    source: TilesetSource,
+}
+
+impl Tileset {
+    /// Loads the tiles from `path`, `tile_size` is ignored for ICA files as each frame is a tile
+    ///
+    /// Only the first [MAX_TILES] tiles are used
+    pub fn open(path: &Path, (tile_width, tile_height): (u8, u8)) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        if path.extension().map(|ext| ext == "ica").unwrap_or_default() {
+            let (image, palette) = AnimatedIndexedImage::from_file_contents(&bytes)?;
+            let mut tiles = image.as_images();
+            if tiles.is_empty() {
+                return Err(eyre!("Tileset has no frames"));
+            }
+            tiles.truncate(MAX_TILES);
+            return Ok(Self {
+                tiles,
+                palette,
+                source: TilesetSource::Animation {
+                    per_frame: image.get_per_frame(),
+                    play_type: image.play_type(),
+                },
+            });
+        }
+        let (image, palette) = IndexedImage::from_file_contents(&bytes)?;
+        if tile_width == 0 || tile_height == 0 {
+            return Err(eyre!("Tile size must be at least 1x1"));
+        }
+        let cols = image.width() / tile_width;
+        let rows = image.height() / tile_height;
+        if cols == 0 || rows == 0 {
+            return Err(eyre!(
+                "Tileset ({}x{}) is smaller than a tile",
+                image.width(),
+                image.height()
+            ));
+        }
+        let mut tiles = vec![];
+        for row in 0..rows {
+            for col in 0..cols {
+                let mut pixels = Vec::with_capacity(tile_width as usize * tile_height as usize);
+                for y in 0..tile_height {
+                    let start = image.get_pixel_index(col * tile_width, row * tile_height + y)?;
+                    pixels
+                        .extend_from_slice(&image.get_pixels()[start..start + tile_width as usize]);
+                }
+                tiles.push(IndexedImage::new(
+                    tile_width,
+                    tile_height,
+                    image.get_palette().to_vec(),
+                    pixels,
+                )?);
+            }
+        }
+        tiles.truncate(MAX_TILES);
+        Ok(Self {
+            tiles,
+            palette,
+            source: TilesetSource::Image(image),
+        })
+    }
+
+    /// Writes `tiles` back to `path` in the same format it was loaded from
+    pub fn save(&self, path: &Path, tiles: &[IndexedImage]) -> Result<()> {
+        if tiles.is_empty() {
+            return Err(eyre!("Tileset has no tiles"));
+        }
+        let bytes = match &self.source {
+            TilesetSource::Image(sheet) => {
+                let tile_width = tiles[0].width();
+                let tile_height = tiles[0].height();
+                let cols = sheet.width() / tile_width;
+                let mut pixels = sheet.get_pixels().to_vec();
+                for (i, tile) in tiles.iter().enumerate() {
+                    let x = (i % cols as usize) as u8 * tile_width;
+                    let y = (i / cols as usize) as u8 * tile_height;
+                    for row in 0..tile_height {
+                        let start = sheet.get_pixel_index(x, y + row)?;
+                        let tile_start = row as usize * tile_width as usize;
+                        pixels[start..start + tile_width as usize].copy_from_slice(
+                            &tile.get_pixels()[tile_start..tile_start + tile_width as usize],
+                        );
+                    }
+                }
+                IndexedImage::new(
+                    sheet.width(),
+                    sheet.height(),
+                    tiles[0].get_palette().to_vec(),
+                    pixels,
+                )?
+                .to_file_contents(&self.palette)?
+            }
+            TilesetSource::Animation {
+                per_frame,
+                play_type,
+            } => AnimatedIndexedImage::new(
+                tiles[0].width(),
+                tiles[0].height(),
+                *per_frame,
+                tiles.len() as u8,
+                tiles[0].get_palette().to_vec(),
+                tiles.iter().flat_map(|t| t.get_pixels().to_vec()).collect(),
+                *play_type,
+            )?
+            .to_file_contents(&self.palette)?,
+        };
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+/// Palette for the map image, the colors are only used for the cursor
+///
+/// Index 0 (empty) is transparent and each tile has a different opaque color
+pub fn map_palette(tile_count: usize) -> Vec<Color> {
+    let mut colors = vec![TRANSPARENT];
+    for i in 0..tile_count.min(MAX_TILES) {
+        let hue = (i * 47 % 360) as u16;
+        colors.push(hue_color(hue));
+    }
+    colors
+}
+
+fn hue_color(hue: u16) -> Color {
+    let x = ((60 - (hue % 120).abs_diff(60)) * 255 / 60) as u8;
+    match hue / 60 {
+        0 => Color::new(255, x, 0, 255),
+        1 => Color::new(x, 255, 0, 255),
+        2 => Color::new(0, 255, x, 255),
+        3 => Color::new(0, x, 255, 255),
+        4 => Color::new(x, 0, 255, 255),
+        _ => Color::new(255, 0, x, 255),
+    }
+}
+
+/// Draws every cell of `map` using `tiles`, returning the size and palette indexes
+///
+/// Empty cells are transparent (or the first color if the palette has no transparent colors)
+pub fn flatten_pixels(map: &IndexedImage, tiles: &[IndexedImage]) -> (usize, usize, Vec<u8>) {
+    let tile_width = tiles[0].width() as usize;
+    let tile_height = tiles[0].height() as usize;
+    let width = map.width() as usize * tile_width;
+    let height = map.height() as usize * tile_height;
+    let trans = get_transparent_colors(&tiles[0])
+        .first()
+        .copied()
+        .unwrap_or(0);
+    let mut pixels = vec![trans; width * height];
+    for (i, cell) in map.get_pixels().iter().enumerate() {
+        let Some(tile) = (*cell as usize).checked_sub(1).and_then(|t| tiles.get(t)) else {
+            continue;
+        };
+        let x = (i % map.width() as usize) * tile_width;
+        let y = (i / map.width() as usize) * tile_height;
+        for row in 0..tile_height {
+            let start = x + (y + row) * width;
+            pixels[start..start + tile_width]
+                .copy_from_slice(&tile.get_pixels()[row * tile_width..(row + 1) * tile_width]);
+        }
+    }
+    (width, height, pixels)
+}
+
+/// The map drawn with its tiles as a single image, fails if it's bigger than 255x255
+pub fn flatten(map: &IndexedImage, tiles: &[IndexedImage]) -> Result<IndexedImage> {
+    let (width, height, pixels) = flatten_pixels(map, tiles);
+    if width > 255 || height > 255 {
+        return Err(eyre!(
+            "Tilemap is too big to flatten ({width}x{height}, max 255x255)"
+        ));
+    }
+    Ok(IndexedImage::new(
+        width as u8,
+        height as u8,
+        tiles[0].get_palette().to_vec(),
+        pixels,
+    )?)
+}
+
+/// The map drawn with its tiles, shrunk (using nearest neighbour) to fit in `max_width`x`max_height`
+pub fn flatten_to_fit(
+    map: &IndexedImage,
+    tiles: &[IndexedImage],
+    max_width: usize,
+    max_height: usize,
+) -> IndexedImage {
+    let (width, height, pixels) = flatten_pixels(map, tiles);
+    let scale = (max_width as f32 / width as f32)
+        .min(max_height as f32 / height as f32)
+        .min(1.0);
+    let new_width = ((width as f32 * scale).floor() as usize).clamp(1, 255);
+    let new_height = ((height as f32 * scale).floor() as usize).clamp(1, 255);
+    let mut scaled = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        let src_y = y * height / new_height;
+        for x in 0..new_width {
+            let src_x = x * width / new_width;
+            scaled.push(pixels[src_x + src_y * width]);
+        }
+    }
+    IndexedImage::new(
+        new_width as u8,
+        new_height as u8,
+        tiles[0].get_palette().to_vec(),
+        scaled,
+    )
+    .expect("Creating tilemap preview")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Empty directory in the system temp dir for test files
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "image_editor_tilemap_{name}_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn tile(pixels: Vec<u8>) -> IndexedImage {
+        IndexedImage::new(2, 1, vec![TRANSPARENT, BLUE, RED], pixels).unwrap()
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let dir = test_dir("map");
+        let path = dir.join("map.ron");
+        let mut map = Tilemap::new(dir.join("tiles.ici"), (8, 8), (2, 2));
+        map.cells = vec![0, 1, 2, 0];
+        map.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"tiles.ici\""));
+        assert!(!saved.contains(dir.to_string_lossy().as_ref()));
+        assert_eq!(Tilemap::open(&path).unwrap(), map);
+    }
+
+    #[test]
+    fn map_keeps_tileset_outside_of_dir() {
+        let dir = test_dir("outside");
+        let path = dir.join("map.ron");
+        let tileset = std::env::temp_dir().join("elsewhere").join("tiles.ica");
+        let map = Tilemap::new(tileset.clone(), (8, 8), (1, 1));
+        map.save(&path).unwrap();
+        assert_eq!(Tilemap::open(&path).unwrap().tileset, tileset);
+    }
+
+    #[test]
+    fn map_cell_count_checked() {
+        let dir = test_dir("cells");
+        let path = dir.join("map.ron");
+        let mut map = Tilemap::new(PathBuf::from("tiles.ici"), (8, 8), (2, 2));
+        map.cells.pop();
+        map.save(&path).unwrap();
+        assert!(Tilemap::open(&path).is_err());
+        map.width = 0;
+        map.height = 0;
+        map.cells.clear();
+        map.save(&path).unwrap();
+        assert!(Tilemap::open(&path).is_err());
+    }
+
+    #[test]
+    fn sheet_tileset_round_trip() {
+        let dir = test_dir("sheet");
+        let path = dir.join("tiles.ici");
+        //the last column isn't part of any tile and must be kept
+        #[rustfmt::skip]
+        let sheet = IndexedImage::new(5, 2, vec![TRANSPARENT, BLUE, RED], vec![
+            1, 1, 2, 2, 1,
+            1, 0, 2, 0, 2,
+        ]).unwrap();
+        fs::write(&path, sheet.to_file_contents(&FilePalette::Colors).unwrap()).unwrap();
+        let tileset = Tileset::open(&path, (2, 2)).unwrap();
+        assert_eq!(tileset.tiles.len(), 2);
+        assert_eq!(tileset.tiles[0].get_pixels(), &[1, 1, 1, 0]);
+        assert_eq!(tileset.tiles[1].get_pixels(), &[2, 2, 2, 0]);
+
+        let mut tiles = tileset.tiles.clone();
+        tiles.swap(0, 1);
+        tileset.save(&path, &tiles).unwrap();
+        let (saved, _) = IndexedImage::from_file_contents(&fs::read(&path).unwrap()).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(saved.get_pixels(), &[
+            2, 2, 1, 1, 1,
+            2, 0, 1, 0, 2,
+        ]);
+        assert_eq!(Tileset::open(&path, (2, 2)).unwrap().tiles, tiles);
+    }
+
+    #[test]
+    fn animation_tileset_round_trip() {
+        let dir = test_dir("anim");
+        let path = dir.join("tiles.ica");
+        let anim = AnimatedIndexedImage::new(
+            2,
+            1,
+            0.5,
+            3,
+            vec![TRANSPARENT, BLUE, RED],
+            vec![0, 1, 1, 2, 2, 0],
+            PlayType::Loops,
+        )
+        .unwrap();
+        fs::write(&path, anim.to_file_contents(&FilePalette::Colors).unwrap()).unwrap();
+        let tileset = Tileset::open(&path, (0, 0)).unwrap();
+        assert_eq!(
+            tileset.tiles,
+            vec![tile(vec![0, 1]), tile(vec![1, 2]), tile(vec![2, 0])]
+        );
+
+        let tiles = vec![tile(vec![2, 2]), tile(vec![1, 1])];
+        tileset.save(&path, &tiles).unwrap();
+        let (saved, _) =
+            AnimatedIndexedImage::from_file_contents(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved.get_per_frame(), 0.5);
+        assert_eq!(saved.play_type(), PlayType::Loops);
+        assert_eq!(Tileset::open(&path, (0, 0)).unwrap().tiles, tiles);
+    }
+
+    #[test]
+    fn save_without_tiles() {
+        let dir = test_dir("empty");
+        let path = dir.join("tiles.ica");
+        let tileset = Tileset {
+            tiles: vec![],
+            palette: FilePalette::Colors,
+            source: TilesetSource::Animation {
+                per_frame: 0.5,
+                play_type: PlayType::Loops,
+            },
+        };
+        assert!(tileset.save(&path, &[]).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn image_empties_missing_tiles() {
+        let mut map = Tilemap::new(PathBuf::from("tiles.ici"), (8, 8), (4, 1));
+        map.cells = vec![0, 1, 3, 2];
+        let image = map.as_image(2).unwrap();
+        assert_eq!(image.get_pixels(), &[0, 1, 0, 2]);
+        assert_eq!(image.get_palette().len(), 3);
+    }
+
+    #[test]
+    fn flatten_map() {
+        let tiles = [tile(vec![1, 2]), tile(vec![2, 2])];
+        let map = IndexedImage::new(2, 2, map_palette(2), vec![1, 0, 2, 1]).unwrap();
+        assert_eq!(
+            flatten_pixels(&map, &tiles),
+            (4, 2, vec![1, 2, 0, 0, 2, 2, 1, 2])
+        );
+        let image = flatten(&map, &tiles).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(image.get_pixels(), &[1, 2, 0, 0, 2, 2, 1, 2]);
+        assert_eq!(image.get_palette(), tiles[0].get_palette());
+    }
+
+    #[test]
+    fn flatten_too_big() {
+        let tiles = [tile(vec![1, 2])];
+        let map = IndexedImage::new(128, 1, map_palette(1), vec![1; 128]).unwrap();
+        assert!(flatten(&map, &tiles).is_err());
+        let preview = flatten_to_fit(&map, &tiles, 64, 64);
+        assert_eq!((preview.width(), preview.height()), (64, 1));
+        assert!(preview.get_pixels().iter().all(|px| *px == 1));
+    }
+
+    #[test]
+    fn flatten_to_fit_keeps_small_maps() {
+        let tiles = [tile(vec![1, 2])];
+        let map = IndexedImage::new(2, 1, map_palette(1), vec![0, 1]).unwrap();
+        let preview = flatten_to_fit(&map, &tiles, 64, 64);
+        assert_eq!((preview.width(), preview.height()), (4, 1));
+        assert_eq!(preview.get_pixels(), &[0, 0, 1, 2]);
+    }
+}
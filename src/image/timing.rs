@@ -0,0 +1,40 @@
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Path of the durations file for the image at `path`
+pub fn durations_file_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".durations");
+    PathBuf::from(path)
+}
+
+/// Writes the milliseconds each frame is shown for, or deletes any existing durations file if
+/// every frame has the same duration as the ICA itself stores that
+pub fn save_durations(path: &Path, durations: &[u32]) -> Result<()> {
+    let durations_path = durations_file_path(path);
+    if durations.windows(2).all(|pair| pair[0] == pair[1]) {
+        if durations_path.exists() {
+            fs::remove_file(durations_path)?;
+        }
+        return Ok(());
+    }
+    fs::write(durations_path, ron::to_string(durations)?)?;
+    Ok(())
+}
+
+/// Reads the frame durations for the image at `path`
+///
+/// Returns None if there's no durations file, and an error if it doesn't match `frame_count`
+pub fn load_durations(path: &Path, frame_count: usize) -> Result<Option<Vec<u32>>> {
+    let durations_path = durations_file_path(path);
+    if !durations_path.exists() {
+        return Ok(None);
+    }
+    let durations: Vec<u32> = ron::from_str(&fs::read_to_string(durations_path)?)?;
+    if durations.len() != frame_count {
+        return Err(eyre!("Durations file doesn't match frame count"));
+    }
+    Ok(Some(durations))
+}
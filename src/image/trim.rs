@@ -1,31 +1,57 @@
-use crate::image::*;
 use color_eyre::Result;
 use pixels_graphics_lib::prelude::IndexedImage;
 
-pub fn remove_blanks(mut image: IndexedImage) -> Result<IndexedImage> {
-    let trans_idxs = get_transparent_colors(&image);
-    if trans_idxs.is_empty() {
-        return Ok(image);
+/// Removes the transparent rows and columns around the content of every frame
+///
+/// All frames are cropped to the same area so they stay aligned
+pub fn remove_blanks(images: Vec<IndexedImage>) -> Result<Vec<IndexedImage>> {
+    match content_bounds(&images) {
+        Some((x, y, w, h)) => images.iter().map(|image| crop(image, x, y, w, h)).collect(),
+        None => Ok(images),
     }
-    if all_transparent(&image, &trans_idxs) {
-        return Ok(image);
-    }
-    while is_row_blank(&image, 0, &trans_idxs) {
-        image = remove_first_row(image)?;
-    }
-    while is_col_blank(&image, 0, &trans_idxs) {
-        image = remove_first_col(image)?;
-    }
-    while is_row_blank(&image, image.size().1 - 1, &trans_idxs) {
-        image = remove_last_row(image)?;
+}
+
+/// Union of the non transparent pixels in every image as (x, y, width, height)
+///
+/// Returns None if every pixel is transparent
+pub fn content_bounds(images: &[IndexedImage]) -> Option<(u8, u8, u8, u8)> {
+    let mut bounds: Option<(u8, u8, u8, u8)> = None;
+    for image in images {
+        let trans_idxs = get_transparent_colors(image);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let i = x as usize + y as usize * image.width() as usize;
+                if trans_idxs.contains(&image.get_pixels()[i]) {
+                    continue;
+                }
+                bounds = Some(match bounds {
+                    None => (x, y, x, y),
+                    Some((left, top, right, bottom)) => {
+                        (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                    }
+                });
+            }
+        }
     }
-    while is_col_blank(&image, image.size().0 - 1, &trans_idxs) {
-        image = remove_last_col(image)?;
+    bounds.map(|(left, top, right, bottom)| (left, top, right - left + 1, bottom - top + 1))
+}
+
+/// Copies the `width`x`height` area with the top left at `x`,`y`
+pub fn crop(image: &IndexedImage, x: u8, y: u8, width: u8, height: u8) -> Result<IndexedImage> {
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in y as usize..y as usize + height as usize {
+        let start = row * image.width() as usize + x as usize;
+        pixels.extend_from_slice(&image.get_pixels()[start..start + width as usize]);
     }
-    Ok(image)
+    Ok(IndexedImage::new(
+        width,
+        height,
+        image.get_palette().to_vec(),
+        pixels,
+    )?)
 }
 
-fn get_transparent_colors(image: &IndexedImage) -> Vec<u8> {
+pub fn get_transparent_colors(image: &IndexedImage) -> Vec<u8> {
     let mut output = vec![];
     for (i, color) in image.get_palette().iter().enumerate() {
         if color.a == 0 {
@@ -35,35 +61,71 @@ fn get_transparent_colors(image: &IndexedImage) -> Vec<u8> {
     output
 }
 
-fn all_transparent(image: &IndexedImage, trans_idxs: &[u8]) -> bool {
-    image.get_pixels().iter().all(|i| trans_idxs.contains(i))
-}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pixels_graphics_lib::prelude::*;
 
-fn is_row_blank(image: &IndexedImage, row: u8, trans_idxs: &[u8]) -> bool {
-    all_pixels_transparent(
-        image,
-        (0..image.width())
-            .map(|i| row as usize * image.width() as usize + i as usize)
-            .collect(),
-        trans_idxs,
-    )
-}
+    fn image(pixels: Vec<u8>) -> IndexedImage {
+        IndexedImage::new(3, 3, vec![TRANSPARENT, BLUE], pixels).unwrap()
+    }
 
-fn is_col_blank(image: &IndexedImage, idx: u8, trans_idxs: &[u8]) -> bool {
-    all_pixels_transparent(
-        image,
-        (0..image.height())
-            .map(|i| i as usize * image.width() as usize + idx as usize)
-            .collect(),
-        trans_idxs,
-    )
-}
+    #[test]
+    fn all_frames_blank() {
+        let frames = vec![image(vec![0; 9]), image(vec![0; 9])];
+        assert_eq!(content_bounds(&frames), None);
+        assert_eq!(remove_blanks(frames.clone()).unwrap(), frames);
+    }
 
-fn all_pixels_transparent(image: &IndexedImage, px: Vec<usize>, trans_idxs: &[u8]) -> bool {
-    for idx in px {
-        if !trans_idxs.contains(&image.get_pixels()[idx]) {
-            return false;
+    #[test]
+    fn content_on_different_frames() {
+        #[rustfmt::skip]
+        let frames = vec![
+            image(vec![
+                0, 0, 0,
+                1, 0, 0,
+                0, 0, 0,
+            ]),
+            image(vec![
+                0, 0, 0,
+                0, 0, 0,
+                0, 1, 0,
+            ]),
+        ];
+        assert_eq!(content_bounds(&frames), Some((0, 1, 2, 2)));
+        let trimmed = remove_blanks(frames).unwrap();
+        assert_eq!(trimmed[0].get_pixels(), &[1, 0, 0, 0]);
+        assert_eq!(trimmed[1].get_pixels(), &[0, 0, 0, 1]);
+        assert!(trimmed
+            .iter()
+            .all(|frame| frame.width() == 2 && frame.height() == 2));
+    }
+
+    #[test]
+    fn content_at_each_edge() {
+        for (i, expected) in [
+            (1, (1, 0, 1, 1)),
+            (3, (0, 1, 1, 1)),
+            (5, (2, 1, 1, 1)),
+            (7, (1, 2, 1, 1)),
+            (0, (0, 0, 1, 1)),
+            (8, (2, 2, 1, 1)),
+        ] {
+            let mut pixels = vec![0; 9];
+            pixels[i] = 1;
+            let frames = vec![image(pixels)];
+            assert_eq!(content_bounds(&frames), Some(expected), "pixel {i}");
+            let trimmed = remove_blanks(frames).unwrap();
+            assert_eq!(trimmed[0].get_pixels(), &[1], "pixel {i}");
         }
     }
-    true
+
+    #[test]
+    fn crop_area() {
+        let source = image(vec![0, 1, 0, 1, 1, 0, 0, 0, 1]);
+        let cropped = crop(&source, 1, 1, 2, 2).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 2));
+        assert_eq!(cropped.get_pixels(), &[1, 0, 0, 1]);
+        assert_eq!(cropped.get_palette(), source.get_palette());
+    }
 }
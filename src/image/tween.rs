@@ -0,0 +1,56 @@
+use crate::image::trim::get_transparent_colors;
+use pixels_graphics_lib::prelude::IndexedImage;
+
+/// How in-between frames are generated
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TweenKind {
+    /// The content of the first frame moves towards the position of the content in the last frame
+    Move,
+    /// The first frame is replaced by the last frame a few pixels at a time
+    Dissolve,
+}
+
+/// Ordered dither thresholds, each pixel switches to the last frame once the progress passes its
+/// threshold
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Pixels of `image` moved by `x`,`y`, uncovered pixels are set to the first transparent color (or
+/// the first color if the palette has no transparent colors)
+pub fn shift_pixels(image: &IndexedImage, x: isize, y: isize) -> Vec<u8> {
+    let width = image.width() as isize;
+    let height = image.height() as isize;
+    let blank = get_transparent_colors(image)
+        .first()
+        .copied()
+        .unwrap_or_default();
+    let mut pixels = vec![blank; image.get_pixels().len()];
+    for py in 0..height {
+        for px in 0..width {
+            let (sx, sy) = (px - x, py - y);
+            if sx >= 0 && sy >= 0 && sx < width && sy < height {
+                pixels[(px + py * width) as usize] = image.get_pixels()[(sx + sy * width) as usize];
+            }
+        }
+    }
+    pixels
+}
+
+/// Pixels part way between `start` and `end` (which must be the same size), `progress` is 0.0
+/// for `start` to 1.0 for `end`
+pub fn dissolve_pixels(start: &IndexedImage, end: &IndexedImage, progress: f64) -> Vec<u8> {
+    let width = start.width() as usize;
+    start
+        .get_pixels()
+        .iter()
+        .zip(end.get_pixels())
+        .enumerate()
+        .map(|(i, (start, end))| {
+            let threshold = (BAYER[(i / width) % 4][(i % width) % 4] as f64 + 0.5) / 16.0;
+            if progress > threshold {
+                *end
+            } else {
+                *start
+            }
+        })
+        .collect()
+}
@@ -3,18 +3,31 @@ mod palettes;
 mod scenes;
 mod ui;
 
+use crate::image::import::SheetSlicing;
+use crate::image::tween::TweenKind;
 use crate::scenes::editor::{BackgroundColors, Editor, EditorDetails};
+use crate::scenes::effect_dialog::{Effect, EffectDialog, EffectKind};
+use crate::scenes::import_sheet_dialog::ImportSheetDialog;
+use crate::scenes::layer_name_dialog::LayerNameDialog;
 use crate::scenes::menu::Menu;
 use crate::scenes::new_image_dialog::NewImageDialog;
+use crate::scenes::new_tilemap_dialog::NewTilemapDialog;
 use crate::scenes::palette_dialog::PaletteDialog;
+use crate::scenes::reference_dialog::ReferenceDialog;
 use crate::scenes::resize_dialog::{ResizeAnchor, ResizeDialog};
 use crate::scenes::save_palette_dialog::SavePaletteDataDialog;
 use crate::scenes::simplify_dialog::SimplifyDialog;
+use crate::scenes::sprite_sheet_dialog::SpriteSheetDialog;
+use crate::scenes::tile_grid_dialog::TileGridDialog;
+use crate::scenes::tilemap_editor::{TilemapDetails, TilemapEditor};
+use crate::scenes::tween_dialog::TweenDialog;
+use crate::ui::reference::ReferenceSettings;
 use color_eyre::Result;
 use directories::UserDirs;
 use log::LevelFilter;
 use pixels_graphics_lib::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::PathBuf;
 use std::{env, fs};
@@ -26,6 +39,7 @@ const WIDTH: usize = 280;
 const HEIGHT: usize = 240;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)] //so prefs saved by older versions still load
 struct Settings {
     pub last_used_dir: PathBuf,
     pub last_used_pal_dir: PathBuf,
@@ -33,24 +47,48 @@ struct Settings {
     pub use_colors: bool,
     pub background_color: BackgroundColors,
     pub last_used_anchor: ResizeAnchor,
+    pub show_pixel_grid: bool,
+    pub pixel_grid_color: Color,
+    pub tile_grid: Option<(u8, u8)>,
+    pub tile_grid_color: Color,
+    pub onion_skin: bool,
+    pub onion_skin_frames: u8,
+    /// Percentage
+    pub onion_skin_opacity: u8,
+    /// Reference image for each document, keyed by document path
+    pub references: HashMap<PathBuf, ReferenceSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            last_used_dir: UserDirs::new()
+                .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
+                .unwrap_or(PathBuf::from("/")),
+            last_used_pal_dir: UserDirs::new()
+                .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
+                .unwrap_or(PathBuf::from("/")),
+            last_used_png_dir: UserDirs::new()
+                .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
+                .unwrap_or(PathBuf::from("/")),
+            use_colors: true,
+            background_color: BackgroundColors::GreyCheck,
+            last_used_anchor: ResizeAnchor::Center,
+            show_pixel_grid: false,
+            pixel_grid_color: Color::new(0, 0, 0, 60),
+            tile_grid: None,
+            tile_grid_color: Color::new(0, 200, 255, 160),
+            onion_skin: false,
+            onion_skin_frames: 1,
+            onion_skin_opacity: 50,
+            references: HashMap::new(),
+        }
+    }
 }
 
 fn settings() -> AppPrefs<Settings> {
-    AppPrefs::new("app", "emmabritton", "image_editor", || Settings {
-        last_used_dir: UserDirs::new()
-            .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
-            .unwrap_or(PathBuf::from("/")),
-        last_used_pal_dir: UserDirs::new()
-            .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
-            .unwrap_or(PathBuf::from("/")),
-        last_used_png_dir: UserDirs::new()
-            .and_then(|ud| ud.document_dir().map(|p| p.to_path_buf()))
-            .unwrap_or(PathBuf::from("/")),
-        use_colors: true,
-        background_color: BackgroundColors::GreyCheck,
-        last_used_anchor: ResizeAnchor::Center,
-    })
-    .expect("Unable to create prefs file")
+    AppPrefs::new("app", "emmabritton", "image_editor", Settings::default)
+        .expect("Unable to create prefs file")
 }
 
 fn main() -> Result<()> {
@@ -97,6 +135,18 @@ fn main() -> Result<()> {
             style,
         )),
         SceneName::Simplify(img, idx) => list.push(SimplifyDialog::new(style, img, idx)),
+        SceneName::Effect(kind) => list.push(EffectDialog::new(kind, style)),
+        SceneName::TileGrid(w, h) => list.push(TileGridDialog::new((w, h), style)),
+        SceneName::SpriteSheet => list.push(SpriteSheetDialog::new(style)),
+        SceneName::ImportSheet(w, h) => list.push(ImportSheetDialog::new((w, h), style)),
+        SceneName::Tween(start, end) => list.push(TweenDialog::new(start, end, style)),
+        SceneName::Reference(settings) => list.push(ReferenceDialog::new(settings, style)),
+        SceneName::LayerName(name) => list.push(LayerNameDialog::new(&name, style)),
+        SceneName::NewTilemap(tileset) => list.push(NewTilemapDialog::new(tileset, style)),
+        SceneName::Tilemap(details) => {
+            list.clear();
+            list.push(TilemapEditor::new(details, settings(), style))
+        }
     };
 
     let mut options = Options::default();
@@ -154,6 +204,15 @@ enum SceneName {
     SavePaletteData(Option<FilePalette>),
     Resize(u8, u8),
     Simplify(IndexedImage, usize), //usize is index for preview background
+    Effect(EffectKind),
+    TileGrid(u8, u8),
+    SpriteSheet,
+    ImportSheet(usize, usize), //size of the sheet
+    Tween(usize, usize),       //first and last keyframe
+    Reference(ReferenceSettings),
+    LayerName(String),
+    NewTilemap(PathBuf), //path is the tileset
+    Tilemap(TilemapDetails),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -161,6 +220,69 @@ enum SceneResult {
     SavePaletteData(FilePalette),
     Palette(Vec<Color>, usize),
     ResizeData(u8, u8, ResizeAnchor),
-    Simplify(IndexedImage),
+    Simplify(Vec<Color>, Vec<u8>), //new palette and the new index for each old palette index
     SimplifyError,
+    Effect(Effect),
+    TileGrid(u8, u8),
+    SpriteSheet(usize, usize), //columns and padding
+    ImportSheet(SheetSlicing),
+    Tween(usize, usize, usize, TweenKind), //first and last keyframe, and number of frames to add
+    Reference(ReferenceSettings),
+    LayerName(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Settings as saved by an older version that didn't have `fields`
+    fn load_without(fields: &[&str]) -> Settings {
+        let settings = Settings {
+            last_used_dir: PathBuf::from("/images"),
+            use_colors: false,
+            last_used_anchor: ResizeAnchor::TopLeft,
+            ..Settings::default()
+        };
+        let mut json = serde_json::to_value(settings).unwrap();
+        for field in fields {
+            json.as_object_mut().unwrap().remove(*field);
+        }
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn settings_without_grids() {
+        let settings = load_without(&[
+            "show_pixel_grid",
+            "pixel_grid_color",
+            "tile_grid",
+            "tile_grid_color",
+        ]);
+        assert_eq!(settings.last_used_dir, PathBuf::from("/images"));
+        assert!(!settings.use_colors);
+        assert_eq!(settings.last_used_anchor, ResizeAnchor::TopLeft);
+        assert!(!settings.show_pixel_grid);
+        assert_eq!(settings.tile_grid, None);
+    }
+
+    #[test]
+    fn settings_without_onion_skin() {
+        let settings = load_without(&[
+            "onion_skin",
+            "onion_skin_frames",
+            "onion_skin_opacity",
+            "references",
+        ]);
+        assert_eq!(settings.last_used_dir, PathBuf::from("/images"));
+        assert!(!settings.onion_skin);
+        assert_eq!(settings.onion_skin_frames, 1);
+        assert_eq!(settings.onion_skin_opacity, 50);
+    }
+
+    #[test]
+    fn settings_without_references() {
+        let settings = load_without(&["references"]);
+        assert_eq!(settings.last_used_dir, PathBuf::from("/images"));
+        assert!(settings.references.is_empty());
+    }
 }
@@ -1,4 +1,7 @@
-use crate::scenes::{file_dialog, import_image, BACKGROUND};
+use crate::scenes::{
+    file_dialog, import_image, is_ctrl_down, BACKGROUND, IMAGE_FILTERS, PADDING, PER_UNDO,
+    STATUS_HEIGHT, UNTITLED,
+};
 use crate::ui::canvas::{Canvas, Tool};
 use crate::ui::palette::PaletteView;
 use crate::{DefaultPalette, SceneName, SceneResult, Settings, HEIGHT, SUR, WIDTH};
@@ -9,14 +12,22 @@ use pixels_graphics_lib::scenes::SceneUpdateResult::{Nothing, Pop, Push};
 use pixels_graphics_lib::ui::prelude::TextFilter::Decimal;
 use pixels_graphics_lib::ui::prelude::*;
 
+use crate::image::animation::{create_animation, read_animation, Playback};
+use crate::image::export::{export_gif, export_sprite_sheet};
+use crate::image::import::{SheetSlicing, SpriteSheet};
+use crate::image::layers::{load_layers, save_layers, Layer};
 use crate::image::resize::resize;
-use crate::image::trim::remove_blanks;
+use crate::image::timing::{load_durations, save_durations};
+use crate::image::trim::{crop, remove_blanks};
+use crate::image::tween::TweenKind;
 use crate::palettes::palette_default;
 use crate::scenes::editor_ui::*;
+use crate::scenes::effect_dialog::{Effect, EffectKind};
 use crate::scenes::resize_dialog::ResizeAnchor;
-use crate::ui::edit_history::EditHistory;
+use crate::ui::edit_history::{EditHistory, DEFAULT_FRAME_DURATION};
 use crate::ui::preview::Preview;
-use crate::ui::timeline::Timeline;
+use crate::ui::reference::{Reference, ReferenceSettings};
+use crate::ui::timeline::{Timeline, TimelineClick, DURATION_HEIGHT, MAX_FRAME_SIZE};
 use crate::SceneName::Resize;
 use image_lib::{save_buffer_with_format, ExtendedColorType, ImageFormat};
 use log::{debug, error};
@@ -29,13 +40,11 @@ use std::ops::Add;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-const PER_UNDO: u64 = 200;
-
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum OneWayAlertAction {
     Double,
     Trim,
-    Simplify,
+    CropToSelection,
     ChangePalette(Vec<Color>, usize),
     ResizeCanvas,
 }
@@ -46,19 +55,31 @@ enum DataLossAlertAction {
     Close,
     Open,
     Import,
+    ImportSheet,
+}
+
+/// Which frames flip, rotate, shift, clear and effects are applied to
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum FrameScope {
+    Current,
+    All,
+    /// Frames selected with Shift+click in the timeline
+    Range,
 }
 
-const PADDING: isize = 4;
 const PALETTE_WIDTH: usize = 58;
 const PALETTE_HEIGHT: usize = 58;
 
-const UNTITLED: &str = "Untitled";
 const CORRUPT: &str = "???";
+const ONION_PREV_TINT: Color = Color::new(255, 40, 40, 255);
+const ONION_NEXT_TINT: Color = Color::new(40, 120, 255, 255);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EditorDetails {
     Open(PathBuf),
     OpenImage(IndexedImage),
+    /// Imported frames and their durations
+    OpenAnimation(Vec<IndexedImage>, Vec<u32>),
     New(u8, u8, Option<Vec<Color>>),
 }
 
@@ -135,7 +156,7 @@ pub struct Editor {
     history: EditHistory,
     is_playing: bool,
     next_frame_swap: f64,
-    anim_frame_idx: usize,
+    playback: Playback,
     prefs: AppPrefs<Settings>,
     error: Option<String>,
     save_data: SaveData,
@@ -147,6 +168,15 @@ pub struct Editor {
     warning: Option<Alert>,
     alert_style: AlertStyle,
     play_type: PlayType,
+    tiled_preview: bool,
+    reference: Option<Reference>,
+    frame_scope: FrameScope,
+    /// First and last frame (inclusive) used when `frame_scope` is [FrameScope::Range]
+    frame_range: Option<(usize, usize)>,
+    /// Image picked to be imported as a sprite sheet, waiting for the slicing settings
+    pending_sheet: Option<SpriteSheet>,
+    /// Set when the view is dragged while Space is held, so releasing Space doesn't also paint
+    space_panned: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
@@ -323,22 +353,41 @@ impl Editor {
                 .0,
             &style.toggle_icon_button,
         );
+        let mut select_tool = ToggleIconButton::new(
+            Coord::default(),
+            "SELECT",
+            Positioning::CenterBottom,
+            IndexedImage::from_file_contents(include_bytes!("../../assets/icons/select.ici"))
+                .unwrap()
+                .0,
+            &style.toggle_icon_button,
+        );
+        let mut wand_tool = ToggleIconButton::new(
+            Coord::default(),
+            "WAND",
+            Positioning::CenterBottom,
+            IndexedImage::from_file_contents(include_bytes!("../../assets/icons/wand.ici"))
+                .unwrap()
+                .0,
+            &style.toggle_icon_button,
+        );
         let mut play_type = PlayType::Loops;
         let mut filename = Label::singleline(UNTITLED, (0, 0), WHITE, Standard6x7, WIDTH - 4);
         let mut error = None;
         let mut save_data;
-        let frames = match details {
+        let mut durations = None;
+        let (cels, layers) = match details {
             EditorDetails::Open(path) => {
                 let file = path.to_string_lossy().to_string();
                 let is_animated = file.contains(".ica");
                 save_data = SaveData::loaded_image(path.clone(), is_animated);
                 let bytes = fs::read(path.clone()).expect("Reading image from file");
                 let (mut images, pal) = if is_animated {
-                    let (image, pal) = AnimatedIndexedImage::from_file_contents(&bytes)
-                        .expect("Reading animated image data");
-                    speed.set_content(&image.get_per_frame().to_string());
-                    play_type = image.play_type();
-                    (image.as_images(), pal)
+                    let (images, file_durations, file_play_type, pal) =
+                        read_animation(&bytes).expect("Reading animated image data");
+                    durations = Some(file_durations);
+                    play_type = file_play_type;
+                    (images, pal)
                 } else {
                     let (image, pal) =
                         IndexedImage::from_file_contents(&bytes).expect("Reading image data");
@@ -360,9 +409,25 @@ impl Editor {
                 }
                 save_data.palette = Some(pal);
                 filename.update_text(&save_data.filename());
+                match load_durations(&path, images.len()) {
+                    Ok(Some(file_durations)) => durations = Some(file_durations),
+                    Ok(None) => {}
+                    Err(e) => error!("Error loading durations for {path:?}: {e:?}"),
+                }
+                let layers = match load_layers(&path, &images) {
+                    Ok(Some((cels, layers))) => {
+                        images = cels;
+                        layers
+                    }
+                    Ok(None) => vec![Layer::default()],
+                    Err(e) => {
+                        error!("Error loading layers for {path:?}: {e:?}");
+                        vec![Layer::default()]
+                    }
+                };
                 prefs.data.last_used_dir = path;
                 prefs.save();
-                images
+                (images, layers)
             }
             EditorDetails::New(w, h, palette) => {
                 save_data = SaveData::new_image();
@@ -376,11 +441,24 @@ impl Editor {
                 if prefs.data.use_colors {
                     save_data.palette = Some(FilePalette::Colors);
                 }
-                vec![IndexedImage::new(w, h, colors, vec![0; w as usize * h as usize]).unwrap()]
+                (
+                    vec![
+                        IndexedImage::new(w, h, colors, vec![0; w as usize * h as usize]).unwrap(),
+                    ],
+                    vec![Layer::default()],
+                )
             }
             EditorDetails::OpenImage(img) => {
                 save_data = SaveData::new_image();
-                vec![img]
+                (vec![img], vec![Layer::default()])
+            }
+            EditorDetails::OpenAnimation(frames, frame_durations) => {
+                save_data = SaveData::new_image();
+                if frames.len() > 1 {
+                    save_data.index = None;
+                }
+                durations = Some(frame_durations);
+                (frames, vec![Layer::default()])
             }
         };
 
@@ -393,7 +471,12 @@ impl Editor {
             prefs.data.background_color.colors(),
         );
 
-        canvas.set_image(frames[0].clone());
+        let mut history = EditHistory::with_layers(cels, layers);
+        if let Some(durations) = durations {
+            history = history.with_durations(durations);
+        }
+        let frames = history.get_images();
+        canvas.set_image(history.get_current_image().clone());
         canvas.set_color_index(1);
         let mut palette = PaletteView::new(
             Coord::new(
@@ -407,8 +490,7 @@ impl Editor {
         let mut preview = Preview::new(Rect::new_with_size((4, 122), 64, 73), true);
         let mut timeline = Timeline::new(Rect::new_with_size((-1, -1), 0, 0));
         timeline.set_frames(frames.clone(), 0);
-        let history = EditHistory::new(frames.clone());
-        preview.set_image(history.get_current_image().clone());
+        preview.set_image(history.get_current_frame());
 
         match prefs.data.background_color {
             BackgroundColors::SolidLightGrey => preview.set_background(2),
@@ -439,6 +521,10 @@ impl Editor {
         layout!(context, circle_tool, left_to_right_of fill_tool, px!(4));
         layout!(context, ellipse_tool, top_to_top_of pencil_tool);
         layout!(context, ellipse_tool, left_to_right_of circle_tool, px!(4));
+        layout!(context, select_tool, top_to_top_of pencil_tool);
+        layout!(context, select_tool, left_to_right_of ellipse_tool, px!(4));
+        layout!(context, wand_tool, top_to_top_of pencil_tool);
+        layout!(context, wand_tool, left_to_right_of select_tool, px!(4));
 
         layout!(context, play_pause, align_left, px!(4));
         layout!(context, play_pause, align_bottom, px!(4));
@@ -462,6 +548,8 @@ impl Editor {
             (Tool::Fill, fill_tool),
             (Tool::Circle, circle_tool),
             (Tool::Ellipse, ellipse_tool),
+            (Tool::Select, select_tool),
+            (Tool::Wand, wand_tool),
         ]);
 
         let mut editor = Self {
@@ -472,6 +560,10 @@ impl Editor {
             error,
             timeline,
             play_type,
+            tiled_preview: false,
+            reference: None,
+            frame_scope: FrameScope::Current,
+            frame_range: None,
             menubar,
             filename,
             result: Nothing,
@@ -488,13 +580,18 @@ impl Editor {
             history,
             is_playing: false,
             next_frame_swap: 0.0,
-            anim_frame_idx: 0,
+            playback: Playback::new(play_type, 1),
             prefs,
             warning: None,
+            pending_sheet: None,
+            space_panned: false,
             alert_style: style.alert.clone(),
             one_way_pending_alert: None,
         };
+        editor.restore_reference();
         editor.relayout_canvas(frames.len() > 1);
+        editor.image_update();
+        editor.set_anim_mode(editor.play_type);
         Box::new(editor)
     }
 
@@ -521,6 +618,7 @@ impl Editor {
                 self.filename.update_text(&self.save_data.filename());
                 self.prefs.data.last_used_dir = path;
                 self.prefs.save();
+                self.store_reference();
             } else {
                 return;
             }
@@ -544,26 +642,34 @@ impl Editor {
                         .expect("Unable to save ici file (converting)");
                     fs::write(filepath, bytes).expect("Unable to save ici file (writing)");
                 } else {
-                    let frames = self.history.get_images();
-                    let pixels = frames
-                        .iter()
-                        .flat_map(|i| i.get_pixels().to_vec())
-                        .collect();
-                    let image = AnimatedIndexedImage::new(
-                        frames[0].width(),
-                        frames[1].height(),
-                        self.speed.content().parse::<f64>().unwrap_or(1.0),
-                        frames.len() as u8,
-                        frames[0].get_palette().to_vec(),
-                        pixels,
-                        PlayType::Loops,
+                    let image = create_animation(
+                        &self.history.get_images(),
+                        self.history.durations(),
+                        self.play_type,
                     )
-                    .unwrap();
+                    .expect("Unable to save ica file (creating)");
                     let bytes = image
                         .to_file_contents(palette)
                         .expect("Unable to save ica file (converting)");
                     fs::write(filepath, bytes).expect("Unable to save ica file (writing)");
                 }
+                let cels = match self.save_data.index {
+                    Some(idx) => {
+                        let count = self.history.layers().len();
+                        &self.history.get_cels()[idx * count..(idx + 1) * count]
+                    }
+                    None => self.history.get_cels(),
+                };
+                if let Err(e) = save_layers(filepath, cels, self.history.layers()) {
+                    error!("Error saving layers for {filepath:?}: {e:?}");
+                }
+                let durations = match self.save_data.index {
+                    Some(_) => &[],
+                    None => self.history.durations(),
+                };
+                if let Err(e) = save_durations(filepath, durations) {
+                    error!("Error saving durations for {filepath:?}: {e:?}");
+                }
             } else {
                 error!("Missing save_data.palette")
             }
@@ -575,14 +681,17 @@ impl Editor {
     fn relayout_canvas(&mut self, show_timeline: bool) {
         let state = self.canvas.get_usage_state();
         if show_timeline {
-            let image_height = self.history.get_current_image().height() as usize;
+            let image_height =
+                (self.history.get_current_image().height() as usize).min(MAX_FRAME_SIZE);
             self.timeline = Timeline::new(Rect::new_with_size(
                 (
                     self.preview.bounds().right() + PADDING,
-                    self.play_pause.bounds().bottom() - (image_height as isize),
+                    self.play_pause.bounds().bottom()
+                        - (image_height as isize)
+                        - (DURATION_HEIGHT as isize),
                 ),
                 200,
-                image_height + 4,
+                image_height + 4 + DURATION_HEIGHT,
             ));
             self.canvas = Canvas::new(
                 Coord::new(
@@ -593,7 +702,8 @@ impl Editor {
                     (WIDTH as isize - self.tools.get(Tool::Pencil).bounds().left()) as usize,
                     (self.timeline.bounds().top()
                         - self.tools.get(Tool::Pencil).bounds().bottom()
-                        - PADDING) as usize,
+                        - PADDING
+                        - STATUS_HEIGHT) as usize,
                 ),
                 self.prefs.data.background_color.colors(),
             );
@@ -610,40 +720,323 @@ impl Editor {
                     (HEIGHT as isize
                         - self.tools.get(Tool::Pencil).bounds().bottom()
                         - PADDING
-                        - PADDING) as usize,
+                        - PADDING
+                        - STATUS_HEIGHT) as usize,
                 ),
                 self.prefs.data.background_color.colors(),
             );
             self.play_pause.set_state(ViewState::Disabled);
         }
         self.canvas.set_color_index(self.palette.get_selected_idx());
-        self.canvas
-            .set_image(self.history.get_current_image().clone());
+        self.update_canvas_image();
         self.timeline
             .set_frames(self.history.get_images(), self.history.active_frame());
+        self.timeline
+            .set_durations(self.history.durations().to_vec());
+        self.update_frame_range();
         self.canvas.set_usage_state(state);
+        self.canvas.set_wrap(self.history.is_wrapping());
+        self.canvas.set_reference(self.reference.clone());
+        self.update_grids();
+        self.update_onion_skin();
     }
 
     fn undo(&mut self) {
+        self.canvas.cancel_floating();
+        self.sync_selection();
         self.history.undo().unwrap();
+        self.canvas
+            .set_selection(self.history.paint_filter().selection.clone());
         self.last_undo = Instant::now().add(Duration::from_millis(PER_UNDO));
         self.image_update();
     }
 
     fn redo(&mut self) {
+        self.canvas.cancel_floating();
+        self.sync_selection();
         self.history.redo().unwrap();
+        self.canvas
+            .set_selection(self.history.paint_filter().selection.clone());
         self.last_undo = Instant::now().add(Duration::from_millis(PER_UNDO));
         self.image_update();
     }
 
-    fn image_update(&mut self) {
+    /// Writes any floating pixels to the image, this should be done before other edits
+    fn commit_floating(&mut self) {
+        if self.canvas.commit_floating(&mut self.history) {
+            self.image_update();
+        }
+    }
+
+    fn cut(&mut self) {
+        if self.canvas.cut_selection(&mut self.history) {
+            self.image_update();
+        }
+    }
+
+    fn paste(&mut self) {
+        self.set_tool(Tool::Select);
+        if self.canvas.paste(&mut self.history) {
+            self.image_update();
+        }
+    }
+
+    /// Flips, rotates or shifts the selected pixels, or the whole image if nothing is selected
+    fn transform(&mut self, transform: fn(&mut EditHistory) -> Result<(), IndexedImageError>) {
+        self.sync_selection();
+        let frames = self.scope_frames();
+        if let Err(e) = self.history.add_frames_edit(&frames, transform) {
+            error!("Error transforming image: {e:?}");
+        }
+        self.canvas
+            .set_selection(self.history.paint_filter().selection.clone());
+        self.image_update();
+    }
+
+    /// Keeps the paint filter matching the canvas selection so tools only paint inside of it
+    fn sync_selection(&mut self) {
+        if self.history.paint_filter().selection.as_ref() != self.canvas.selection() {
+            let mut filter = self.history.paint_filter().clone();
+            filter.selection = self.canvas.selection().cloned();
+            self.history.set_paint_filter(filter);
+        }
+    }
+
+    /// Replaces the history (e.g. after a one way operation), keeping the paint settings
+    ///
+    /// `cels` must contain an image for each layer of each frame
+    fn reset_history(&mut self, cels: Vec<IndexedImage>, layers: Vec<Layer>) {
+        let mut filter = self.history.paint_filter().clone();
+        let palette_changed = cels.first().map(|cel| cel.get_palette())
+            != Some(self.history.get_current_image().get_palette());
+        let wrap = self.history.is_wrapping();
+        let durations = self.history.durations().to_vec();
+        let history = EditHistory::with_layers(cels, layers);
+        self.history = if durations.len() == history.frame_count() {
+            history.with_durations(durations)
+        } else {
+            history
+        };
+        //the color picked for replace only may not exist anymore
+        if palette_changed {
+            filter.replace_only = None;
+        }
+        self.set_replace_only(filter.replace_only);
+        self.history.set_paint_filter(filter);
+        self.history.set_wrapping(wrap);
+    }
+
+    /// Restricts painting to pixels of palette index `idx`, or any pixel if `None`
+    fn set_replace_only(&mut self, idx: Option<u8>) {
+        let mut filter = self.history.paint_filter().clone();
+        filter.replace_only = idx;
+        self.menubar
+            .set_checked(MenuId::MenuEditReplaceOnly, idx.is_some());
+        self.palette.set_replace_only(idx);
+        self.history.set_paint_filter(filter);
+    }
+
+    /// Shows the active layer on the canvas along with the other visible layers
+    fn update_canvas_image(&mut self) {
         self.canvas
             .set_image(self.history.get_current_image().clone());
+        self.canvas.set_layers(
+            self.history.get_layers_around(),
+            self.history.layers()[self.history.active_layer()].visible,
+        );
+    }
+
+    fn image_update(&mut self) {
+        self.update_canvas_image();
         self.timeline
             .set_frames(self.history.get_images(), self.history.active_frame());
-        self.preview
-            .set_image(self.history.get_current_image().clone());
+        self.preview.set_image(self.history.get_current_frame());
         self.palette.set_palette(self.canvas.get_palette());
+        //palette edits (and undoing them) can remove the replace only color
+        if let Some(idx) = self.history.paint_filter().replace_only {
+            if idx as usize >= self.canvas.get_palette().len() {
+                self.set_replace_only(None);
+            }
+        }
+        self.menubar.set_checked(
+            MenuId::MenuLayerVisible,
+            self.history.layers()[self.history.active_layer()].visible,
+        );
+        self.update_durations();
+        self.update_onion_skin();
+    }
+
+    /// Shows the frame durations on the timeline and the active frame's in the speed field,
+    /// unless it's being edited
+    fn update_durations(&mut self) {
+        self.timeline
+            .set_durations(self.history.durations().to_vec());
+        if !self.speed.is_focused() {
+            let millis = self.history.durations()[self.history.active_frame()];
+            self.speed
+                .set_content(&(millis as f64 / 1000.0).to_string());
+        }
+    }
+
+    /// Parses the speed field as seconds, None if it's not a valid duration
+    fn speed_millis(&self) -> Option<u32> {
+        match self.speed.content().parse::<f64>() {
+            Ok(secs) if secs > 0.0 => Some(((secs * 1000.0).round() as u32).max(1)),
+            _ => None,
+        }
+    }
+
+    /// Sets the duration of the active frame from the speed field
+    fn apply_speed(&mut self) {
+        if let Some(millis) = self.speed_millis() {
+            if let Err(e) = self
+                .history
+                .set_frame_duration(self.history.active_frame(), millis)
+            {
+                error!("Error setting frame duration: {e:?}");
+            }
+        }
+        self.update_durations();
+    }
+
+    /// Sets the duration of every frame from the speed field
+    fn apply_speed_to_all(&mut self) {
+        self.speed.unfocus();
+        if let Some(millis) = self.speed_millis() {
+            if let Err(e) = self.history.set_all_durations(millis) {
+                error!("Error setting frame durations: {e:?}");
+            }
+        }
+        self.update_durations();
+    }
+
+    fn set_frame_scope(&mut self, scope: FrameScope) {
+        self.frame_scope = scope;
+        self.menubar.uncheck_all_children(MenuId::MenuAnimScope);
+        self.menubar.set_checked(
+            match scope {
+                FrameScope::Current => MenuId::MenuAnimScopeCurrent,
+                FrameScope::All => MenuId::MenuAnimScopeAll,
+                FrameScope::Range => MenuId::MenuAnimScopeRange,
+            },
+            true,
+        );
+        self.update_frame_range();
+    }
+
+    fn update_frame_range(&mut self) {
+        let range = if self.frame_scope == FrameScope::Range {
+            self.frame_range
+        } else {
+            None
+        };
+        self.timeline.set_range(range);
+    }
+
+    /// Frames that image operations should be applied to
+    fn scope_frames(&self) -> Vec<usize> {
+        let frames: Vec<usize> = match (self.frame_scope, self.frame_range) {
+            (FrameScope::All, _) => (0..self.history.frame_count()).collect(),
+            (FrameScope::Range, Some((start, end))) => (start..=end)
+                .filter(|i| *i < self.history.frame_count())
+                .collect(),
+            _ => vec![],
+        };
+        if frames.is_empty() {
+            vec![self.history.active_frame()]
+        } else {
+            frames
+        }
+    }
+
+    /// Asks how many frames to generate between the ends of the Shift+click range, or the active
+    /// frame and the next one if there isn't a range
+    fn open_tweens(&mut self) {
+        let (start, end) = match self.frame_range {
+            Some((start, end)) if start < end && end < self.history.frame_count() => (start, end),
+            _ => (self.history.active_frame(), self.history.active_frame() + 1),
+        };
+        if end >= self.history.frame_count() {
+            self.warning = Some(Alert::new_warning(
+                &["Select two frames", "(Shift+click in the timeline)"],
+                WIDTH,
+                HEIGHT,
+                &self.alert_style,
+            ));
+            return;
+        }
+        self.result = Push(false, SceneName::Tween(start, end));
+    }
+
+    fn add_tweens(&mut self, start: usize, end: usize, count: usize, kind: TweenKind) {
+        self.commit_floating();
+        if let Err(e) = self.history.add_tweens(start, end, count, kind) {
+            error!("Error generating in-betweens: {e:?}");
+        }
+        self.frame_range = None;
+        self.update_frame_range();
+        self.image_update();
+    }
+
+    /// Moves a frame to a new position and shows the result
+    fn move_frame<F: FnOnce(&mut EditHistory) -> Result<(), IndexedImageError>>(
+        &mut self,
+        update: F,
+    ) {
+        self.commit_floating();
+        if let Err(e) = update(&mut self.history) {
+            error!("Error moving frame: {e:?}");
+        }
+        self.image_update();
+    }
+
+    /// Starts or stops the animation, editing is disabled while it plays
+    fn set_playing(&mut self, playing: bool) {
+        self.is_playing = playing;
+        let state = if playing {
+            ViewState::Disabled
+        } else {
+            ViewState::Normal
+        };
+        self.add_frame.set_state(state);
+        self.speed.set_state(state);
+        self.remove_frame.set_state(state);
+        self.copy_frame.set_state(state);
+        self.timeline.set_state(state);
+        self.palette.set_state(state);
+        self.canvas.set_state(state);
+        self.menubar.set_state(MenuId::MenuEdit, state);
+        self.menubar.set_state(MenuId::MenuImage, state);
+        self.menubar.set_state(MenuId::MenuPalette, state);
+        self.menubar.set_state(MenuId::MenuAnim, state);
+        self.menubar.set_state(MenuId::MenuLayer, state);
+        if playing {
+            self.playback = Playback::new(self.play_type, self.history.frame_count());
+            self.next_frame_swap = self.frame_seconds(self.playback.frame());
+            self.show_playback_frame();
+        } else {
+            self.update_canvas_image();
+            self.preview.set_image(self.history.get_current_frame());
+            self.timeline.set_active(self.history.active_frame());
+        }
+    }
+
+    fn show_playback_frame(&mut self) {
+        let image = self.history.get_image(self.playback.frame());
+        self.canvas.set_image(image.clone());
+        self.canvas.set_layers((None, None), true);
+        self.preview.set_image(image);
+        self.timeline.set_active(self.playback.frame());
+    }
+
+    /// Seconds to show the frame at `idx` for while playing
+    fn frame_seconds(&self, idx: usize) -> f64 {
+        self.history
+            .durations()
+            .get(idx)
+            .copied()
+            .unwrap_or(DEFAULT_FRAME_DURATION) as f64
+            / 1000.0
     }
 
     fn open_file(&mut self) {
@@ -668,7 +1061,7 @@ impl Editor {
     }
 
     fn double_size(&mut self) {
-        if self.canvas.get_image().width() > 32 || self.canvas.get_image().height() > 32 {
+        if self.canvas.get_image().width() > 127 || self.canvas.get_image().height() > 127 {
             self.warning = Some(Alert::new_warning(
                 &["Can not double size, image too large"],
                 WIDTH,
@@ -677,10 +1070,20 @@ impl Editor {
             ));
             return;
         }
-        match self.history.get_current_image().scale(Scaling::nn_double()) {
-            Ok(image) => {
-                debug!("Image size doubled to {}x{}", image.width(), image.height());
-                self.history = EditHistory::new(vec![image]);
+        let result: Result<Vec<IndexedImage>, IndexedImageError> = self
+            .history
+            .get_cels()
+            .iter()
+            .map(|cel| cel.scale(Scaling::nn_double()))
+            .collect();
+        match result {
+            Ok(cels) => {
+                debug!(
+                    "Image size doubled to {}x{}",
+                    cels[0].width(),
+                    cels[0].height()
+                );
+                self.reset_history(cels, self.history.layers().to_vec());
                 self.image_update();
             }
             Err(err) => {
@@ -695,12 +1098,16 @@ impl Editor {
         }
     }
 
+    /// Crops every frame to the area containing non transparent pixels in any frame or layer
     fn trim(&mut self) {
-        match remove_blanks(self.history.get_current_image().clone()) {
-            Ok(image) => {
-                debug!("Canvas resized to {}x{}", image.width(), image.height());
-                self.history = EditHistory::new(vec![image]);
-                self.image_update();
+        match remove_blanks(self.history.get_cels().to_vec()) {
+            Ok(images) => {
+                debug!(
+                    "Canvas resized to {}x{}",
+                    images[0].width(),
+                    images[0].height()
+                );
+                self.replace_frames(images);
             }
             Err(err) => {
                 error!("Error trimming: {err:?}");
@@ -709,11 +1116,52 @@ impl Editor {
         }
     }
 
+    /// Crops every frame to the bounds of the selection
+    fn crop_to_selection(&mut self) {
+        let Some((top_left, bottom_right)) = self.canvas.selection().and_then(|s| s.bounds())
+        else {
+            return;
+        };
+        let width = (bottom_right.x - top_left.x + 1) as u8;
+        let height = (bottom_right.y - top_left.y + 1) as u8;
+        let result: color_eyre::Result<Vec<IndexedImage>> = self
+            .history
+            .get_cels()
+            .iter()
+            .map(|image| crop(image, top_left.x as u8, top_left.y as u8, width, height))
+            .collect();
+        match result {
+            Ok(images) => {
+                debug!("Canvas cropped to {width}x{height}");
+                self.replace_frames(images);
+            }
+            Err(err) => {
+                error!("Error cropping: {err:?}");
+                self.error = Some(format!("Error cropping canvas: {err}"));
+            }
+        }
+    }
+
+    /// Replaces every frame after a one way operation that changes the size of the image
+    fn replace_frames(&mut self, cels: Vec<IndexedImage>) {
+        self.reset_history(cels, self.history.layers().to_vec());
+        self.image_update();
+        if self.history.frame_count() > 1 {
+            self.relayout_canvas(true);
+        }
+    }
+
     fn resize(&mut self, w: u8, h: u8, anchor: ResizeAnchor) {
-        match resize(w, h, anchor, self.history.get_current_image()) {
-            Ok(img) => {
+        let result: color_eyre::Result<Vec<IndexedImage>> = self
+            .history
+            .get_cels()
+            .iter()
+            .map(|cel| resize(w, h, anchor, cel))
+            .collect();
+        match result {
+            Ok(cels) => {
                 debug!("Canvas resized to {w}x{h}");
-                self.history = EditHistory::new(vec![img]);
+                self.reset_history(cels, self.history.layers().to_vec());
                 self.image_update();
             }
             Err(e) => {
@@ -735,7 +1183,7 @@ impl Editor {
         )
         .save_file()
         {
-            let image = Image::from_indexed(self.history.get_current_image());
+            let image = Image::from_indexed(&self.history.get_current_frame());
             if image.is_transparent() && format == ImageFormat::Jpeg {
                 self.warning = Some(Alert::new_warning(
                     &["Can not export image: JPEG", "doesn't support transparency"],
@@ -775,21 +1223,129 @@ impl Editor {
         }
     }
 
+    /// Exports every frame as an animated GIF using the frame durations and play type
+    fn export_gif(&mut self) {
+        if let Some(path) =
+            file_dialog(self.prefs.data.last_used_dir.clone(), &[("GIF", "gif")]).save_file()
+        {
+            if let Err(e) = export_gif(
+                &path,
+                &self.history.get_images(),
+                self.history.durations(),
+                self.play_type,
+            ) {
+                error!("Error saving gif to {path:?}: {e:?}");
+                self.warning = Some(Alert::new_warning(
+                    &["Error saving image"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+
+    /// Exports every frame packed into one PNG, with the frame positions and durations in a JSON
+    /// file next to it
+    fn export_sprite_sheet(&mut self, columns: usize, padding: usize) {
+        if let Some(path) =
+            file_dialog(self.prefs.data.last_used_dir.clone(), &[("PNG", "png")]).save_file()
+        {
+            if let Err(e) = export_sprite_sheet(
+                &path,
+                &self.history.get_images(),
+                self.history.durations(),
+                columns,
+                padding,
+            ) {
+                error!("Error saving sprite sheet to {path:?}: {e:?}");
+                self.warning = Some(Alert::new_warning(
+                    &["Error saving image"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+
     fn import(&mut self) {
         if let Some(result) = import_image(&self.alert_style, &mut self.prefs) {
             match result {
-                Ok(img) => {
+                Ok(EditorDetails::OpenImage(img)) => {
                     debug!("New image imported");
-                    self.history = EditHistory::new(vec![img]);
+                    self.reset_history(vec![img], vec![Layer::default()]);
                     self.image_update();
                     self.palette.set_color_index(0);
                     self.canvas.set_color_index(0);
                 }
+                Ok(details) => {
+                    debug!("New animation imported");
+                    self.result = Push(false, SceneName::Editor(details));
+                }
                 Err(alert) => self.warning = Some(alert),
             }
         }
     }
 
+    /// Opens an image to import as a sprite sheet, the dialog for how to slice it is shown next
+    fn pick_sheet(&mut self) {
+        if let Some(path) =
+            file_dialog(self.prefs.data.last_used_png_dir.clone(), &IMAGE_FILTERS).pick_file()
+        {
+            match SpriteSheet::open(&path) {
+                Ok(sheet) => {
+                    self.result = Push(false, SceneName::ImportSheet(sheet.width, sheet.height));
+                    self.pending_sheet = Some(sheet);
+                    self.prefs.data.last_used_png_dir = path;
+                    self.prefs.save();
+                }
+                Err(e) => {
+                    error!("Error importing sprite sheet from {path:?}: {e:?}");
+                    self.warning = Some(Alert::new_warning(
+                        &["Error opening image"],
+                        WIDTH,
+                        HEIGHT,
+                        &self.alert_style,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Replaces the image with a frame for each cell of the pending sprite sheet
+    fn import_sheet(&mut self, slicing: SheetSlicing) {
+        let Some(sheet) = self.pending_sheet.take() else {
+            return;
+        };
+        match sheet.slice(&slicing) {
+            Ok(frames) => {
+                debug!("Sprite sheet imported as {} frames", frames.len());
+                if self.is_playing {
+                    self.set_playing(false);
+                }
+                let count = frames.len();
+                self.reset_history(frames, vec![Layer::default()]);
+                self.frame_range = None;
+                self.update_frame_range();
+                self.relayout_canvas(count > 1);
+                self.image_update();
+                self.palette.set_color_index(0);
+                self.canvas.set_color_index(0);
+                self.save_data.index = if count > 1 { None } else { Some(0) };
+            }
+            Err(e) => {
+                error!("Error slicing sprite sheet with {slicing:?}: {e:?}");
+                self.warning = Some(Alert::new_warning(
+                    &["Error importing sprite sheet", &e.to_string()],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+
     fn change_palette(&mut self, colors: &[Color], selected: usize) {
         if let Err(e) = self.history.add_palette_change(colors) {
             panic!("Failed to update palette: (please raise issue on github) {e:?}");
@@ -838,6 +1394,264 @@ impl Editor {
         }
     }
 
+    fn set_tile_grid(&mut self, size: Option<(u8, u8)>) {
+        self.prefs.data.tile_grid = size;
+        self.prefs.save();
+        self.menubar
+            .uncheck_all_children(MenuId::MenuCanvasTileGrid);
+        let id = match size {
+            None => MenuId::MenuCanvasTileGridNone,
+            Some((8, 8)) => MenuId::MenuCanvasTileGrid8,
+            Some((16, 16)) => MenuId::MenuCanvasTileGrid16,
+            Some(_) => MenuId::MenuCanvasTileGridCustom,
+        };
+        self.menubar.set_checked(id, true);
+        self.update_grids();
+    }
+
+    fn load_reference(&mut self) {
+        if let Some(path) =
+            file_dialog(self.prefs.data.last_used_png_dir.clone(), &IMAGE_FILTERS).pick_file()
+        {
+            match Reference::open(ReferenceSettings::new(path.clone())) {
+                Ok(reference) => {
+                    self.prefs.data.last_used_png_dir = path;
+                    self.set_reference(Some(reference));
+                }
+                Err(e) => {
+                    error!("Error opening reference image {path:?}: {e}");
+                    self.warning = Some(Alert::new_warning(
+                        &["Error opening image"],
+                        WIDTH,
+                        HEIGHT,
+                        &self.alert_style,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn set_reference(&mut self, reference: Option<Reference>) {
+        self.reference = reference;
+        self.canvas.set_reference(self.reference.clone());
+        self.store_reference();
+    }
+
+    /// Remembers the reference for this document, unsaved documents aren't remembered
+    fn store_reference(&mut self) {
+        if let Some(path) = &self.save_data.path {
+            match &self.reference {
+                Some(reference) => self
+                    .prefs
+                    .data
+                    .references
+                    .insert(path.clone(), reference.settings.clone()),
+                None => self.prefs.data.references.remove(path),
+            };
+            self.prefs.save();
+        }
+    }
+
+    fn restore_reference(&mut self) {
+        if let Some(settings) = self
+            .save_data
+            .path
+            .as_ref()
+            .and_then(|path| self.prefs.data.references.get(path))
+        {
+            match Reference::open(settings.clone()) {
+                Ok(reference) => self.reference = Some(reference),
+                Err(e) => error!("Error opening reference image {:?}: {e}", settings.path),
+            }
+        }
+    }
+
+    /// Cursor position, the color under it, current shape size and frame
+    fn status_text(&self, mouse_xy: Coord) -> String {
+        let mut parts = vec![];
+        if let Some((x, y)) = self.canvas.cursor_position(mouse_xy) {
+            //all visible layers, as the active layer may be transparent where others aren't
+            let image = if self.is_playing {
+                self.history.get_image(self.playback.frame())
+            } else {
+                self.history.get_current_frame()
+            };
+            parts.push(format!("{x},{y}"));
+            if let Some(idx) = image
+                .get_pixel_index(x, y)
+                .ok()
+                .and_then(|i| image.get_pixel(i).ok())
+            {
+                let color = image.get_color(idx).unwrap_or(TRANSPARENT);
+                parts.push(format!(
+                    "#{idx} {:02X}{:02X}{:02X}{:02X}",
+                    color.r, color.g, color.b, color.a
+                ));
+            }
+        }
+        if let Some((w, h)) = self.canvas.shape_size(mouse_xy) {
+            if self.canvas.get_usage_state().0 == Tool::Line {
+                parts.push(format!("{w}x{h} len {}", w.max(h)));
+            } else {
+                parts.push(format!("{w}x{h}"));
+            }
+        }
+        if self.history.frame_count() > 1 {
+            let frame = if self.is_playing {
+                self.playback.frame()
+            } else {
+                self.history.active_frame()
+            };
+            parts.push(format!(
+                "frame {}/{}",
+                frame + 1,
+                self.history.frame_count()
+            ));
+        }
+        parts.join("  ")
+    }
+
+    fn set_onion_frames(&mut self, count: u8) {
+        self.prefs.data.onion_skin_frames = count;
+        self.prefs.save();
+        self.menubar
+            .uncheck_all_children(MenuId::MenuAnimOnionFrames);
+        let id = match count {
+            1 => MenuId::MenuAnimOnionFrames1,
+            2 => MenuId::MenuAnimOnionFrames2,
+            _ => MenuId::MenuAnimOnionFrames3,
+        };
+        self.menubar.set_checked(id, true);
+        self.update_onion_skin();
+    }
+
+    fn set_onion_opacity(&mut self, percent: u8) {
+        self.prefs.data.onion_skin_opacity = percent;
+        self.prefs.save();
+        self.menubar
+            .uncheck_all_children(MenuId::MenuAnimOnionOpacity);
+        let id = match percent {
+            25 => MenuId::MenuAnimOnionOpacity25,
+            50 => MenuId::MenuAnimOnionOpacity50,
+            _ => MenuId::MenuAnimOnionOpacity75,
+        };
+        self.menubar.set_checked(id, true);
+        self.update_onion_skin();
+    }
+
+    /// Shows the frames around the active one on the canvas, each step away is fainter
+    fn update_onion_skin(&mut self) {
+        let mut frames = vec![];
+        if self.prefs.data.onion_skin && !self.is_playing {
+            let active = self.history.active_frame();
+            let count = self.prefs.data.onion_skin_frames as usize;
+            let opacity = self.prefs.data.onion_skin_opacity as usize * 255 / 100;
+            for distance in (1..=count).rev() {
+                let alpha = |mut tint: Color| {
+                    tint.a = (opacity / distance) as u8;
+                    tint
+                };
+                if let Some(idx) = active.checked_sub(distance) {
+                    frames.push((self.history.get_image(idx).clone(), alpha(ONION_PREV_TINT)));
+                }
+                if active + distance < self.history.frame_count() {
+                    frames.push((
+                        self.history.get_image(active + distance).clone(),
+                        alpha(ONION_NEXT_TINT),
+                    ));
+                }
+            }
+        }
+        self.canvas.set_onion_skin(frames);
+    }
+
+    fn update_grids(&mut self) {
+        let settings = &self.prefs.data;
+        self.canvas.set_pixel_grid(
+            settings
+                .show_pixel_grid
+                .then_some(settings.pixel_grid_color),
+        );
+        self.canvas
+            .set_tile_grid(settings.tile_grid, settings.tile_grid_color);
+    }
+
+    fn on_shortcut(&mut self, key: KeyCode) {
+        if self.is_playing {
+            return;
+        }
+        match key {
+            KeyCode::KeyB => self.set_tool(Tool::Pencil),
+            KeyCode::KeyL => self.set_tool(Tool::Line),
+            KeyCode::KeyR => self.set_tool(Tool::Rect),
+            KeyCode::KeyG => self.set_tool(Tool::Fill),
+            KeyCode::KeyC => self.set_tool(Tool::Circle),
+            KeyCode::KeyE => self.set_tool(Tool::Ellipse),
+            KeyCode::KeyM => self.set_tool(Tool::Select),
+            KeyCode::KeyW => self.set_tool(Tool::Wand),
+            KeyCode::BracketLeft => self.cycle_color(-1),
+            KeyCode::BracketRight => self.cycle_color(1),
+            KeyCode::PageUp => self.select_layer(1),
+            KeyCode::PageDown => self.select_layer(-1),
+            KeyCode::ArrowUp => self.canvas.move_keyboard_cursor(0, -1),
+            KeyCode::ArrowDown => self.canvas.move_keyboard_cursor(0, 1),
+            KeyCode::ArrowLeft => self.canvas.move_keyboard_cursor(-1, 0),
+            KeyCode::ArrowRight => self.canvas.move_keyboard_cursor(1, 0),
+            KeyCode::Space => {
+                if self.canvas.on_keyboard_paint(&mut self.history) {
+                    self.image_update();
+                }
+            }
+            KeyCode::Escape => self.canvas.cancel(),
+            KeyCode::Enter => self.commit_floating(),
+            _ => {
+                if let Some(idx) = digit_value(key) {
+                    self.set_color_index(idx);
+                }
+            }
+        }
+    }
+
+    fn set_tool(&mut self, tool: Tool) {
+        self.commit_floating();
+        self.tools.set_selected(tool);
+        self.canvas.set_tool(tool);
+    }
+
+    fn set_color_index(&mut self, idx: u8) {
+        if (idx as usize) < self.canvas.get_palette().len() {
+            self.palette.set_color_index(idx);
+            self.canvas.set_color_index(idx);
+        }
+    }
+
+    /// Select the next (`1`) or previous (`-1`) palette color, wrapping around
+    fn cycle_color(&mut self, diff: isize) {
+        let count = self.canvas.get_palette().len() as isize;
+        let idx = (self.palette.get_selected_idx() as isize + diff).rem_euclid(count);
+        self.set_color_index(idx as u8);
+    }
+
+    /// Adds, removes, reorders, renames or selects a layer and shows the result
+    fn update_layers<F: FnOnce(&mut EditHistory) -> Result<(), IndexedImageError>>(
+        &mut self,
+        update: F,
+    ) {
+        if let Err(e) = update(&mut self.history) {
+            error!("Error updating layers: {e:?}");
+        }
+        self.image_update();
+    }
+
+    /// Select the layer above (`1`) or below (`-1`) the active layer
+    fn select_layer(&mut self, diff: isize) {
+        let idx = self.history.active_layer() as isize + diff;
+        if idx >= 0 && (idx as usize) < self.history.layers().len() {
+            self.commit_floating();
+            self.update_layers(|h| h.add_layer_select(idx as usize));
+        }
+    }
+
     fn set_anim_mode(&mut self, play_type: PlayType) {
         self.play_type = play_type;
         self.menubar.uncheck_all_children(MenuId::MenuAnimMode);
@@ -855,6 +1669,22 @@ impl Editor {
     }
 }
 
+fn digit_value(key: KeyCode) -> Option<u8> {
+    match key {
+        KeyCode::Digit0 => Some(0),
+        KeyCode::Digit1 => Some(1),
+        KeyCode::Digit2 => Some(2),
+        KeyCode::Digit3 => Some(3),
+        KeyCode::Digit4 => Some(4),
+        KeyCode::Digit5 => Some(5),
+        KeyCode::Digit6 => Some(6),
+        KeyCode::Digit7 => Some(7),
+        KeyCode::Digit8 => Some(8),
+        KeyCode::Digit9 => Some(9),
+        _ => None,
+    }
+}
+
 fn color_type(format: ImageFormat) -> ExtendedColorType {
     if format == ImageFormat::Jpeg {
         ExtendedColorType::Rgb8
@@ -926,6 +1756,27 @@ impl Scene<SceneResult, SceneName> for Editor {
             (WHITE, PixelFont::Standard4x5, Positioning::RightTop),
         );
 
+        if self.history.layers().len() > 1 {
+            let layer = &self.history.layers()[self.history.active_layer()];
+            graphics.draw_text(
+                &format!(
+                    "LAYER {}/{}{}\n{}",
+                    self.history.active_layer() + 1,
+                    self.history.layers().len(),
+                    if layer.visible { "" } else { " OFF" },
+                    layer.name
+                ),
+                TextPos::px(self.preview.bounds().top_left() - (0, 14)),
+                (WHITE, PixelFont::Standard4x5, WrappingStrategy::Cutoff(15)),
+            );
+        }
+
+        graphics.draw_text(
+            &self.status_text(mouse.xy),
+            TextPos::px(self.canvas.bounds().bottom_left() + (0, 2)),
+            (WHITE, PixelFont::Standard4x5, WrappingStrategy::None),
+        );
+
         render!(
             graphics,
             mouse,
@@ -964,9 +1815,6 @@ impl Scene<SceneResult, SceneName> for Editor {
         if self.data_loss_pending_alert.is_some() || self.one_way_pending_alert.is_some() {
             return;
         }
-        if self.warning.is_some() && matches!(key, KeyCode::Escape | KeyCode::Enter) {
-            self.warning = None;
-        }
         if self.last_undo < Instant::now() {
             if key == KeyCode::KeyZ
                 && !held.contains(&KeyCode::ShiftLeft)
@@ -989,13 +1837,33 @@ impl Scene<SceneResult, SceneName> for Editor {
                 self.redo();
             }
         }
+        if is_ctrl_down(held) && !self.speed.is_focused() && !self.is_playing {
+            match key {
+                KeyCode::KeyX => self.cut(),
+                KeyCode::KeyC => {
+                    self.canvas.copy_selection();
+                }
+                KeyCode::KeyV => self.paste(),
+                _ => {}
+            }
+        }
     }
 
     fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
-        if self.data_loss_pending_alert.is_some()
-            || self.one_way_pending_alert.is_some()
-            || self.warning.is_some()
-        {
+        let panned = key == KeyCode::Space && std::mem::take(&mut self.space_panned);
+        if self.data_loss_pending_alert.is_some() || self.one_way_pending_alert.is_some() {
+            return;
+        }
+        //closed on key up so the key isn't also used as a shortcut
+        if self.warning.is_some() {
+            if matches!(key, KeyCode::Escape | KeyCode::Enter) {
+                self.warning = None;
+            }
+            return;
+        }
+        if self.speed.is_focused() && matches!(key, KeyCode::Enter | KeyCode::NumpadEnter) {
+            self.speed.unfocus();
+            self.apply_speed();
             return;
         }
         self.speed.on_key_press(key, held);
@@ -1003,18 +1871,32 @@ impl Scene<SceneResult, SceneName> for Editor {
         if !self.speed.is_focused() {
             let shift_down =
                 held.contains(&KeyCode::ShiftLeft) || held.contains(&KeyCode::ShiftRight);
+            if shift_down
+                && matches!(
+                    key,
+                    KeyCode::ArrowUp
+                        | KeyCode::ArrowDown
+                        | KeyCode::ArrowLeft
+                        | KeyCode::ArrowRight
+                )
+            {
+                self.commit_floating();
+            }
             if shift_down && key == KeyCode::ArrowUp {
-                self.history.move_up().unwrap();
-                self.image_update();
+                self.transform(EditHistory::move_up);
             } else if shift_down && key == KeyCode::ArrowDown {
-                self.history.move_down().unwrap();
-                self.image_update();
+                self.transform(EditHistory::move_down);
             } else if shift_down && key == KeyCode::ArrowLeft {
-                self.history.move_left().unwrap();
-                self.image_update();
+                self.transform(EditHistory::move_left);
             } else if shift_down && key == KeyCode::ArrowRight {
-                self.history.move_right().unwrap();
-                self.image_update();
+                self.transform(EditHistory::move_right);
+            } else if matches!(key, KeyCode::Equal | KeyCode::NumpadAdd) && !is_ctrl_down(held) {
+                self.canvas.zoom_in(None);
+            } else if matches!(key, KeyCode::Minus | KeyCode::NumpadSubtract) && !is_ctrl_down(held)
+            {
+                self.canvas.zoom_out(None);
+            } else if !shift_down && !is_ctrl_down(held) && !panned {
+                self.on_shortcut(key);
             }
         }
     }
@@ -1047,6 +1929,7 @@ impl Scene<SceneResult, SceneName> for Editor {
                         DataLossAlertAction::Close => self.result = Pop(None),
                         DataLossAlertAction::Open => self.open_file(),
                         DataLossAlertAction::Import => self.import(),
+                        DataLossAlertAction::ImportSheet => self.pick_sheet(),
                     }
                 }
                 self.data_loss_pending_alert = None;
@@ -1059,15 +1942,7 @@ impl Scene<SceneResult, SceneName> for Editor {
                         OneWayAlertAction::ResizeCanvas => self.open_resize(),
                         OneWayAlertAction::Double => self.double_size(),
                         OneWayAlertAction::Trim => self.trim(),
-                        OneWayAlertAction::Simplify => {
-                            self.result = Push(
-                                false,
-                                SceneName::Simplify(
-                                    self.history.get_current_image().clone(),
-                                    self.preview.selected_background(),
-                                ),
-                            )
-                        }
+                        OneWayAlertAction::CropToSelection => self.crop_to_selection(),
                         OneWayAlertAction::ChangePalette(colors, selected) => {
                             self.change_palette(&colors, selected)
                         }
@@ -1085,6 +1960,16 @@ impl Scene<SceneResult, SceneName> for Editor {
         }
         if self.menubar.is_expanded() {
             if let Some(id) = self.menubar.on_mouse_click(down_at, mouse.xy) {
+                if !matches!(
+                    id,
+                    MenuId::MenuEditUndo
+                        | MenuId::MenuEditRedo
+                        | MenuId::MenuEditCut
+                        | MenuId::MenuEditCopy
+                        | MenuId::MenuEditPaste
+                ) {
+                    self.commit_floating();
+                }
                 match id {
                     MenuId::MenuFileQuit => {
                         if self.history.is_empty() {
@@ -1094,7 +1979,10 @@ impl Scene<SceneResult, SceneName> for Editor {
                         }
                     }
                     MenuId::MenuImageClear => {
-                        self.history.add_clear().unwrap();
+                        let frames = self.scope_frames();
+                        self.history
+                            .add_frames_edit(&frames, EditHistory::add_clear)
+                            .unwrap();
                         self.image_update();
                     }
                     MenuId::MenuFileSave => {
@@ -1123,6 +2011,36 @@ impl Scene<SceneResult, SceneName> for Editor {
                     }
                     MenuId::MenuEditUndo => self.undo(),
                     MenuId::MenuEditRedo => self.redo(),
+                    MenuId::MenuEditCut => self.cut(),
+                    MenuId::MenuEditCopy => {
+                        self.canvas.copy_selection();
+                    }
+                    MenuId::MenuEditPaste => self.paste(),
+                    MenuId::MenuEditSelectColor => {
+                        self.canvas.select_color(self.palette.get_selected_idx())
+                    }
+                    MenuId::MenuEditLockTransparency => {
+                        let mut filter = self.history.paint_filter().clone();
+                        filter.lock_transparency = !filter.lock_transparency;
+                        self.menubar.set_checked(
+                            MenuId::MenuEditLockTransparency,
+                            filter.lock_transparency,
+                        );
+                        self.history.set_paint_filter(filter);
+                    }
+                    MenuId::MenuEditWrap => {
+                        let wrap = !self.history.is_wrapping();
+                        self.history.set_wrapping(wrap);
+                        self.canvas.set_wrap(wrap);
+                        self.menubar.set_checked(MenuId::MenuEditWrap, wrap);
+                    }
+                    MenuId::MenuEditReplaceOnly => {
+                        let idx = match self.history.paint_filter().replace_only {
+                            Some(_) => None,
+                            None => Some(self.palette.get_selected_idx()),
+                        };
+                        self.set_replace_only(idx);
+                    }
                     MenuId::MenuPaletteEdit => {
                         let colors = self.canvas.get_image().get_palette().to_vec();
                         self.result = Push(
@@ -1157,52 +2075,40 @@ impl Scene<SceneResult, SceneName> for Editor {
                         }
                     }
                     MenuId::MenuImageFlipH => {
-                        self.history.flip_h().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::flip_h);
                     }
                     MenuId::MenuImageFlipV => {
-                        self.history.flip_v().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::flip_v);
                     }
                     MenuId::MenuImageRotCw90 => {
-                        self.history.rotate_cw_90().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_cw_90);
                     }
                     MenuId::MenuImageRotCw180 => {
-                        self.history.rotate_cw_180().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_cw_180);
                     }
                     MenuId::MenuImageRotCw270 => {
-                        self.history.rotate_cw_270().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_cw_270);
                     }
                     MenuId::MenuImageRotCcw90 => {
-                        self.history.rotate_ccw_90().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_ccw_90);
                     }
                     MenuId::MenuImageRotCcw180 => {
-                        self.history.rotate_ccw_180().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_ccw_180);
                     }
                     MenuId::MenuImageRotCcw270 => {
-                        self.history.rotate_ccw_270().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::rotate_ccw_270);
                     }
                     MenuId::MenuImageShiftUp => {
-                        self.history.move_up().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::move_up);
                     }
                     MenuId::MenuImageShiftDown => {
-                        self.history.move_down().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::move_down);
                     }
                     MenuId::MenuImageShiftLeft => {
-                        self.history.move_left().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::move_left);
                     }
                     MenuId::MenuImageShiftRight => {
-                        self.history.move_right().unwrap();
-                        self.image_update();
+                        self.transform(EditHistory::move_right);
                     }
                     MenuId::MenuFile => {}
                     MenuId::MenuEdit => {}
@@ -1211,6 +2117,16 @@ impl Scene<SceneResult, SceneName> for Editor {
                     MenuId::MenuImageRotCw => {}
                     MenuId::MenuImageRotCcw => {}
                     MenuId::MenuImageShift => {}
+                    MenuId::MenuImageOutline => {}
+                    MenuId::MenuImageOutlineOutside => {
+                        self.result = Push(false, SceneName::Effect(EffectKind::OutlineOutside))
+                    }
+                    MenuId::MenuImageOutlineInside => {
+                        self.result = Push(false, SceneName::Effect(EffectKind::OutlineInside))
+                    }
+                    MenuId::MenuImageDropShadow => {
+                        self.result = Push(false, SceneName::Effect(EffectKind::DropShadow))
+                    }
                     MenuId::MenuCanvas => {}
                     MenuId::MenuCanvasResize => {
                         if self.history.is_empty() {
@@ -1226,6 +2142,49 @@ impl Scene<SceneResult, SceneName> for Editor {
                             self.one_way_pending_alert = Some(OneWayAlertAction::Trim);
                         }
                     }
+                    MenuId::MenuCanvasCrop => {
+                        if self.history.is_empty() {
+                            self.crop_to_selection();
+                        } else {
+                            self.one_way_pending_alert = Some(OneWayAlertAction::CropToSelection);
+                        }
+                    }
+                    MenuId::MenuCanvasZoom => {}
+                    MenuId::MenuCanvasZoomIn => self.canvas.zoom_in(None),
+                    MenuId::MenuCanvasZoomOut => self.canvas.zoom_out(None),
+                    MenuId::MenuCanvasZoomFit => self.canvas.fit(),
+                    MenuId::MenuCanvasPixelGrid => {
+                        self.prefs.data.show_pixel_grid = !self.prefs.data.show_pixel_grid;
+                        self.prefs.save();
+                        self.menubar.set_checked(
+                            MenuId::MenuCanvasPixelGrid,
+                            self.prefs.data.show_pixel_grid,
+                        );
+                        self.update_grids();
+                    }
+                    MenuId::MenuCanvasTiledPreview => {
+                        self.tiled_preview = !self.tiled_preview;
+                        self.menubar
+                            .set_checked(MenuId::MenuCanvasTiledPreview, self.tiled_preview);
+                        self.preview.set_tiled(self.tiled_preview);
+                    }
+                    MenuId::MenuCanvasReference => {}
+                    MenuId::MenuCanvasReferenceLoad => self.load_reference(),
+                    MenuId::MenuCanvasReferenceAdjust => {
+                        if let Some(reference) = &self.reference {
+                            self.result =
+                                Push(false, SceneName::Reference(reference.settings.clone()));
+                        }
+                    }
+                    MenuId::MenuCanvasReferenceClear => self.set_reference(None),
+                    MenuId::MenuCanvasTileGrid => {}
+                    MenuId::MenuCanvasTileGridNone => self.set_tile_grid(None),
+                    MenuId::MenuCanvasTileGrid8 => self.set_tile_grid(Some((8, 8))),
+                    MenuId::MenuCanvasTileGrid16 => self.set_tile_grid(Some((16, 16))),
+                    MenuId::MenuCanvasTileGridCustom => {
+                        let (w, h) = self.prefs.data.tile_grid.unwrap_or((8, 8));
+                        self.result = Push(false, SceneName::TileGrid(w, h));
+                    }
                     MenuId::MenuImageDoubleSize => {
                         if self.history.is_empty() {
                             self.double_size();
@@ -1237,28 +2196,36 @@ impl Scene<SceneResult, SceneName> for Editor {
                     MenuId::MenuFileExportJpeg => self.export(ImageFormat::Jpeg),
                     MenuId::MenuFileExportBmp => self.export(ImageFormat::Bmp),
                     MenuId::MenuFileExportIco => self.export(ImageFormat::Ico),
+                    MenuId::MenuFileExportGif => self.export_gif(),
+                    MenuId::MenuFileExportSpriteSheet => {
+                        self.result = Push(false, SceneName::SpriteSheet)
+                    }
                     MenuId::MenuFileExportTga => self.export(ImageFormat::Tga),
                     MenuId::MenuFileExport => {}
-                    MenuId::MenuFileImport => {
+                    MenuId::MenuFileImport => {}
+                    MenuId::MenuFileImportImage => {
                         if self.history.is_empty() {
                             self.import();
                         } else {
                             self.data_loss_pending_alert = Some(DataLossAlertAction::Import);
                         }
                     }
-                    MenuId::MenuPaletteSimplify => {
+                    MenuId::MenuFileImportSpriteSheet => {
                         if self.history.is_empty() {
-                            self.result = Push(
-                                false,
-                                SceneName::Simplify(
-                                    self.history.get_current_image().clone(),
-                                    self.preview.selected_background(),
-                                ),
-                            )
+                            self.pick_sheet();
                         } else {
-                            self.one_way_pending_alert = Some(OneWayAlertAction::Simplify);
+                            self.data_loss_pending_alert = Some(DataLossAlertAction::ImportSheet);
                         }
                     }
+                    MenuId::MenuPaletteSimplify => {
+                        self.result = Push(
+                            false,
+                            SceneName::Simplify(
+                                self.history.get_current_frame(),
+                                self.preview.selected_background(),
+                            ),
+                        )
+                    }
                     MenuId::MenuCanvasBackground => {}
                     MenuId::MenuCanvasBackgroundGreyCheck => {
                         self.set_bg_color(BackgroundColors::GreyCheck)
@@ -1285,50 +2252,60 @@ impl Scene<SceneResult, SceneName> for Editor {
                     MenuId::MenuAnimModeLoop => self.set_anim_mode(PlayType::Loops),
                     MenuId::MenuAnimModeLoopReverse => self.set_anim_mode(PlayType::LoopsReversed),
                     MenuId::MenuAnimModeLoopBoth => self.set_anim_mode(PlayType::LoopsBoth),
+                    MenuId::MenuAnimDurationAll => self.apply_speed_to_all(),
+                    MenuId::MenuAnimScope => {}
+                    MenuId::MenuAnimScopeCurrent => self.set_frame_scope(FrameScope::Current),
+                    MenuId::MenuAnimScopeAll => self.set_frame_scope(FrameScope::All),
+                    MenuId::MenuAnimScopeRange => self.set_frame_scope(FrameScope::Range),
+                    MenuId::MenuAnimMoveLeft => self.move_frame(EditHistory::move_frame_left),
+                    MenuId::MenuAnimMoveRight => self.move_frame(EditHistory::move_frame_right),
+                    MenuId::MenuAnimTween => self.open_tweens(),
+                    MenuId::MenuAnimOnionSkin => {
+                        self.prefs.data.onion_skin = !self.prefs.data.onion_skin;
+                        self.prefs.save();
+                        self.menubar
+                            .set_checked(MenuId::MenuAnimOnionSkin, self.prefs.data.onion_skin);
+                        self.update_onion_skin();
+                    }
+                    MenuId::MenuAnimOnionFrames => {}
+                    MenuId::MenuAnimOnionFrames1 => self.set_onion_frames(1),
+                    MenuId::MenuAnimOnionFrames2 => self.set_onion_frames(2),
+                    MenuId::MenuAnimOnionFrames3 => self.set_onion_frames(3),
+                    MenuId::MenuAnimOnionOpacity => {}
+                    MenuId::MenuAnimOnionOpacity25 => self.set_onion_opacity(25),
+                    MenuId::MenuAnimOnionOpacity50 => self.set_onion_opacity(50),
+                    MenuId::MenuAnimOnionOpacity75 => self.set_onion_opacity(75),
+                    MenuId::MenuLayer => {}
+                    MenuId::MenuLayerAdd => self.update_layers(EditHistory::add_layer),
+                    MenuId::MenuLayerDelete => self.update_layers(EditHistory::remove_layer),
+                    MenuId::MenuLayerMoveUp => self.update_layers(|h| h.move_layer(true)),
+                    MenuId::MenuLayerMoveDown => self.update_layers(|h| h.move_layer(false)),
+                    MenuId::MenuLayerRename => {
+                        let name = self.history.layers()[self.history.active_layer()]
+                            .name
+                            .clone();
+                        self.result = Push(false, SceneName::LayerName(name));
+                    }
+                    MenuId::MenuLayerVisible => {
+                        let visible = !self.history.layers()[self.history.active_layer()].visible;
+                        self.update_layers(|h| h.set_layer_visible(visible));
+                    }
+                    MenuId::MenuLayerSelectAbove => self.select_layer(1),
+                    MenuId::MenuLayerSelectBelow => self.select_layer(-1),
                 }
             }
             return;
         }
         if let Some(tool) = self.tools.on_mouse_click(down_at, mouse.xy) {
+            self.commit_floating();
             self.canvas.set_tool(tool)
         }
         if self.play_pause.on_mouse_click(down_at, mouse.xy) {
-            if self.is_playing {
-                self.is_playing = false;
-                self.add_frame.set_state(ViewState::Normal);
-                self.speed.set_state(ViewState::Normal);
-                self.remove_frame.set_state(ViewState::Normal);
-                self.copy_frame.set_state(ViewState::Normal);
-                self.timeline.set_state(ViewState::Normal);
-                self.palette.set_state(ViewState::Normal);
-                self.canvas.set_state(ViewState::Normal);
-                self.menubar.set_state(MenuId::MenuEdit, ViewState::Normal);
-                self.menubar.set_state(MenuId::MenuImage, ViewState::Normal);
-                self.menubar
-                    .set_state(MenuId::MenuPalette, ViewState::Normal);
-                self.menubar.set_state(MenuId::MenuAnim, ViewState::Normal);
-            } else {
-                self.is_playing = true;
-                self.anim_frame_idx = 0;
-                self.next_frame_swap = self.speed.content().parse::<f64>().unwrap_or(1.0);
-                self.add_frame.set_state(ViewState::Disabled);
-                self.speed.set_state(ViewState::Disabled);
-                self.remove_frame.set_state(ViewState::Disabled);
-                self.copy_frame.set_state(ViewState::Disabled);
-                self.timeline.set_state(ViewState::Disabled);
-                self.palette.set_state(ViewState::Disabled);
-                self.canvas.set_state(ViewState::Disabled);
-                self.menubar
-                    .set_state(MenuId::MenuEdit, ViewState::Disabled);
-                self.menubar
-                    .set_state(MenuId::MenuImage, ViewState::Disabled);
-                self.menubar
-                    .set_state(MenuId::MenuPalette, ViewState::Disabled);
-                self.menubar
-                    .set_state(MenuId::MenuAnim, ViewState::Disabled);
-            }
+            self.commit_floating();
+            self.set_playing(!self.is_playing);
         }
         if self.add_frame.on_mouse_click(down_at, mouse.xy) {
+            self.commit_floating();
             self.history.add_blank_frame().unwrap();
             self.timeline
                 .set_frames(self.history.get_images(), self.history.active_frame());
@@ -1338,6 +2315,7 @@ impl Scene<SceneResult, SceneName> for Editor {
             self.save_data.index = None;
         }
         if self.remove_frame.on_mouse_click(down_at, mouse.xy) {
+            self.commit_floating();
             self.history.remove_frame().unwrap();
             self.timeline
                 .set_frames(self.history.get_images(), self.history.active_frame());
@@ -1347,6 +2325,7 @@ impl Scene<SceneResult, SceneName> for Editor {
             }
         }
         if self.copy_frame.on_mouse_click(down_at, mouse.xy) {
+            self.commit_floating();
             self.history.add_duplicate_frame().unwrap();
             self.timeline
                 .set_frames(self.history.get_images(), self.history.active_frame());
@@ -1355,22 +2334,46 @@ impl Scene<SceneResult, SceneName> for Editor {
             }
             self.save_data.index = None;
         }
+        let speed_was_focused = self.speed.is_focused();
         self.speed.on_mouse_click(down_at, mouse.xy);
+        if speed_was_focused && !self.speed.is_focused() {
+            self.apply_speed();
+        }
         if self.palette.on_mouse_click(mouse.xy) {
             self.canvas.set_color_index(self.palette.get_selected_idx());
         }
         self.canvas.on_mouse_up(mouse.xy, &mut self.history);
         let background_color = self.preview.on_mouse_click(mouse.xy);
         self.timeline.set_background(background_color);
-        if let Some(frame) = self.timeline.on_mouse_click(mouse.xy) {
-            self.history.add_frame_select(frame).unwrap();
+        match self.timeline.on_mouse_click(down_at, mouse.xy) {
+            Some(TimelineClick::Frame(frame))
+                if keys.contains(&KeyCode::ShiftLeft) || keys.contains(&KeyCode::ShiftRight) =>
+            {
+                let active = self.history.active_frame();
+                self.frame_range = Some((active.min(frame), active.max(frame)));
+                self.timeline.set_active(active);
+                self.update_frame_range();
+            }
+            Some(TimelineClick::Frame(frame)) => {
+                self.commit_floating();
+                self.history.add_frame_select(frame).unwrap();
+            }
+            Some(TimelineClick::Duration(frame)) => {
+                self.commit_floating();
+                self.history.add_frame_select(frame).unwrap();
+                self.speed.focus();
+            }
+            Some(TimelineClick::Move { from, to }) => {
+                self.move_frame(|h| h.move_frame(from, to));
+            }
+            None => {}
         }
+        self.update_durations();
+        self.update_onion_skin();
 
-        self.canvas
-            .set_image(self.history.get_current_image().clone());
-        self.preview.set_image(self.canvas.get_image().clone());
-        self.timeline
-            .update_frame(self.history.get_current_image().clone());
+        self.update_canvas_image();
+        self.preview.set_image(self.history.get_current_frame());
+        self.timeline.update_frame(self.history.get_current_frame());
     }
 
     fn on_scroll(
@@ -1382,6 +2385,9 @@ impl Scene<SceneResult, SceneName> for Editor {
     ) {
         self.palette.on_scroll(mouse.xy, y_diff);
         self.timeline.on_scroll(mouse.xy, x_diff);
+        if !self.menubar.is_expanded() {
+            self.canvas.on_scroll(mouse.xy, y_diff);
+        }
     }
 
     fn update(
@@ -1392,23 +2398,38 @@ impl Scene<SceneResult, SceneName> for Editor {
         _: &Window,
     ) -> SceneUpdateResult<SceneResult, SceneName> {
         self.speed.update(timing);
+        self.canvas.update(timing);
 
         self.canvas.set_shift_pressed(
             held.contains(&KeyCode::ShiftLeft) || held.contains(&KeyCode::ShiftRight),
         );
+        self.canvas
+            .set_alt_pressed(held.contains(&KeyCode::AltLeft) || held.contains(&KeyCode::AltRight));
+        self.canvas.on_mouse_move(mouse.xy);
+        self.sync_selection();
+        let view_moving = self.data_loss_pending_alert.is_none()
+            && self.one_way_pending_alert.is_none()
+            && self.warning.is_none()
+            && !self.menubar.is_expanded()
+            && self
+                .canvas
+                .on_view_drag(mouse, held.contains(&KeyCode::Space));
+        if view_moving && held.contains(&KeyCode::Space) {
+            self.space_panned = true;
+        }
 
         if self.is_playing {
             self.next_frame_swap -= timing.fixed_time_step;
             if self.next_frame_swap <= 0.0 {
-                self.next_frame_swap = self.speed.content().parse::<f64>().unwrap_or(1.0);
-                self.anim_frame_idx += 1;
-                if self.anim_frame_idx >= self.history.frame_count() {
-                    self.anim_frame_idx = 0;
+                if self
+                    .playback
+                    .advance(self.play_type, self.history.frame_count())
+                {
+                    self.next_frame_swap = self.frame_seconds(self.playback.frame());
+                    self.show_playback_frame();
+                } else {
+                    self.set_playing(false);
                 }
-                let image = self.history.get_image(self.anim_frame_idx);
-                self.canvas.set_image(image.clone());
-                self.preview.set_image(image.clone());
-                self.timeline.set_active(self.anim_frame_idx)
             }
         } else {
             let frame_count = self.history.frame_count();
@@ -1430,13 +2451,12 @@ impl Scene<SceneResult, SceneName> for Editor {
                 && self.one_way_pending_alert.is_none()
                 && self.warning.is_none()
                 && !self.menubar.is_expanded()
+                && !view_moving
                 && self.canvas.on_mouse_down(mouse.xy, &mut self.history)
             {
-                self.canvas
-                    .set_image(self.history.get_current_image().clone());
-                self.preview.set_image(self.canvas.get_image().clone());
-                self.timeline
-                    .update_frame(self.history.get_current_image().clone());
+                self.update_canvas_image();
+                self.preview.set_image(self.history.get_current_frame());
+                self.timeline.update_frame(self.history.get_current_frame());
                 if let Some(c) = self.history.is_first_event() {
                     match (self.preview.selected_background(), c) {
                         (0, WHITE) => {
@@ -1459,20 +2479,43 @@ impl Scene<SceneResult, SceneName> for Editor {
 
         if self.history.frame_count() == 1 {
             self.menubar
-                .set_state(MenuId::MenuCanvas, ViewState::Normal);
-            self.menubar
-                .set_state(MenuId::MenuImageDoubleSize, ViewState::Normal);
-            self.menubar
-                .set_state(MenuId::MenuPaletteSimplify, ViewState::Normal);
+                .set_state(MenuId::MenuCanvasResize, ViewState::Normal);
         } else {
             self.menubar
-                .set_state(MenuId::MenuCanvas, ViewState::Disabled);
-            self.menubar
-                .set_state(MenuId::MenuImageDoubleSize, ViewState::Disabled);
-            self.menubar
-                .set_state(MenuId::MenuPaletteSimplify, ViewState::Disabled);
+                .set_state(MenuId::MenuCanvasResize, ViewState::Disabled);
         }
 
+        let layer = self.history.active_layer();
+        let layer_count = self.history.layers().len();
+        let enabled = |enabled: bool| {
+            if enabled {
+                ViewState::Normal
+            } else {
+                ViewState::Disabled
+            }
+        };
+        self.menubar
+            .set_state(MenuId::MenuLayerDelete, enabled(layer_count > 1));
+        self.menubar
+            .set_state(MenuId::MenuLayerMoveUp, enabled(layer + 1 < layer_count));
+        self.menubar.set_state(
+            MenuId::MenuLayerSelectAbove,
+            enabled(layer + 1 < layer_count),
+        );
+        self.menubar
+            .set_state(MenuId::MenuLayerMoveDown, enabled(layer > 0));
+        self.menubar
+            .set_state(MenuId::MenuLayerSelectBelow, enabled(layer > 0));
+
+        self.menubar.set_state(
+            MenuId::MenuCanvasCrop,
+            if self.canvas.selection().is_some() {
+                ViewState::Normal
+            } else {
+                ViewState::Disabled
+            },
+        );
+
         self.menubar.on_mouse_move(mouse.xy);
 
         self.result.clone()
@@ -1513,13 +2556,53 @@ impl Scene<SceneResult, SceneName> for Editor {
                         &self.alert_style,
                     ));
                 }
-                SceneResult::Simplify(img) => {
-                    debug!("Palette simplified to {:?}", img.get_palette());
-                    self.history = EditHistory::new(vec![img]);
+                SceneResult::Effect(effect) => {
+                    let color = self.palette.get_selected_idx();
+                    let frames = self.scope_frames();
+                    let result = self
+                        .history
+                        .add_frames_edit(&frames, |history| match effect {
+                            Effect::Outline { inside, diagonals } => {
+                                history.add_outline(inside, diagonals, color)
+                            }
+                            Effect::DropShadow(x, y) => history.add_drop_shadow((x, y), color),
+                        });
+                    if let Err(e) = result {
+                        error!("Error applying {effect:?}: {e:?}");
+                    }
+                    self.image_update();
+                }
+                SceneResult::Simplify(palette, mapping) => {
+                    debug!("Palette simplified to {palette:?}");
+                    let replace_only = self
+                        .history
+                        .paint_filter()
+                        .replace_only
+                        .and_then(|idx| mapping.get(idx as usize).copied());
+                    if let Err(e) = self.history.add_palette_remap(palette, mapping) {
+                        error!("Error simplifying palette: {e:?}");
+                    } else {
+                        self.set_replace_only(replace_only);
+                    }
                     self.image_update();
                     self.palette.set_color_index(0);
                     self.canvas.set_color_index(0);
                 }
+                SceneResult::TileGrid(w, h) => self.set_tile_grid(Some((w, h))),
+                SceneResult::SpriteSheet(columns, padding) => {
+                    self.export_sprite_sheet(columns, padding)
+                }
+                SceneResult::ImportSheet(slicing) => self.import_sheet(slicing),
+                SceneResult::Tween(start, end, count, kind) => {
+                    self.add_tweens(start, end, count, kind)
+                }
+                SceneResult::LayerName(name) => self.update_layers(|h| h.rename_layer(&name)),
+                SceneResult::Reference(settings) => {
+                    if let Some(mut reference) = self.reference.take() {
+                        reference.settings = settings;
+                        self.set_reference(Some(reference));
+                    }
+                }
             }
         }
         self.result = Nothing;
@@ -17,6 +17,13 @@ pub enum MenuId {
     MenuFileQuit,
     MenuEditUndo,
     MenuEditRedo,
+    MenuEditCut,
+    MenuEditCopy,
+    MenuEditPaste,
+    MenuEditSelectColor,
+    MenuEditLockTransparency,
+    MenuEditReplaceOnly,
+    MenuEditWrap,
     MenuImageFlipV,
     MenuImageFlipH,
     MenuImageClear,
@@ -38,6 +45,22 @@ pub enum MenuId {
     MenuCanvas,
     MenuCanvasResize,
     MenuCanvasTrim,
+    MenuCanvasCrop,
+    MenuCanvasZoom,
+    MenuCanvasZoomIn,
+    MenuCanvasZoomOut,
+    MenuCanvasZoomFit,
+    MenuCanvasPixelGrid,
+    MenuCanvasTiledPreview,
+    MenuCanvasReference,
+    MenuCanvasReferenceLoad,
+    MenuCanvasReferenceAdjust,
+    MenuCanvasReferenceClear,
+    MenuCanvasTileGrid,
+    MenuCanvasTileGridNone,
+    MenuCanvasTileGrid8,
+    MenuCanvasTileGrid16,
+    MenuCanvasTileGridCustom,
     MenuCanvasBackground,
     MenuCanvasBackgroundGreyCheck,
     MenuCanvasBackgroundPurpleCheck,
@@ -46,13 +69,21 @@ pub enum MenuId {
     MenuCanvasBackgroundSolidBlack,
     MenuCanvasBackgroundSolidWhite,
     MenuImageDoubleSize,
+    MenuImageOutline,
+    MenuImageOutlineOutside,
+    MenuImageOutlineInside,
+    MenuImageDropShadow,
     MenuFileExport,
     MenuFileExportPng,
     MenuFileExportJpeg,
     MenuFileExportTga,
     MenuFileExportBmp,
     MenuFileExportIco,
+    MenuFileExportGif,
+    MenuFileExportSpriteSheet,
     MenuFileImport,
+    MenuFileImportImage,
+    MenuFileImportSpriteSheet,
     MenuPaletteSimplify,
     MenuAnim,
     MenuAnimMode,
@@ -61,6 +92,32 @@ pub enum MenuId {
     MenuAnimModeLoop,
     MenuAnimModeLoopReverse,
     MenuAnimModeLoopBoth,
+    MenuAnimDurationAll,
+    MenuAnimScope,
+    MenuAnimScopeCurrent,
+    MenuAnimScopeAll,
+    MenuAnimScopeRange,
+    MenuAnimMoveLeft,
+    MenuAnimMoveRight,
+    MenuAnimTween,
+    MenuAnimOnionSkin,
+    MenuAnimOnionFrames,
+    MenuAnimOnionFrames1,
+    MenuAnimOnionFrames2,
+    MenuAnimOnionFrames3,
+    MenuAnimOnionOpacity,
+    MenuAnimOnionOpacity25,
+    MenuAnimOnionOpacity50,
+    MenuAnimOnionOpacity75,
+    MenuLayer,
+    MenuLayerAdd,
+    MenuLayerDelete,
+    MenuLayerMoveUp,
+    MenuLayerMoveDown,
+    MenuLayerRename,
+    MenuLayerVisible,
+    MenuLayerSelectAbove,
+    MenuLayerSelectBelow,
 }
 
 pub(super) fn create_menubar(style: &UiStyle, settings: &AppPrefs<Settings>) -> MenuBar<MenuId> {
@@ -78,7 +135,14 @@ pub(super) fn create_menubar(style: &UiStyle, settings: &AppPrefs<Settings>) ->
                     MenuBarItem::new_button(MenuFileOpen, "Open"),
                     MenuBarItem::new_button(MenuFileSave, "Save"),
                     MenuBarItem::new_button(MenuFileSaveAs, "Save As"),
-                    MenuBarItem::new_button(MenuFileImport, "Import"),
+                    MenuBarItem::new_menu(
+                        MenuFileImport,
+                        "Import",
+                        &[
+                            (MenuFileImportImage, "Image"),
+                            (MenuFileImportSpriteSheet, "Sprite sheet"),
+                        ],
+                    ),
                     MenuBarItem::new_menu(
                         MenuFileExport,
                         "Export",
@@ -88,15 +152,31 @@ pub(super) fn create_menubar(style: &UiStyle, settings: &AppPrefs<Settings>) ->
                             (MenuFileExportTga, "TGA"),
                             (MenuFileExportBmp, "BMP"),
                             (MenuFileExportIco, "Icon"),
+                            (MenuFileExportGif, "GIF (animated)"),
+                            (MenuFileExportSpriteSheet, "Sprite sheet"),
                         ],
                     ),
                     MenuBarItem::new_button(MenuFileQuit, "Quit"),
                 ],
             ),
-            MenuBarItem::new_menu(
+            MenuBarItem::new(
                 MenuEdit,
                 "Edit",
-                &[(MenuEditUndo, "Undo"), (MenuEditRedo, "Redo")],
+                vec![
+                    MenuBarItem::new_button(MenuEditUndo, "Undo"),
+                    MenuBarItem::new_button(MenuEditRedo, "Redo"),
+                    MenuBarItem::new_button(MenuEditCut, "Cut"),
+                    MenuBarItem::new_button(MenuEditCopy, "Copy"),
+                    MenuBarItem::new_button(MenuEditPaste, "Paste"),
+                    MenuBarItem::new_button(MenuEditSelectColor, "Select color"),
+                    MenuBarItem::new_checkable(
+                        MenuEditLockTransparency,
+                        "Lock transparency",
+                        false,
+                    ),
+                    MenuBarItem::new_checkable(MenuEditReplaceOnly, "Replace only current", false),
+                    MenuBarItem::new_checkable(MenuEditWrap, "Wrap around", false),
+                ],
             ),
             MenuBarItem::new(
                 MenuCanvas,
@@ -104,6 +184,47 @@ pub(super) fn create_menubar(style: &UiStyle, settings: &AppPrefs<Settings>) ->
                 vec![
                     MenuBarItem::new_button(MenuCanvasResize, "Resize"),
                     MenuBarItem::new_button(MenuCanvasTrim, "Trim"),
+                    MenuBarItem::new_button(MenuCanvasCrop, "Crop to selection"),
+                    MenuBarItem::new_menu(
+                        MenuCanvasZoom,
+                        "Zoom",
+                        &[
+                            (MenuCanvasZoomIn, "In"),
+                            (MenuCanvasZoomOut, "Out"),
+                            (MenuCanvasZoomFit, "Fit"),
+                        ],
+                    ),
+                    MenuBarItem::new_checkable(
+                        MenuCanvasPixelGrid,
+                        "Pixel grid",
+                        settings.data.show_pixel_grid,
+                    ),
+                    MenuBarItem::new_checkable(MenuCanvasTiledPreview, "Tiled preview", false),
+                    MenuBarItem::new_menu(
+                        MenuCanvasReference,
+                        "Reference",
+                        &[
+                            (MenuCanvasReferenceLoad, "Load"),
+                            (MenuCanvasReferenceAdjust, "Adjust"),
+                            (MenuCanvasReferenceClear, "Clear"),
+                        ],
+                    ),
+                    MenuBarItem::new_options(
+                        MenuCanvasTileGrid,
+                        "Tile grid",
+                        &[
+                            (MenuCanvasTileGridNone, "None"),
+                            (MenuCanvasTileGrid8, "8x8"),
+                            (MenuCanvasTileGrid16, "16x16"),
+                            (MenuCanvasTileGridCustom, "Custom"),
+                        ],
+                        match settings.data.tile_grid {
+                            None => 0,
+                            Some((8, 8)) => 1,
+                            Some((16, 16)) => 2,
+                            Some(_) => 3,
+                        },
+                    ),
                     MenuBarItem::new_options(
                         MenuCanvasBackground,
                         "Background",
@@ -146,28 +267,93 @@ pub(super) fn create_menubar(style: &UiStyle, settings: &AppPrefs<Settings>) ->
                             (MenuImageShiftRight, "Right"),
                         ],
                     ),
+                    MenuBarItem::new_menu(
+                        MenuImageOutline,
+                        "Outline",
+                        &[
+                            (MenuImageOutlineOutside, "Outside"),
+                            (MenuImageOutlineInside, "Inside"),
+                        ],
+                    ),
+                    MenuBarItem::new_button(MenuImageDropShadow, "Drop shadow"),
                     MenuBarItem::new_button(MenuImageDoubleSize, "Double size"),
                     MenuBarItem::new_button(MenuImageClear, "Clear"),
                 ],
             ),
+            MenuBarItem::new(
+                MenuLayer,
+                "Layer",
+                vec![
+                    MenuBarItem::new_button(MenuLayerAdd, "Add"),
+                    MenuBarItem::new_button(MenuLayerDelete, "Delete"),
+                    MenuBarItem::new_button(MenuLayerMoveUp, "Move up"),
+                    MenuBarItem::new_button(MenuLayerMoveDown, "Move down"),
+                    MenuBarItem::new_button(MenuLayerRename, "Rename"),
+                    MenuBarItem::new_checkable(MenuLayerVisible, "Visible", true),
+                    MenuBarItem::new_button(MenuLayerSelectAbove, "Select above"),
+                    MenuBarItem::new_button(MenuLayerSelectBelow, "Select below"),
+                ],
+            ),
             MenuBarItem::new(
                 MenuAnim,
                 "Anim",
-                vec![MenuBarItem::new_options(
-                    MenuAnimMode,
-                    "Mode",
-                    &[
-                        (MenuAnimModeLoop, "Loop"),
-                        (MenuAnimModeLoopReverse, "Loop Reversed"),
-                        (MenuAnimModeLoopBoth, "Loop both ways"),
-                        (MenuAnimModeOnce, "Once"),
-                        (
-                            MenuAnimModeOnceReverse,
-                            "Once reverse lorem ipsum offscreen test",
-                        ),
-                    ],
-                    0,
-                )],
+                vec![
+                    MenuBarItem::new_options(
+                        MenuAnimMode,
+                        "Mode",
+                        &[
+                            (MenuAnimModeLoop, "Loop"),
+                            (MenuAnimModeLoopReverse, "Loop Reversed"),
+                            (MenuAnimModeLoopBoth, "Loop both ways"),
+                            (MenuAnimModeOnce, "Once"),
+                            (MenuAnimModeOnceReverse, "Once Reversed"),
+                        ],
+                        0,
+                    ),
+                    MenuBarItem::new_button(MenuAnimDurationAll, "Apply duration to all"),
+                    MenuBarItem::new_options(
+                        MenuAnimScope,
+                        "Edit frames",
+                        &[
+                            (MenuAnimScopeCurrent, "Current"),
+                            (MenuAnimScopeAll, "All"),
+                            (MenuAnimScopeRange, "Range (Shift+click)"),
+                        ],
+                        0,
+                    ),
+                    MenuBarItem::new_button(MenuAnimMoveLeft, "Move frame left"),
+                    MenuBarItem::new_button(MenuAnimMoveRight, "Move frame right"),
+                    MenuBarItem::new_button(MenuAnimTween, "Generate in-betweens"),
+                    MenuBarItem::new_checkable(
+                        MenuAnimOnionSkin,
+                        "Onion skin",
+                        settings.data.onion_skin,
+                    ),
+                    MenuBarItem::new_options(
+                        MenuAnimOnionFrames,
+                        "Onion frames",
+                        &[
+                            (MenuAnimOnionFrames1, "1"),
+                            (MenuAnimOnionFrames2, "2"),
+                            (MenuAnimOnionFrames3, "3"),
+                        ],
+                        (settings.data.onion_skin_frames.clamp(1, 3) - 1) as usize,
+                    ),
+                    MenuBarItem::new_options(
+                        MenuAnimOnionOpacity,
+                        "Onion opacity",
+                        &[
+                            (MenuAnimOnionOpacity25, "25%"),
+                            (MenuAnimOnionOpacity50, "50%"),
+                            (MenuAnimOnionOpacity75, "75%"),
+                        ],
+                        match settings.data.onion_skin_opacity {
+                            0..=37 => 0,
+                            38..=62 => 1,
+                            _ => 2,
+                        },
+                    ),
+                ],
             ),
             MenuBarItem::new_menu(
                 MenuPalette,
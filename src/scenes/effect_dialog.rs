@@ -0,0 +1,234 @@
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EffectKind {
+    OutlineOutside,
+    OutlineInside,
+    DropShadow,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Effect {
+    Outline { inside: bool, diagonals: bool },
+    DropShadow(isize, isize),
+}
+
+pub struct EffectDialog {
+    result: SUR,
+    kind: EffectKind,
+    bg: ShapeCollection,
+    title: Label,
+    offset: Label,
+    x: TextField,
+    y: TextField,
+    diagonals: Checkbox,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl EffectDialog {
+    pub fn new(kind: EffectKind, style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let name = match kind {
+            EffectKind::OutlineOutside => "Outline outside",
+            EffectKind::OutlineInside => "Outline inside",
+            EffectKind::DropShadow => "Drop shadow",
+        };
+        let mut title = Label::singleline(
+            name,
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut offset = Label::singleline(
+            "Offset (x, y)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut x = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            "1",
+            &[TextFilter::NegativeNumbers],
+            &style.text_field,
+        );
+        let mut y = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            "1",
+            &[TextFilter::NegativeNumbers],
+            &style.text_field,
+        );
+        let mut diagonals = Checkbox::new((0, 0), "Include diagonals", false, &style.checkbox);
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Apply", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, diagonals, align_left, px!(6));
+        layout!(context, diagonals, top_to_bottom_of title, px!(12));
+
+        layout!(context, offset, align_left, px!(6));
+        layout!(context, offset, top_to_bottom_of title, px!(12));
+
+        layout!(context, x, align_left, px!(6));
+        layout!(context, x, top_to_bottom_of offset, px!(4));
+
+        layout!(context, y, left_to_right_of x, px!(6));
+        layout!(context, y, top_to_top_of x);
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        if kind == EffectKind::DropShadow {
+            x.focus();
+        }
+
+        Box::new(EffectDialog {
+            result: Nothing,
+            kind,
+            bg,
+            title,
+            offset,
+            x,
+            y,
+            diagonals,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl EffectDialog {
+    fn verify(&mut self) {
+        let effect = match self.kind {
+            EffectKind::OutlineOutside => Effect::Outline {
+                inside: false,
+                diagonals: self.diagonals.is_checked(),
+            },
+            EffectKind::OutlineInside => Effect::Outline {
+                inside: true,
+                diagonals: self.diagonals.is_checked(),
+            },
+            EffectKind::DropShadow => {
+                let x = self.x.content().parse::<i8>();
+                let y = self.y.content().parse::<i8>();
+                if let (Ok(x), Ok(y)) = (x, y) {
+                    Effect::DropShadow(x as isize, y as isize)
+                } else {
+                    self.alert = Some(Alert::new_warning(
+                        &["Invalid offset, both", "must be between -128 and 127"],
+                        WIDTH,
+                        HEIGHT,
+                        &self.alert_style,
+                    ));
+                    return;
+                }
+            }
+        };
+        self.result = Pop(Some(SceneResult::Effect(effect)));
+    }
+}
+
+impl Scene<SceneResult, SceneName> for EffectDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(graphics, mouse, self.title, self.submit, self.cancel);
+        if self.kind == EffectKind::DropShadow {
+            render!(graphics, mouse, self.offset, self.x, self.y);
+        } else {
+            self.diagonals.render(graphics, mouse);
+        }
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        if key == KeyCode::Escape {
+            self.result = Pop(None);
+        }
+        if self.kind != EffectKind::DropShadow {
+            return;
+        }
+        self.x.on_key_press(key, held);
+        self.y.on_key_press(key, held);
+        if key == KeyCode::Tab && self.x.is_focused() {
+            self.x.unfocus();
+            self.y.focus();
+        } else if key == KeyCode::Tab && self.y.is_focused() {
+            self.y.unfocus();
+            self.x.focus();
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                if self.kind == EffectKind::DropShadow {
+                    self.x.on_mouse_click(down_at, mouse.xy);
+                    self.y.on_mouse_click(down_at, mouse.xy);
+                } else {
+                    let _ = self.diagonals.on_mouse_click(down_at, mouse.xy);
+                }
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        self.x.update(timing);
+        self.y.update(timing);
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,307 @@
+use crate::image::import::{SheetCells, SheetSlicing};
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+const CELL_SIZE_TEXT: &str = "Cell size (w, h)";
+const CELL_COUNT_TEXT: &str = "Columns, rows";
+
+pub struct ImportSheetDialog {
+    result: SUR,
+    bg: ShapeCollection,
+    title: Label,
+    sheet_size: Label,
+    use_count: Checkbox,
+    cells_label: Label,
+    cells_x: TextField,
+    cells_y: TextField,
+    offset_label: Label,
+    offset_x: TextField,
+    offset_y: TextField,
+    spacing_label: Label,
+    spacing_x: TextField,
+    spacing_y: TextField,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl ImportSheetDialog {
+    pub fn new(size: (usize, usize), style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "Import sprite sheet",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut sheet_size = Label::singleline(
+            &format!("Sheet is {}x{}", size.0, size.1),
+            (0, 0),
+            WHITE,
+            PixelFont::Standard4x5,
+            style.dialog.bounds.width(),
+        );
+        let mut use_count = Checkbox::new((0, 0), "Use columns and rows", false, &style.checkbox);
+        let mut cells_label = Label::singleline(
+            CELL_SIZE_TEXT,
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut offset_label = Label::singleline(
+            "Offset (x, y)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut spacing_label = Label::singleline(
+            "Spacing (x, y)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        //most sheets are a single row of square frames
+        let default_cell = size.1.min(255).to_string();
+        let field = |content: &str| {
+            TextField::new(
+                (0, 0),
+                4,
+                PixelFont::Standard6x7,
+                (None, None),
+                content,
+                &[TextFilter::Numbers],
+                &style.text_field,
+            )
+        };
+        let mut cells_x = field(&default_cell);
+        let mut cells_y = field(&default_cell);
+        let mut offset_x = field("0");
+        let mut offset_y = field("0");
+        let mut spacing_x = field("0");
+        let mut spacing_y = field("0");
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Import", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, sheet_size, align_left, px!(6));
+        layout!(context, sheet_size, top_to_bottom_of title, px!(6));
+
+        layout!(context, use_count, align_left, px!(6));
+        layout!(context, use_count, top_to_bottom_of sheet_size, px!(8));
+
+        layout!(context, cells_label, align_left, px!(6));
+        layout!(context, cells_label, top_to_bottom_of use_count, px!(8));
+
+        layout!(context, cells_x, align_left, px!(6));
+        layout!(context, cells_x, top_to_bottom_of cells_label, px!(4));
+
+        layout!(context, cells_y, left_to_right_of cells_x, px!(6));
+        layout!(context, cells_y, top_to_top_of cells_x);
+
+        layout!(context, offset_label, align_left, px!(6));
+        layout!(context, offset_label, top_to_bottom_of cells_x, px!(10));
+
+        layout!(context, offset_x, align_left, px!(6));
+        layout!(context, offset_x, top_to_bottom_of offset_label, px!(4));
+
+        layout!(context, offset_y, left_to_right_of offset_x, px!(6));
+        layout!(context, offset_y, top_to_top_of offset_x);
+
+        layout!(context, spacing_label, align_left, px!(100));
+        layout!(context, spacing_label, top_to_top_of offset_label);
+
+        layout!(context, spacing_x, left_to_left_of spacing_label);
+        layout!(context, spacing_x, top_to_top_of offset_x);
+
+        layout!(context, spacing_y, left_to_right_of spacing_x, px!(6));
+        layout!(context, spacing_y, top_to_top_of offset_x);
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        cells_x.focus();
+
+        Box::new(ImportSheetDialog {
+            result: Nothing,
+            bg,
+            title,
+            sheet_size,
+            use_count,
+            cells_label,
+            cells_x,
+            cells_y,
+            offset_label,
+            offset_x,
+            offset_y,
+            spacing_label,
+            spacing_x,
+            spacing_y,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl ImportSheetDialog {
+    /// Fields in tab order
+    fn fields(&mut self) -> [&mut TextField; 6] {
+        [
+            &mut self.cells_x,
+            &mut self.cells_y,
+            &mut self.offset_x,
+            &mut self.offset_y,
+            &mut self.spacing_x,
+            &mut self.spacing_y,
+        ]
+    }
+
+    fn focus_next(&mut self) {
+        let fields = self.fields();
+        if let Some(idx) = fields.iter().position(|field| field.is_focused()) {
+            fields[idx].unfocus();
+            fields[(idx + 1) % fields.len()].focus();
+        }
+    }
+
+    fn verify(&mut self) {
+        let values: Vec<Option<usize>> = self
+            .fields()
+            .iter()
+            .map(|field| field.content().parse::<u16>().ok().map(|v| v as usize))
+            .collect();
+        match values[..] {
+            [Some(cells_x), Some(cells_y), Some(offset_x), Some(offset_y), Some(spacing_x), Some(spacing_y)] =>
+            {
+                let cells = if self.use_count.is_checked() {
+                    SheetCells::Count(cells_x, cells_y)
+                } else {
+                    SheetCells::Size(cells_x, cells_y)
+                };
+                self.result = Pop(Some(SceneResult::ImportSheet(SheetSlicing {
+                    cells,
+                    offset: (offset_x, offset_y),
+                    spacing: (spacing_x, spacing_y),
+                })));
+            }
+            _ => {
+                self.alert = Some(Alert::new_warning(
+                    &["Invalid value, all fields", "must be filled in"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for ImportSheetDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.sheet_size,
+            self.use_count,
+            self.cells_label,
+            self.cells_x,
+            self.cells_y,
+            self.offset_label,
+            self.offset_x,
+            self.offset_y,
+            self.spacing_label,
+            self.spacing_x,
+            self.spacing_y,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        match key {
+            KeyCode::Escape => self.result = Pop(None),
+            KeyCode::Enter => self.verify(),
+            KeyCode::Tab => self.focus_next(),
+            _ => {
+                for field in self.fields() {
+                    field.on_key_press(key, held);
+                }
+            }
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                for field in self.fields() {
+                    field.on_mouse_click(down_at, mouse.xy);
+                }
+                if let Some(checked) = self.use_count.on_mouse_click(down_at, mouse.xy) {
+                    self.cells_label.update_text(if checked {
+                        CELL_COUNT_TEXT
+                    } else {
+                        CELL_SIZE_TEXT
+                    });
+                }
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        for field in self.fields() {
+            field.update(timing);
+        }
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
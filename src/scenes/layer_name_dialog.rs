@@ -0,0 +1,153 @@
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+const MAX_NAME_LEN: usize = 20;
+
+pub struct LayerNameDialog {
+    result: SUR,
+    bg: ShapeCollection,
+    title: Label,
+    name: TextField,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl LayerNameDialog {
+    pub fn new(name: &str, style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "Rename layer",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut name = TextField::new(
+            (0, 0),
+            MAX_NAME_LEN,
+            PixelFont::Standard6x7,
+            (None, None),
+            name,
+            &[TextFilter::All],
+            &style.text_field,
+        );
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Apply", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, name, align_left, px!(6));
+        layout!(context, name, top_to_bottom_of title, px!(12));
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        name.focus();
+
+        Box::new(LayerNameDialog {
+            result: Nothing,
+            bg,
+            title,
+            name,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl LayerNameDialog {
+    fn verify(&mut self) {
+        let name = self.name.content().trim();
+        if name.is_empty() {
+            self.alert = Some(Alert::new_warning(
+                &["Name can not be empty"],
+                WIDTH,
+                HEIGHT,
+                &self.alert_style,
+            ));
+        } else {
+            self.result = Pop(Some(SceneResult::LayerName(name.to_string())));
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for LayerNameDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.name,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        match key {
+            KeyCode::Escape => self.result = Pop(None),
+            KeyCode::Enter => self.verify(),
+            _ => self.name.on_key_press(key, held),
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                self.name.on_mouse_click(down_at, mouse.xy);
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        self.name.update(timing);
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
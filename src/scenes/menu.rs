@@ -3,9 +3,14 @@ use pixels_graphics_lib::ui::prelude::relative::LayoutContext;
 use pixels_graphics_lib::ui::prelude::*;
 use pixels_graphics_lib::{layout, px, render};
 
+use crate::image::tilemap::Tilemap;
 use crate::scenes::editor::EditorDetails;
+use crate::scenes::tilemap_editor::TilemapDetails;
 use crate::scenes::{file_dialog, import_image, BACKGROUND};
 use crate::SceneUpdateResult::{Nothing, Push};
+use log::error;
+use std::path::PathBuf;
+
 use crate::{DefaultPalette, Scene, SceneName, SceneResult, Settings, HEIGHT, SUR, WIDTH};
 
 const PALETTE_INFO_POS: TextPos = TextPos::Px(10, 200);
@@ -16,6 +21,8 @@ pub struct Menu {
     new_button: Button,
     load_button: Button,
     import_button: Button,
+    new_tilemap_button: Button,
+    load_tilemap_button: Button,
     format_label: Label,
     default_palette: DefaultPalette,
     prefs: AppPrefs<Settings>,
@@ -37,6 +44,8 @@ impl Menu {
         let mut new_button = Button::new((0, 0), "New image", Some(86), &style.button);
         let mut load_button = Button::new((0, 0), "Load ICI", Some(86), &style.button);
         let mut import_button = Button::new((0, 0), "Import", Some(86), &style.button);
+        let mut new_tilemap_button = Button::new((0, 0), "New tilemap", Some(86), &style.button);
+        let mut load_tilemap_button = Button::new((0, 0), "Load tilemap", Some(86), &style.button);
         let mut format_label = Label::singleline(
             "PNG, BMP, JPG & TGA",
             (0, 0),
@@ -59,6 +68,12 @@ impl Menu {
         layout!(context, import_button, left_to_left_of title);
         layout!(context, import_button, top_to_bottom_of load_button, px!(8));
 
+        layout!(context, new_tilemap_button, left_to_right_of new_button, px!(16));
+        layout!(context, new_tilemap_button, top_to_top_of new_button);
+
+        layout!(context, load_tilemap_button, left_to_left_of new_tilemap_button);
+        layout!(context, load_tilemap_button, top_to_top_of load_button);
+
         layout!(context, format_label, left_to_left_of import_button);
         layout!(context, format_label, top_to_bottom_of import_button, px!(4));
 
@@ -68,6 +83,8 @@ impl Menu {
             new_button,
             load_button,
             import_button,
+            new_tilemap_button,
+            load_tilemap_button,
             prefs,
             default_palette: palette,
             warning: None,
@@ -77,6 +94,35 @@ impl Menu {
     }
 }
 
+impl Menu {
+    fn load_tilemap(&mut self, path: PathBuf) {
+        let result = Tilemap::open(&path).and_then(|map| Ok((map.open_tileset()?, map)));
+        match result {
+            Ok((tileset, map)) => {
+                self.prefs.data.last_used_dir = path.clone();
+                self.prefs.save();
+                self.result = Push(
+                    true,
+                    SceneName::Tilemap(TilemapDetails {
+                        map,
+                        tileset,
+                        path: Some(path),
+                    }),
+                );
+            }
+            Err(e) => {
+                error!("Error opening tilemap {path:?}: {e:?}");
+                self.warning = Some(Alert::new_warning(
+                    &["Error opening tilemap"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+}
+
 impl Scene<SceneResult, SceneName> for Menu {
     fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
         graphics.clear(BACKGROUND);
@@ -88,6 +134,8 @@ impl Scene<SceneResult, SceneName> for Menu {
             self.new_button,
             self.title,
             self.load_button,
+            self.new_tilemap_button,
+            self.load_tilemap_button,
             self.format_label
         );
 
@@ -112,6 +160,10 @@ impl Scene<SceneResult, SceneName> for Menu {
                 ),
             ),
         }
+
+        if let Some(alert) = &self.warning {
+            alert.render(graphics, mouse);
+        }
     }
 
     fn on_key_up(&mut self, _: KeyCode, _: &MouseData, _: &FxHashSet<KeyCode>) {}
@@ -126,6 +178,12 @@ impl Scene<SceneResult, SceneName> for Menu {
         if button != MouseButton::Left {
             return;
         }
+        if let Some(alert) = &mut self.warning {
+            if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                self.warning = None;
+            }
+            return;
+        }
         if self.new_button.on_mouse_click(down_at, mouse.xy) {
             self.result = Push(false, SceneName::NewImage(None));
         }
@@ -139,12 +197,28 @@ impl Scene<SceneResult, SceneName> for Menu {
                 self.result = Push(true, SceneName::Editor(EditorDetails::Open(path)));
             }
         }
+        if self.new_tilemap_button.on_mouse_click(down_at, mouse.xy) {
+            if let Some(path) = file_dialog(
+                self.prefs.data.last_used_dir.clone(),
+                &[("IndexedImage", "ici"), ("AnimatedIndexedImage", "ica")],
+            )
+            .pick_file()
+            {
+                self.result = Push(false, SceneName::NewTilemap(path));
+            }
+        }
+        if self.load_tilemap_button.on_mouse_click(down_at, mouse.xy) {
+            if let Some(path) =
+                file_dialog(self.prefs.data.last_used_dir.clone(), &[("Tilemap", "ron")])
+                    .pick_file()
+            {
+                self.load_tilemap(path);
+            }
+        }
         if self.import_button.on_mouse_click(down_at, mouse.xy) {
             if let Some(result) = import_image(&self.alert_style, &mut self.prefs) {
                 match result {
-                    Ok(img) => {
-                        self.result = Push(true, SceneName::Editor(EditorDetails::OpenImage(img)))
-                    }
+                    Ok(details) => self.result = Push(true, SceneName::Editor(details)),
                     Err(alert) => self.warning = Some(alert),
                 }
             }
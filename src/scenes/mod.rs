@@ -1,8 +1,10 @@
+use crate::image::import::open_animation;
+use crate::scenes::editor::EditorDetails;
 use crate::{Settings, HEIGHT, WIDTH};
 use directories::UserDirs;
 use log::error;
 use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
-use pixels_graphics_lib::prelude::AppPrefs;
+use pixels_graphics_lib::prelude::{AppPrefs, FxHashSet, KeyCode};
 use pixels_graphics_lib::ui::alert::Alert;
 use pixels_graphics_lib::ui::styles::AlertStyle;
 use rfd::FileDialog;
@@ -10,12 +12,22 @@ use std::path::PathBuf;
 
 pub mod editor;
 mod editor_ui;
+pub mod effect_dialog;
+pub mod import_sheet_dialog;
+pub mod layer_name_dialog;
 pub mod menu;
 pub mod new_image_dialog;
+pub mod new_tilemap_dialog;
 pub mod palette_dialog;
+pub mod reference_dialog;
 pub mod resize_dialog;
 pub mod save_palette_dialog;
 pub mod simplify_dialog;
+pub mod sprite_sheet_dialog;
+pub mod tile_grid_dialog;
+pub mod tilemap_editor;
+mod tilemap_editor_ui;
+pub mod tween_dialog;
 
 const BACKGROUND: Color = Color {
     r: 30,
@@ -24,6 +36,23 @@ const BACKGROUND: Color = Color {
     a: 255,
 };
 
+const PER_UNDO: u64 = 200;
+const PADDING: isize = 4;
+/// Space under the canvas for the status line
+const STATUS_HEIGHT: isize = 7;
+const UNTITLED: &str = "Untitled";
+
+const IMAGE_FILTERS: [(&str, &str); 8] = [
+    ("PNG", "png"),
+    ("APNG", "apng"),
+    ("GIF", "gif"),
+    ("JPG", "jpg"),
+    ("JPEG", "jpeg"),
+    ("TGA", "tga"),
+    ("BMP", "bmp"),
+    ("Icon", "ico"),
+];
+
 fn file_dialog(path: PathBuf, filters: &[(&str, &str)]) -> FileDialog {
     let mut dialog = FileDialog::new();
     for filter in filters {
@@ -40,28 +69,36 @@ fn file_dialog(path: PathBuf, filters: &[(&str, &str)]) -> FileDialog {
     dialog
 }
 
+/// Picks an image to import, animated GIFs and PNGs are imported with all their frames
 pub fn import_image(
     alert_style: &AlertStyle,
     settings: &mut AppPrefs<Settings>,
-) -> Option<Result<IndexedImage, Alert>> {
-    if let Some(path) = file_dialog(
-        settings.data.last_used_png_dir.clone(),
-        &[
-            ("PNG", "png"),
-            ("JPG", "jpg"),
-            ("JPEG", "jpeg"),
-            ("TGA", "tga"),
-            ("BMP", "bmp"),
-            ("Icon", "ico"),
-        ],
-    )
-    .pick_file()
+) -> Option<Result<EditorDetails, Alert>> {
+    if let Some(path) =
+        file_dialog(settings.data.last_used_png_dir.clone(), &IMAGE_FILTERS).pick_file()
     {
+        match open_animation(&path) {
+            Ok(Some((frames, durations))) => {
+                settings.data.last_used_png_dir = path;
+                settings.save();
+                return Some(Ok(EditorDetails::OpenAnimation(frames, durations)));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Error importing animation from {path:?}: {e:?}");
+                return Some(Err(Alert::new_warning(
+                    &["Error opening image", &e.to_string()],
+                    WIDTH,
+                    HEIGHT,
+                    alert_style,
+                )));
+            }
+        }
         return match open_image(&path) {
             Ok(img) => {
-                if img.width() > 64 || img.height() > 64 {
+                if img.width() > 255 || img.height() > 255 {
                     Some(Err(Alert::new_warning(
-                        &["Image is too big", "(max 64x64)"],
+                        &["Image is too big", "(max 255x255)"],
                         WIDTH,
                         HEIGHT,
                         alert_style,
@@ -76,7 +113,7 @@ pub fn import_image(
                         Ok(ici) => {
                             settings.data.last_used_png_dir = path;
                             settings.save();
-                            Some(Ok(ici))
+                            Some(Ok(EditorDetails::OpenImage(ici)))
                         }
                         Err(e) => {
                             error!("Error converting image from {path:?}: {e:?}");
@@ -103,3 +140,10 @@ pub fn import_image(
     }
     None
 }
+
+fn is_ctrl_down(held: &FxHashSet<KeyCode>) -> bool {
+    held.contains(&KeyCode::ControlLeft)
+        || held.contains(&KeyCode::SuperLeft)
+        || held.contains(&KeyCode::ControlRight)
+        || held.contains(&KeyCode::SuperRight)
+}
@@ -54,7 +54,7 @@ impl NewImageDialog {
         ));
         let mut width_field = TextField::new(
             (0, 0),
-            2,
+            3,
             PixelFont::Standard6x7,
             (None, None),
             "",
@@ -73,15 +73,20 @@ impl NewImageDialog {
         ));
         let mut height_field = TextField::new(
             (0, 0),
-            2,
+            3,
             PixelFont::Standard6x7,
             (None, None),
             "",
             &[Numbers],
             &style.text_field,
         );
-        let mut size_label =
-            Label::singleline("Between 0..=64", (0, 0), WHITE, PixelFont::Standard4x5, 100);
+        let mut size_label = Label::singleline(
+            "Between 1..=255",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard4x5,
+            100,
+        );
         let mut palette_checkbox = Checkbox::new((0, 0), "Copy palette", false, &style.checkbox);
 
         layout!(context, width_label, align_top, px!(8));
@@ -240,9 +245,9 @@ impl NewImageDialog {
         } else {
             let width = u8::from_str(self.width_field.content());
             let height = u8::from_str(self.height_field.content());
-            if width.is_err() {
+            if width.is_err() || width == Ok(0) {
                 Err(String::from("Width is invalid"))
-            } else if height.is_err() {
+            } else if height.is_err() || height == Ok(0) {
                 Err(String::from("Height is invalid"))
             } else {
                 let width = width.unwrap();
@@ -0,0 +1,287 @@
+use crate::image::tilemap::Tilemap;
+use crate::scenes::tilemap_editor::TilemapDetails;
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use log::error;
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+use std::fs;
+use std::path::PathBuf;
+
+pub struct NewTilemapDialog {
+    result: SUR,
+    bg: ShapeCollection,
+    title: Label,
+    tile_size: Label,
+    tile_width: TextField,
+    tile_height: TextField,
+    map_size: Label,
+    map_width: TextField,
+    map_height: TextField,
+    cancel: Button,
+    submit: Button,
+    tileset: PathBuf,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl NewTilemapDialog {
+    pub fn new(tileset: PathBuf, style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "New tilemap",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut tile_size = Label::singleline(
+            "Tile size (w, h)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut map_size = Label::singleline(
+            "Map size in tiles (w, h)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let field = |content: &str| {
+            TextField::new(
+                (0, 0),
+                3,
+                PixelFont::Standard6x7,
+                (None, None),
+                content,
+                &[TextFilter::Numbers],
+                &style.text_field,
+            )
+        };
+        let mut tile_width = field("8");
+        let mut tile_height = field("8");
+        let mut map_width = field("16");
+        let mut map_height = field("16");
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Create", Some(80), &style.button);
+
+        //each frame of an animated tileset is a tile so the size can't be changed
+        if tileset
+            .extension()
+            .map(|ext| ext == "ica")
+            .unwrap_or_default()
+        {
+            match fs::read(&tileset)
+                .map_err(color_eyre::Report::from)
+                .and_then(|bytes| Ok(AnimatedIndexedImage::from_file_contents(&bytes)?))
+            {
+                Ok((image, _)) => {
+                    tile_width.set_content(&image.width().to_string());
+                    tile_height.set_content(&image.height().to_string());
+                }
+                Err(e) => error!("Error reading tileset {tileset:?}: {e:?}"),
+            }
+            tile_width.set_state(ViewState::Disabled);
+            tile_height.set_state(ViewState::Disabled);
+        }
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, tile_size, align_left, px!(6));
+        layout!(context, tile_size, top_to_bottom_of title, px!(12));
+
+        layout!(context, tile_width, align_left, px!(6));
+        layout!(context, tile_width, top_to_bottom_of tile_size, px!(4));
+
+        layout!(context, tile_height, left_to_right_of tile_width, px!(6));
+        layout!(context, tile_height, top_to_top_of tile_width);
+
+        layout!(context, map_size, align_left, px!(6));
+        layout!(context, map_size, top_to_bottom_of tile_width, px!(10));
+
+        layout!(context, map_width, align_left, px!(6));
+        layout!(context, map_width, top_to_bottom_of map_size, px!(4));
+
+        layout!(context, map_height, left_to_right_of map_width, px!(6));
+        layout!(context, map_height, top_to_top_of map_width);
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        map_width.focus();
+
+        Box::new(NewTilemapDialog {
+            result: Nothing,
+            bg,
+            title,
+            tile_size,
+            tile_width,
+            tile_height,
+            map_size,
+            map_width,
+            map_height,
+            cancel,
+            submit,
+            tileset,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl NewTilemapDialog {
+    fn fields(&mut self) -> [&mut TextField; 4] {
+        [
+            &mut self.tile_width,
+            &mut self.tile_height,
+            &mut self.map_width,
+            &mut self.map_height,
+        ]
+    }
+
+    /// Moves focus to the next enabled field
+    fn focus_next(&mut self) {
+        let fields = self.fields();
+        let Some(current) = fields.iter().position(|field| field.is_focused()) else {
+            return;
+        };
+        fields[current].unfocus();
+        for i in 1..=fields.len() {
+            let next = (current + i) % fields.len();
+            if fields[next].get_state() == ViewState::Normal {
+                fields[next].focus();
+                return;
+            }
+        }
+    }
+
+    fn show_warning(&mut self, text: &[&str]) {
+        self.alert = Some(Alert::new_warning(text, WIDTH, HEIGHT, &self.alert_style));
+    }
+
+    fn verify(&mut self) {
+        let size = |w: &TextField, h: &TextField| match (
+            w.content().parse::<u8>(),
+            h.content().parse::<u8>(),
+        ) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => Some((w, h)),
+            _ => None,
+        };
+        let Some(tile_size) = size(&self.tile_width, &self.tile_height) else {
+            self.show_warning(&["Invalid tile size, both", "must be between 1 and 255"]);
+            return;
+        };
+        let Some(map_size) = size(&self.map_width, &self.map_height) else {
+            self.show_warning(&["Invalid map size, both", "must be between 1 and 255"]);
+            return;
+        };
+        let map = Tilemap::new(self.tileset.clone(), tile_size, map_size);
+        match map.open_tileset() {
+            Ok(tileset) => {
+                self.result = Push(
+                    true,
+                    SceneName::Tilemap(TilemapDetails {
+                        map,
+                        tileset,
+                        path: None,
+                    }),
+                )
+            }
+            Err(e) => {
+                error!("Error opening tileset {:?}: {e:?}", self.tileset);
+                self.show_warning(&["Error opening tileset", "Check the tile size"]);
+            }
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for NewTilemapDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.tile_size,
+            self.tile_width,
+            self.tile_height,
+            self.map_size,
+            self.map_width,
+            self.map_height,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        match key {
+            KeyCode::Escape => self.result = Pop(None),
+            KeyCode::Enter => self.verify(),
+            KeyCode::Tab => self.focus_next(),
+            _ => {
+                for field in self.fields() {
+                    field.on_key_press(key, held);
+                }
+            }
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                for field in self.fields() {
+                    field.on_mouse_click(down_at, mouse.xy);
+                }
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        for field in self.fields() {
+            field.update(timing);
+        }
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
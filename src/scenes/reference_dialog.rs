@@ -0,0 +1,231 @@
+use crate::ui::reference::ReferenceSettings;
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+pub struct ReferenceDialog {
+    result: SUR,
+    settings: ReferenceSettings,
+    bg: ShapeCollection,
+    title: Label,
+    opacity_label: Label,
+    scale_label: Label,
+    offset_label: Label,
+    opacity: TextField,
+    scale: TextField,
+    x: TextField,
+    y: TextField,
+    above: Checkbox,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl ReferenceDialog {
+    pub fn new(settings: ReferenceSettings, style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let label = |text: &str, font: PixelFont| {
+            Label::singleline(text, (0, 0), WHITE, font, style.dialog.bounds.width())
+        };
+        let field = |content: String, filter: TextFilter| {
+            TextField::new(
+                (0, 0),
+                4,
+                PixelFont::Standard6x7,
+                (None, None),
+                &content,
+                &[filter],
+                &style.text_field,
+            )
+        };
+        let mut title = label("Reference image", PixelFont::Standard8x10);
+        let mut opacity_label = label("Opacity (%)", PixelFont::Standard6x7);
+        let mut scale_label = label("Scale (%)", PixelFont::Standard6x7);
+        let mut offset_label = label("Offset (x, y)", PixelFont::Standard6x7);
+        let mut opacity = field(settings.opacity.to_string(), TextFilter::Numbers);
+        let mut scale = field(settings.scale.to_string(), TextFilter::Numbers);
+        let mut x = field(settings.offset.0.to_string(), TextFilter::NegativeNumbers);
+        let mut y = field(settings.offset.1.to_string(), TextFilter::NegativeNumbers);
+        let mut above = Checkbox::new((0, 0), "Draw above image", settings.above, &style.checkbox);
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Apply", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, opacity_label, align_left, px!(6));
+        layout!(context, opacity_label, top_to_bottom_of title, px!(12));
+        layout!(context, opacity, align_left, px!(6));
+        layout!(context, opacity, top_to_bottom_of opacity_label, px!(4));
+
+        layout!(context, scale_label, left_to_right_of opacity_label, px!(12));
+        layout!(context, scale_label, top_to_top_of opacity_label);
+        layout!(context, scale, left_to_left_of scale_label);
+        layout!(context, scale, top_to_top_of opacity);
+
+        layout!(context, offset_label, align_left, px!(6));
+        layout!(context, offset_label, top_to_bottom_of opacity, px!(8));
+        layout!(context, x, align_left, px!(6));
+        layout!(context, x, top_to_bottom_of offset_label, px!(4));
+        layout!(context, y, left_to_right_of x, px!(6));
+        layout!(context, y, top_to_top_of x);
+
+        layout!(context, above, align_left, px!(6));
+        layout!(context, above, top_to_bottom_of x, px!(8));
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        opacity.focus();
+
+        Box::new(ReferenceDialog {
+            result: Nothing,
+            settings,
+            bg,
+            title,
+            opacity_label,
+            scale_label,
+            offset_label,
+            opacity,
+            scale,
+            x,
+            y,
+            above,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl ReferenceDialog {
+    fn verify(&mut self) {
+        let opacity = self.opacity.content().parse::<u8>();
+        let scale = self.scale.content().parse::<u16>();
+        let x = self.x.content().parse::<isize>();
+        let y = self.y.content().parse::<isize>();
+        match (opacity, scale, x, y) {
+            (Ok(opacity), Ok(scale), Ok(x), Ok(y)) if opacity <= 100 && scale > 0 => {
+                let mut settings = self.settings.clone();
+                settings.opacity = opacity;
+                settings.scale = scale;
+                settings.offset = (x, y);
+                settings.above = self.above.is_checked();
+                self.result = Pop(Some(SceneResult::Reference(settings)));
+            }
+            _ => {
+                self.alert = Some(Alert::new_warning(
+                    &[
+                        "Opacity must be 0 to 100",
+                        "scale more than 0",
+                        "and offset a number",
+                    ],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+
+    fn fields(&mut self) -> [&mut TextField; 4] {
+        [&mut self.opacity, &mut self.scale, &mut self.x, &mut self.y]
+    }
+}
+
+impl Scene<SceneResult, SceneName> for ReferenceDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.opacity_label,
+            self.scale_label,
+            self.offset_label,
+            self.opacity,
+            self.scale,
+            self.x,
+            self.y,
+            self.above,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        if key == KeyCode::Escape {
+            self.result = Pop(None);
+        }
+        for field in self.fields() {
+            field.on_key_press(key, held);
+        }
+        if key == KeyCode::Tab {
+            let fields = self.fields();
+            if let Some(idx) = fields.iter().position(|field| field.is_focused()) {
+                fields[idx].unfocus();
+                fields[(idx + 1) % fields.len()].focus();
+            }
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                for field in self.fields() {
+                    field.on_mouse_click(down_at, mouse.xy);
+                }
+                let _ = self.above.on_mouse_click(down_at, mouse.xy);
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        for field in self.fields() {
+            field.update(timing);
+        }
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
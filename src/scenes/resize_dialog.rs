@@ -234,7 +234,7 @@ impl ResizeDialog {
 impl ResizeDialog {
     fn verify(&mut self) {
         let alert = Some(Alert::new_warning(
-            &["Invalid width/height, both", "must be between 1 and 64"],
+            &["Invalid width/height, both", "must be between 1 and 255"],
             WIDTH,
             HEIGHT,
             &self.alert_style,
@@ -242,18 +242,16 @@ impl ResizeDialog {
         let anchor = self.anchors.get_selected();
         let w = self.width.content().parse::<u8>();
         let h = self.height.content().parse::<u8>();
-        if w.is_err() || h.is_err() {
-            self.alert = alert;
-        } else {
-            let w = w.unwrap();
-            let h = h.unwrap();
-            if w == 0 || h == 0 || w > 64 || h > 64 {
+        if let (Ok(w), Ok(h)) = (w, h) {
+            if w == 0 || h == 0 {
                 self.alert = alert;
             } else {
                 self.prefs.data.last_used_anchor = *anchor;
                 self.prefs.save();
                 self.result = Pop(Some(SceneResult::ResizeData(w, h, *anchor)));
             }
+        } else {
+            self.alert = alert;
         }
     }
 }
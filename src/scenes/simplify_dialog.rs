@@ -15,6 +15,8 @@ pub struct SimplifyDialog {
     result: SUR,
     image: IndexedImage,
     new_image: IndexedImage,
+    /// New palette index for each color in the current palette
+    mapping: Vec<u8>,
     bg: ShapeCollection,
     cancel: Button,
     current_preview: Preview,
@@ -107,6 +109,7 @@ impl SimplifyDialog {
         Box::new(SimplifyDialog {
             result: SceneUpdateResult::Nothing,
             image: image.clone(),
+            mapping: (0..image.get_palette().len()).map(|i| i as u8).collect(),
             new_image: image,
             bg: dialog_background(WIDTH, HEIGHT, &style.dialog),
             cancel,
@@ -141,18 +144,19 @@ impl SimplifyDialog {
                 .map(|color| {
                     new_palette.iter().position(|c| c == color).expect(
                         "missing color during simplification (please raise issue on github)",
-                    )
+                    ) as u8
                 })
-                .collect::<Vec<usize>>();
+                .collect::<Vec<u8>>();
 
             let mut pixels = vec![];
             for px in self.image.get_pixels() {
-                pixels.push(mapping[*px as usize] as u8)
+                pixels.push(mapping[*px as usize])
             }
 
             match IndexedImage::new(self.image.width(), self.image.height(), new_palette, pixels) {
                 Ok(img) => {
                     self.new_image = img;
+                    self.mapping = mapping;
                     self.new_preview.set_image(self.new_image.clone());
                     self.new_color_count
                         .update_text(&self.new_image.get_palette().len().to_string());
@@ -207,7 +211,10 @@ impl Scene<SceneResult, SceneName> for SimplifyDialog {
                 self.result = Pop(None);
             }
             if self.save.on_mouse_click(down_at, mouse.xy) {
-                self.result = Pop(Some(Simplify(self.new_image.clone())));
+                self.result = Pop(Some(Simplify(
+                    self.new_image.get_palette().to_vec(),
+                    self.mapping.clone(),
+                )));
             }
             self.amount.on_mouse_click(down_at, mouse.xy);
         }
@@ -0,0 +1,225 @@
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+pub struct SpriteSheetDialog {
+    result: SUR,
+    bg: ShapeCollection,
+    title: Label,
+    columns_label: Label,
+    columns: TextField,
+    columns_note: Label,
+    padding_label: Label,
+    padding: TextField,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl SpriteSheetDialog {
+    pub fn new(style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "Sprite sheet",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut columns_label = Label::singleline(
+            "Columns",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut columns_note = Label::singleline(
+            "0 for one row, 1 for one column",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard4x5,
+            style.dialog.bounds.width(),
+        );
+        let mut padding_label = Label::singleline(
+            "Padding (px)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut columns = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            "0",
+            &[TextFilter::Numbers],
+            &style.text_field,
+        );
+        let mut padding = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            "0",
+            &[TextFilter::Numbers],
+            &style.text_field,
+        );
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Export", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, columns_label, align_left, px!(6));
+        layout!(context, columns_label, top_to_bottom_of title, px!(12));
+
+        layout!(context, columns, align_left, px!(6));
+        layout!(context, columns, top_to_bottom_of columns_label, px!(4));
+
+        layout!(context, columns_note, left_to_right_of columns, px!(6));
+        layout!(context, columns_note, centerv_to_centerv_of columns);
+
+        layout!(context, padding_label, align_left, px!(6));
+        layout!(context, padding_label, top_to_bottom_of columns, px!(10));
+
+        layout!(context, padding, align_left, px!(6));
+        layout!(context, padding, top_to_bottom_of padding_label, px!(4));
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        columns.focus();
+
+        Box::new(SpriteSheetDialog {
+            result: Nothing,
+            bg,
+            title,
+            columns_label,
+            columns,
+            columns_note,
+            padding_label,
+            padding,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl SpriteSheetDialog {
+    fn verify(&mut self) {
+        let columns = self.columns.content().parse::<u8>();
+        let padding = self.padding.content().parse::<u8>();
+        match (columns, padding) {
+            (Ok(columns), Ok(padding)) => {
+                self.result = Pop(Some(SceneResult::SpriteSheet(
+                    columns as usize,
+                    padding as usize,
+                )));
+            }
+            _ => {
+                self.alert = Some(Alert::new_warning(
+                    &["Invalid value, both", "must be between 0 and 255"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for SpriteSheetDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.columns_label,
+            self.columns,
+            self.columns_note,
+            self.padding_label,
+            self.padding,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        match key {
+            KeyCode::Escape => self.result = Pop(None),
+            KeyCode::Enter => self.verify(),
+            KeyCode::Tab if self.columns.is_focused() => {
+                self.columns.unfocus();
+                self.padding.focus();
+            }
+            KeyCode::Tab if self.padding.is_focused() => {
+                self.padding.unfocus();
+                self.columns.focus();
+            }
+            _ => {
+                self.columns.on_key_press(key, held);
+                self.padding.on_key_press(key, held);
+            }
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                self.columns.on_mouse_click(down_at, mouse.xy);
+                self.padding.on_mouse_click(down_at, mouse.xy);
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        self.columns.update(timing);
+        self.padding.update(timing);
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
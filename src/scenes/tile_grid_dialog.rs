@@ -0,0 +1,192 @@
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+pub struct TileGridDialog {
+    result: SUR,
+    bg: ShapeCollection,
+    title: Label,
+    size: Label,
+    width: TextField,
+    height: TextField,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl TileGridDialog {
+    pub fn new((width, height): (u8, u8), style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "Tile grid",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut size = Label::singleline(
+            "Tile size (w, h)",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut width = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            &width.to_string(),
+            &[TextFilter::Numbers],
+            &style.text_field,
+        );
+        let mut height = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            &height.to_string(),
+            &[TextFilter::Numbers],
+            &style.text_field,
+        );
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Apply", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, size, align_left, px!(6));
+        layout!(context, size, top_to_bottom_of title, px!(12));
+
+        layout!(context, width, align_left, px!(6));
+        layout!(context, width, top_to_bottom_of size, px!(4));
+
+        layout!(context, height, left_to_right_of width, px!(6));
+        layout!(context, height, top_to_top_of width);
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        width.focus();
+
+        Box::new(TileGridDialog {
+            result: Nothing,
+            bg,
+            title,
+            size,
+            width,
+            height,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl TileGridDialog {
+    fn verify(&mut self) {
+        let w = self.width.content().parse::<u8>();
+        let h = self.height.content().parse::<u8>();
+        match (w, h) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => {
+                self.result = Pop(Some(SceneResult::TileGrid(w, h)));
+            }
+            _ => {
+                self.alert = Some(Alert::new_warning(
+                    &["Invalid size, both", "must be between 1 and 255"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for TileGridDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.size,
+            self.width,
+            self.height,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        if key == KeyCode::Escape {
+            self.result = Pop(None);
+        }
+        self.width.on_key_press(key, held);
+        self.height.on_key_press(key, held);
+        if key == KeyCode::Tab && self.width.is_focused() {
+            self.width.unfocus();
+            self.height.focus();
+        } else if key == KeyCode::Tab && self.height.is_focused() {
+            self.height.unfocus();
+            self.width.focus();
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                self.width.on_mouse_click(down_at, mouse.xy);
+                self.height.on_mouse_click(down_at, mouse.xy);
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        self.width.update(timing);
+        self.height.update(timing);
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,664 @@
+use crate::image::tilemap::{flatten, flatten_pixels, flatten_to_fit, Tilemap, Tileset};
+use crate::scenes::editor::BackgroundColors;
+use crate::scenes::tilemap_editor_ui::MenuId::*;
+use crate::scenes::tilemap_editor_ui::*;
+use crate::scenes::{
+    file_dialog, is_ctrl_down, BACKGROUND, PADDING, PER_UNDO, STATUS_HEIGHT, UNTITLED,
+};
+use crate::ui::canvas::{Canvas, Tool};
+use crate::ui::edit_history::EditHistory;
+use crate::ui::palette::PaletteView;
+use crate::ui::preview::Preview;
+use crate::ui::tile_picker::TilePicker;
+use crate::{SceneName, SceneResult, Settings, HEIGHT, SUR, WIDTH};
+use image_lib::{save_buffer_with_format, ExtendedColorType, ImageFormat};
+use log::error;
+use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
+use pixels_graphics_lib::prelude::PixelFont::Standard6x7;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::scenes::SceneUpdateResult::{Nothing, Pop};
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::{layout, px, render};
+use std::fs;
+use std::ops::Add;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const PICKER_WIDTH: usize = 58;
+const PICKER_HEIGHT: usize = 76;
+const PALETTE_HEIGHT: usize = 40;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilemapDetails {
+    pub map: Tilemap,
+    pub tileset: Tileset,
+    /// Where the map was loaded from, None for new maps
+    pub path: Option<PathBuf>,
+}
+
+/// Paints tiles onto a grid, the tiles can also be edited and the map updates as they change
+pub struct TilemapEditor {
+    result: SUR,
+    menubar: MenuBar<MenuId>,
+    filename: Label,
+    tools: ToggleIconButtonGroup<Tool>,
+    canvas: Canvas,
+    tile_picker: TilePicker,
+    palette: PaletteView,
+    preview: Preview,
+    /// Map settings and cells as of the last save
+    map: Tilemap,
+    /// Single image with a pixel per cell
+    map_history: EditHistory,
+    /// Tiles as of the last save
+    tileset: Tileset,
+    /// A frame per tile
+    tile_history: EditHistory,
+    path: Option<PathBuf>,
+    editing_tiles: bool,
+    last_undo: Instant,
+    prefs: AppPrefs<Settings>,
+    data_loss_alert: Alert,
+    quit_pending: bool,
+    warning: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl TilemapEditor {
+    pub fn new(details: TilemapDetails, prefs: AppPrefs<Settings>, style: &UiStyle) -> Box<Self> {
+        let mut menubar = create_menubar(style);
+        let data_loss_alert = Alert::new_question(
+            &["Are you sure?", "All changes will be lost"],
+            "Cancel",
+            "Yes",
+            WIDTH,
+            HEIGHT,
+            &style.alert,
+        );
+        let mut pencil_tool = ToggleIconButton::new(
+            Coord::default(),
+            "PENCIL",
+            Positioning::CenterBottom,
+            IndexedImage::from_file_contents(include_bytes!("../../assets/icons/pencil.ici"))
+                .unwrap()
+                .0,
+            &style.toggle_icon_button,
+        );
+        let mut rect_tool = ToggleIconButton::new(
+            Coord::default(),
+            "RECT",
+            Positioning::CenterBottom,
+            IndexedImage::from_file_contents(include_bytes!("../../assets/icons/rect.ici"))
+                .unwrap()
+                .0,
+            &style.toggle_icon_button,
+        );
+        let mut fill_tool = ToggleIconButton::new(
+            Coord::default(),
+            "FILL",
+            Positioning::CenterBottom,
+            IndexedImage::from_file_contents(include_bytes!("../../assets/icons/fill.ici"))
+                .unwrap()
+                .0,
+            &style.toggle_icon_button,
+        );
+        let mut filename = Label::singleline(UNTITLED, (0, 0), WHITE, Standard6x7, WIDTH - 4);
+        if let Some(path) = &details.path {
+            filename.update_text(&map_filename(path));
+        }
+        let mut tile_picker = TilePicker::new(Coord::default(), (PICKER_WIDTH, PICKER_HEIGHT));
+        tile_picker.set_tiles(&details.tileset.tiles);
+        let mut palette = PaletteView::new(Coord::default(), (PICKER_WIDTH, PALETTE_HEIGHT));
+        palette.set_palette(details.tileset.tiles[0].get_palette());
+        palette.set_color_index(1);
+        palette.set_state(ViewState::Disabled);
+        let mut preview = Preview::new(Rect::new_with_size((0, 0), 64, 64), false);
+        match prefs.data.background_color {
+            BackgroundColors::SolidLightGrey => preview.set_background(2),
+            BackgroundColors::SolidDarkGrey => preview.set_background(3),
+            BackgroundColors::SolidBlack => preview.set_background(1),
+            _ => preview.set_background(0),
+        }
+
+        let context = LayoutContext::new(Rect::new((0, 0), (WIDTH, HEIGHT)));
+        layout!(context, menubar, align_top);
+        layout!(context, menubar, align_left);
+
+        layout!(context, filename, top_to_bottom_of menubar, px!(4));
+        layout!(context, filename, align_left, px!(1));
+
+        layout!(context, tile_picker, align_left, px!(4));
+        layout!(context, tile_picker, top_to_bottom_of filename, px!(6));
+
+        layout!(context, palette, align_left, px!(4));
+        layout!(context, palette, top_to_bottom_of tile_picker, px!(4));
+
+        layout!(context, preview, align_left, px!(4));
+        layout!(context, preview, align_bottom, px!(4));
+
+        layout!(context, pencil_tool, top_to_bottom_of filename, px!(4));
+        layout!(context, pencil_tool, left_to_right_of tile_picker, px!(20));
+        layout!(context, rect_tool, top_to_top_of pencil_tool);
+        layout!(context, rect_tool, left_to_right_of pencil_tool, px!(4));
+        layout!(context, fill_tool, top_to_top_of pencil_tool);
+        layout!(context, fill_tool, left_to_right_of rect_tool, px!(4));
+
+        let canvas = Canvas::new(
+            Coord::new(
+                pencil_tool.bounds().left(),
+                pencil_tool.bounds().bottom() + PADDING,
+            ),
+            (
+                (WIDTH as isize - pencil_tool.bounds().left()) as usize,
+                (HEIGHT as isize
+                    - pencil_tool.bounds().bottom()
+                    - PADDING
+                    - PADDING
+                    - STATUS_HEIGHT) as usize,
+            ),
+            prefs.data.background_color.colors(),
+        );
+
+        let tools = ToggleIconButtonGroup::new(vec![
+            (Tool::Pencil, pencil_tool),
+            (Tool::Rect, rect_tool),
+            (Tool::Fill, fill_tool),
+        ]);
+
+        let map_image = details
+            .map
+            .as_image(details.tileset.tiles.len())
+            .expect("Creating tilemap image");
+        let mut map = details.map;
+        map.cells = map_image.get_pixels().to_vec();
+
+        let mut editor = Self {
+            result: Nothing,
+            menubar,
+            filename,
+            tools,
+            canvas,
+            tile_picker,
+            palette,
+            preview,
+            map,
+            map_history: EditHistory::new(vec![map_image]),
+            tile_history: EditHistory::new(details.tileset.tiles.clone()),
+            tileset: details.tileset,
+            path: details.path,
+            editing_tiles: false,
+            last_undo: Instant::now(),
+            prefs,
+            data_loss_alert,
+            quit_pending: false,
+            warning: None,
+            alert_style: style.alert.clone(),
+        };
+        editor.update_canvas();
+        editor.update_preview();
+        Box::new(editor)
+    }
+}
+
+impl TilemapEditor {
+    fn has_changes(&self) -> bool {
+        self.map_history.get_current_image().get_pixels() != self.map.cells
+            || self.tile_history.get_cels() != self.tileset.tiles
+    }
+
+    fn save(&mut self, new_file: bool) {
+        if self.path.is_none() || new_file {
+            match file_dialog(self.prefs.data.last_used_dir.clone(), &[("Tilemap", "ron")])
+                .save_file()
+            {
+                Some(path) => {
+                    self.filename.update_text(&map_filename(&path));
+                    self.prefs.data.last_used_dir = path.clone();
+                    self.prefs.save();
+                    self.path = Some(path);
+                }
+                None => return,
+            }
+        }
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        if self.tile_history.get_cels() != self.tileset.tiles {
+            if let Err(e) = self
+                .tileset
+                .save(&self.map.tileset, self.tile_history.get_cels())
+            {
+                error!("Error saving tileset to {:?}: {e:?}", self.map.tileset);
+                self.show_warning(&["Error saving tileset"]);
+                return;
+            }
+            self.tileset.tiles = self.tile_history.get_cels().to_vec();
+        }
+        let mut map = self.map.clone();
+        map.cells = self.map_history.get_current_image().get_pixels().to_vec();
+        if let Err(e) = map.save(&path) {
+            error!("Error saving tilemap to {path:?}: {e:?}");
+            self.show_warning(&["Error saving tilemap"]);
+            return;
+        }
+        self.map = map;
+    }
+
+    /// Saves the map drawn with its tiles as a single ICI
+    fn export_ici(&mut self) {
+        let image = match flatten(
+            self.map_history.get_current_image(),
+            self.tile_history.get_cels(),
+        ) {
+            Ok(image) => image,
+            Err(e) => {
+                error!("Error flattening tilemap: {e:?}");
+                self.show_warning(&["Tilemap is too big for ICI", "(max 255x255 pixels)"]);
+                return;
+            }
+        };
+        if let Some(path) = file_dialog(
+            self.prefs.data.last_used_dir.clone(),
+            &[("IndexedImage", "ici")],
+        )
+        .save_file()
+        {
+            let result = image
+                .to_file_contents(&self.tileset.palette)
+                .map_err(color_eyre::Report::from)
+                .and_then(|bytes| Ok(fs::write(&path, bytes)?));
+            if let Err(e) = result {
+                error!("Error exporting tilemap to {path:?}: {e:?}");
+                self.show_warning(&["Error saving image"]);
+            }
+        }
+    }
+
+    /// Saves the map drawn with its tiles as a PNG, this isn't limited to 255x255
+    fn export_png(&mut self) {
+        if let Some(path) =
+            file_dialog(self.prefs.data.last_used_dir.clone(), &[("PNG", "png")]).save_file()
+        {
+            let tiles = self.tile_history.get_cels();
+            let (width, height, pixels) =
+                flatten_pixels(self.map_history.get_current_image(), tiles);
+            let palette = tiles[0].get_palette();
+            let values: Vec<u8> = pixels
+                .iter()
+                .flat_map(|idx| ColorConversion::<[u8; 4]>::to_rgba(palette[*idx as usize]))
+                .collect();
+            if let Err(e) = save_buffer_with_format(
+                &path,
+                &values,
+                width as u32,
+                height as u32,
+                ExtendedColorType::Rgba8,
+                ImageFormat::Png,
+            ) {
+                error!("Error exporting tilemap to {path:?}: {e:?}");
+                self.show_warning(&["Error saving image"]);
+            }
+        }
+    }
+
+    fn show_warning(&mut self, text: &[&str]) {
+        self.warning = Some(Alert::new_warning(text, WIDTH, HEIGHT, &self.alert_style));
+    }
+
+    fn history(&mut self) -> &mut EditHistory {
+        if self.editing_tiles {
+            &mut self.tile_history
+        } else {
+            &mut self.map_history
+        }
+    }
+
+    fn undo(&mut self) {
+        self.history().undo().unwrap();
+        self.last_undo = Instant::now().add(Duration::from_millis(PER_UNDO));
+        self.refresh();
+    }
+
+    fn redo(&mut self) {
+        self.history().redo().unwrap();
+        self.last_undo = Instant::now().add(Duration::from_millis(PER_UNDO));
+        self.refresh();
+    }
+
+    /// Switches between painting the map and editing the tiles
+    fn set_editing_tiles(&mut self, editing_tiles: bool) {
+        self.canvas.cancel();
+        self.editing_tiles = editing_tiles;
+        self.menubar.uncheck_all_children(MenuMode);
+        self.menubar.set_checked(
+            if editing_tiles {
+                MenuModeTiles
+            } else {
+                MenuModeMap
+            },
+            true,
+        );
+        if editing_tiles {
+            self.palette.set_state(ViewState::Normal);
+            self.select_tile(self.tile_picker.get_selected().max(1));
+        } else {
+            self.palette.set_state(ViewState::Disabled);
+            self.update_canvas();
+        }
+    }
+
+    /// Selects `idx` in the picker (0 is empty otherwise it's the tile index + 1),
+    /// when editing tiles the empty tile can't be selected
+    fn select_tile(&mut self, idx: u8) {
+        if self.editing_tiles {
+            let idx = idx.max(1);
+            if self.tile_history.active_frame() != idx as usize - 1 {
+                self.tile_history
+                    .add_frame_select(idx as usize - 1)
+                    .unwrap();
+            }
+        }
+        self.tile_picker.set_selected(idx);
+        self.update_canvas();
+    }
+
+    fn update_canvas(&mut self) {
+        if self.editing_tiles {
+            self.canvas.set_tiles(vec![]);
+            self.canvas
+                .set_image(self.tile_history.get_current_image().clone());
+            self.canvas.set_color_index(self.palette.get_selected_idx());
+        } else {
+            self.canvas.set_tiles(self.tile_history.get_cels().to_vec());
+            self.canvas
+                .set_image(self.map_history.get_current_image().clone());
+            self.canvas.set_color_index(self.tile_picker.get_selected());
+        }
+    }
+
+    fn update_preview(&mut self) {
+        let bounds = self.preview.bounds();
+        self.preview.set_image(flatten_to_fit(
+            self.map_history.get_current_image(),
+            self.tile_history.get_cels(),
+            bounds.width(),
+            bounds.height(),
+        ));
+    }
+
+    /// Updates everything after the map or tiles have changed (e.g. undo)
+    fn refresh(&mut self) {
+        if self.editing_tiles {
+            self.tile_picker
+                .set_selected(self.tile_history.active_frame() as u8 + 1);
+        }
+        self.tile_picker.set_tiles(self.tile_history.get_cels());
+        self.update_canvas();
+        self.update_preview();
+    }
+
+    fn set_tool(&mut self, tool: Tool) {
+        self.tools.set_selected(tool);
+        self.canvas.set_tool(tool);
+    }
+
+    /// Cursor position and the tile or color under it
+    fn status_text(&self, mouse_xy: Coord) -> String {
+        let mut parts = vec![];
+        if let Some((x, y)) = self.canvas.cursor_position(mouse_xy) {
+            let image = self.canvas.get_image();
+            parts.push(format!("{x},{y}"));
+            if let Some(idx) = image
+                .get_pixel_index(x, y)
+                .ok()
+                .and_then(|i| image.get_pixel(i).ok())
+            {
+                if self.editing_tiles {
+                    parts.push(format!("#{idx}"));
+                } else if idx == 0 {
+                    parts.push(String::from("empty"));
+                } else {
+                    parts.push(format!("tile {idx}"));
+                }
+            }
+        }
+        if let Some((w, h)) = self.canvas.shape_size(mouse_xy) {
+            parts.push(format!("{w}x{h}"));
+        }
+        parts.join("  ")
+    }
+}
+
+fn map_filename(path: &std::path::Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or(UNTITLED.to_string())
+}
+
+impl Scene<SceneResult, SceneName> for TilemapEditor {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        graphics.clear(BACKGROUND);
+
+        graphics.draw_line(
+            (0, self.filename.bounds().bottom()),
+            (WIDTH as isize, self.filename.bounds().bottom()),
+            LIGHT_GRAY,
+        );
+
+        graphics.draw_text(
+            &format!(
+                "{}x{} TILES OF {}x{}",
+                self.map.width, self.map.height, self.map.tile_width, self.map.tile_height
+            ),
+            TextPos::px(coord!(WIDTH, self.filename.bounds().bottom() as usize + 2)),
+            (WHITE, PixelFont::Standard4x5, Positioning::RightTop),
+        );
+
+        let mode = if self.editing_tiles {
+            format!(
+                "TILE {}/{}",
+                self.tile_history.active_frame() + 1,
+                self.tile_history.frame_count()
+            )
+        } else {
+            String::from("MAP")
+        };
+        graphics.draw_text(
+            &mode,
+            TextPos::px(self.preview.bounds().top_left() - (0, 7)),
+            (WHITE, PixelFont::Standard4x5, WrappingStrategy::Cutoff(15)),
+        );
+
+        graphics.draw_text(
+            &self.status_text(mouse.xy),
+            TextPos::px(self.canvas.bounds().bottom_left() + (0, 2)),
+            (WHITE, PixelFont::Standard4x5, WrappingStrategy::None),
+        );
+
+        render!(
+            graphics,
+            mouse,
+            self.canvas,
+            self.tools,
+            self.tile_picker,
+            self.preview,
+            self.filename,
+        );
+        if self.editing_tiles {
+            self.palette.render(graphics, mouse);
+        }
+        self.menubar.render(graphics, mouse);
+
+        if self.quit_pending {
+            self.data_loss_alert.render(graphics, mouse);
+        }
+
+        if let Some(alert) = &self.warning {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_down(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.quit_pending {
+            return;
+        }
+        if self.warning.is_some() && matches!(key, KeyCode::Escape | KeyCode::Enter) {
+            self.warning = None;
+        }
+        if self.last_undo < Instant::now() && is_ctrl_down(held) {
+            let shift_down =
+                held.contains(&KeyCode::ShiftLeft) || held.contains(&KeyCode::ShiftRight);
+            if key == KeyCode::KeyZ && !shift_down {
+                self.undo();
+            }
+            if (key == KeyCode::KeyZ && shift_down) || key == KeyCode::KeyY {
+                self.redo();
+            }
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.quit_pending || self.warning.is_some() || is_ctrl_down(held) {
+            return;
+        }
+        match key {
+            KeyCode::KeyB => self.set_tool(Tool::Pencil),
+            KeyCode::KeyR => self.set_tool(Tool::Rect),
+            KeyCode::KeyG => self.set_tool(Tool::Fill),
+            KeyCode::Tab => self.set_editing_tiles(!self.editing_tiles),
+            KeyCode::Equal | KeyCode::NumpadAdd => self.canvas.zoom_in(None),
+            KeyCode::Minus | KeyCode::NumpadSubtract => self.canvas.zoom_out(None),
+            KeyCode::Escape => self.canvas.cancel(),
+            _ => {}
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if button != MouseButton::Left {
+            return;
+        }
+        if self.quit_pending {
+            if let Some(result) = self.data_loss_alert.on_mouse_click(down_at, mouse.xy) {
+                if result == AlertResult::Positive {
+                    self.result = Pop(None);
+                }
+                self.quit_pending = false;
+            }
+            return;
+        }
+        if let Some(alert) = &mut self.warning {
+            if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                self.warning = None;
+            }
+            return;
+        }
+        if self.menubar.is_expanded() {
+            if let Some(id) = self.menubar.on_mouse_click(down_at, mouse.xy) {
+                match id {
+                    MenuFileSave => self.save(false),
+                    MenuFileSaveAs => self.save(true),
+                    MenuFileExportIci => self.export_ici(),
+                    MenuFileExportPng => self.export_png(),
+                    MenuFileQuit => {
+                        if self.has_changes() {
+                            self.quit_pending = true;
+                        } else {
+                            self.result = Pop(None);
+                        }
+                    }
+                    MenuEditUndo => self.undo(),
+                    MenuEditRedo => self.redo(),
+                    MenuModeMap => self.set_editing_tiles(false),
+                    MenuModeTiles => self.set_editing_tiles(true),
+                    MenuCanvasZoomIn => self.canvas.zoom_in(None),
+                    MenuCanvasZoomOut => self.canvas.zoom_out(None),
+                    MenuCanvasZoomFit => self.canvas.fit(),
+                    MenuFile | MenuFileExport | MenuEdit | MenuMode | MenuCanvas => {}
+                }
+            }
+            return;
+        }
+        if let Some(tool) = self.tools.on_mouse_click(down_at, mouse.xy) {
+            self.canvas.set_tool(tool)
+        }
+        if self.tile_picker.on_mouse_click(mouse.xy) {
+            self.select_tile(self.tile_picker.get_selected());
+        }
+        if self.editing_tiles && self.palette.on_mouse_click(mouse.xy) {
+            self.canvas.set_color_index(self.palette.get_selected_idx());
+        }
+        self.preview.on_mouse_click(mouse.xy);
+        if self.editing_tiles {
+            self.canvas.on_mouse_up(mouse.xy, &mut self.tile_history);
+        } else {
+            self.canvas.on_mouse_up(mouse.xy, &mut self.map_history);
+        }
+        self.refresh();
+    }
+
+    fn on_scroll(&mut self, mouse: &MouseData, _: isize, y_diff: isize, _: &FxHashSet<KeyCode>) {
+        self.tile_picker.on_scroll(mouse.xy, y_diff);
+        if self.editing_tiles {
+            self.palette.on_scroll(mouse.xy, y_diff);
+        }
+        if !self.menubar.is_expanded() {
+            self.canvas.on_scroll(mouse.xy, y_diff);
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        mouse: &MouseData,
+        held: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SUR {
+        self.canvas.update(timing);
+        self.canvas.set_shift_pressed(
+            held.contains(&KeyCode::ShiftLeft) || held.contains(&KeyCode::ShiftRight),
+        );
+        self.canvas.on_mouse_move(mouse.xy);
+        let blocked = self.quit_pending || self.warning.is_some() || self.menubar.is_expanded();
+        let view_moving = !blocked
+            && self
+                .canvas
+                .on_view_drag(mouse, held.contains(&KeyCode::Space));
+
+        if mouse.is_down(MouseButton::Left).is_some() && !blocked && !view_moving {
+            if self.editing_tiles {
+                if self.canvas.on_mouse_down(mouse.xy, &mut self.tile_history) {
+                    self.canvas
+                        .set_image(self.tile_history.get_current_image().clone());
+                    self.tile_picker.update_tile(
+                        self.tile_history.active_frame(),
+                        self.tile_history.get_current_image(),
+                    );
+                    self.update_preview();
+                }
+            } else if self.canvas.on_mouse_down(mouse.xy, &mut self.map_history) {
+                self.canvas
+                    .set_image(self.map_history.get_current_image().clone());
+                self.update_preview();
+            }
+        }
+
+        self.menubar.on_mouse_move(mouse.xy);
+
+        self.result.clone()
+    }
+
+    fn resuming(&mut self, _: Option<SceneResult>) {
+        self.result = Nothing;
+    }
+
+    fn is_dialog(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,74 @@
+use crate::scenes::tilemap_editor_ui::MenuId::*;
+use crate::{HEIGHT, WIDTH};
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::prelude::*;
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(clippy::enum_variant_names)] //matches the editor menu ids
+pub enum MenuId {
+    MenuFile,
+    MenuFileSave,
+    MenuFileSaveAs,
+    MenuFileExport,
+    MenuFileExportIci,
+    MenuFileExportPng,
+    MenuFileQuit,
+    MenuEdit,
+    MenuEditUndo,
+    MenuEditRedo,
+    MenuMode,
+    MenuModeMap,
+    MenuModeTiles,
+    MenuCanvas,
+    MenuCanvasZoomIn,
+    MenuCanvasZoomOut,
+    MenuCanvasZoomFit,
+}
+
+pub(super) fn create_menubar(style: &UiStyle) -> MenuBar<MenuId> {
+    MenuBar::new(
+        &style.menu,
+        Coord::default(),
+        (WIDTH, HEIGHT),
+        true,
+        &[
+            MenuBarItem::new(
+                MenuFile,
+                "File",
+                vec![
+                    MenuBarItem::new_button(MenuFileSave, "Save"),
+                    MenuBarItem::new_button(MenuFileSaveAs, "Save As"),
+                    MenuBarItem::new_menu(
+                        MenuFileExport,
+                        "Export",
+                        &[(MenuFileExportIci, "ICI"), (MenuFileExportPng, "PNG")],
+                    ),
+                    MenuBarItem::new_button(MenuFileQuit, "Quit"),
+                ],
+            ),
+            MenuBarItem::new(
+                MenuEdit,
+                "Edit",
+                vec![
+                    MenuBarItem::new_button(MenuEditUndo, "Undo"),
+                    MenuBarItem::new_button(MenuEditRedo, "Redo"),
+                ],
+            ),
+            MenuBarItem::new_options(
+                MenuMode,
+                "Mode",
+                &[(MenuModeMap, "Map"), (MenuModeTiles, "Tiles")],
+                0,
+            ),
+            MenuBarItem::new(
+                MenuCanvas,
+                "Canvas",
+                vec![
+                    MenuBarItem::new_button(MenuCanvasZoomIn, "Zoom in"),
+                    MenuBarItem::new_button(MenuCanvasZoomOut, "Zoom out"),
+                    MenuBarItem::new_button(MenuCanvasZoomFit, "Zoom to fit"),
+                ],
+            ),
+        ],
+    )
+}
@@ -0,0 +1,215 @@
+use crate::image::tween::TweenKind;
+use crate::{SceneName, SceneResult, HEIGHT, SUR, WIDTH};
+use pixels_graphics_lib::prelude::SceneUpdateResult::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::layout::relative::LayoutContext;
+use pixels_graphics_lib::ui::prelude::*;
+use pixels_graphics_lib::*;
+
+pub struct TweenDialog {
+    result: SUR,
+    start: usize,
+    end: usize,
+    bg: ShapeCollection,
+    title: Label,
+    frames: Label,
+    count_label: Label,
+    count: TextField,
+    dissolve: Checkbox,
+    dissolve_note: Label,
+    cancel: Button,
+    submit: Button,
+    alert: Option<Alert>,
+    alert_style: AlertStyle,
+}
+
+impl TweenDialog {
+    pub fn new(start: usize, end: usize, style: &UiStyle) -> Box<Self> {
+        let bg = dialog_background(WIDTH, HEIGHT, &style.dialog);
+        let mut title = Label::singleline(
+            "In-betweens",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard8x10,
+            style.dialog.bounds.width(),
+        );
+        let mut frames = Label::singleline(
+            &format!("Between frames {} and {}", start + 1, end + 1),
+            (0, 0),
+            WHITE,
+            PixelFont::Standard4x5,
+            style.dialog.bounds.width(),
+        );
+        let mut count_label = Label::singleline(
+            "Frames to add",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard6x7,
+            style.dialog.bounds.width(),
+        );
+        let mut count = TextField::new(
+            (0, 0),
+            3,
+            PixelFont::Standard6x7,
+            (None, None),
+            "1",
+            &[TextFilter::Numbers],
+            &style.text_field,
+        );
+        let mut dissolve = Checkbox::new((0, 0), "Cross-dissolve", false, &style.checkbox);
+        let mut dissolve_note = Label::singleline(
+            "Otherwise the first frame is moved",
+            (0, 0),
+            WHITE,
+            PixelFont::Standard4x5,
+            style.dialog.bounds.width(),
+        );
+        let mut cancel = Button::new((0, 0), "Cancel", Some(80), &style.button);
+        let mut submit = Button::new((0, 0), "Add", Some(80), &style.button);
+
+        let context = LayoutContext::new(style.dialog.bounds.clone());
+
+        layout!(context, title, align_top, px!(8));
+        layout!(context, title, align_centerh);
+
+        layout!(context, frames, align_left, px!(6));
+        layout!(context, frames, top_to_bottom_of title, px!(6));
+
+        layout!(context, count_label, align_left, px!(6));
+        layout!(context, count_label, top_to_bottom_of frames, px!(10));
+
+        layout!(context, count, align_left, px!(6));
+        layout!(context, count, top_to_bottom_of count_label, px!(4));
+
+        layout!(context, dissolve, align_left, px!(6));
+        layout!(context, dissolve, top_to_bottom_of count, px!(12));
+
+        layout!(context, dissolve_note, align_left, px!(6));
+        layout!(context, dissolve_note, top_to_bottom_of dissolve, px!(4));
+
+        layout!(context, submit, align_bottom, px!(4));
+        layout!(context, cancel, align_bottom, px!(4));
+        layout!(context, submit, align_right, px!(4));
+        layout!(context, cancel, align_left, px!(4));
+
+        count.focus();
+
+        Box::new(TweenDialog {
+            result: Nothing,
+            start,
+            end,
+            bg,
+            title,
+            frames,
+            count_label,
+            count,
+            dissolve,
+            dissolve_note,
+            cancel,
+            submit,
+            alert: None,
+            alert_style: style.alert.clone(),
+        })
+    }
+}
+
+impl TweenDialog {
+    fn verify(&mut self) {
+        match self.count.content().parse::<u8>() {
+            Ok(count) if count > 0 => {
+                let kind = if self.dissolve.is_checked() {
+                    TweenKind::Dissolve
+                } else {
+                    TweenKind::Move
+                };
+                self.result = Pop(Some(SceneResult::Tween(
+                    self.start,
+                    self.end,
+                    count as usize,
+                    kind,
+                )));
+            }
+            _ => {
+                self.alert = Some(Alert::new_warning(
+                    &["Invalid value, must", "be between 1 and 255"],
+                    WIDTH,
+                    HEIGHT,
+                    &self.alert_style,
+                ));
+            }
+        }
+    }
+}
+
+impl Scene<SceneResult, SceneName> for TweenDialog {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData, _: &FxHashSet<KeyCode>) {
+        self.bg.render(graphics);
+        render!(
+            graphics,
+            mouse,
+            self.title,
+            self.frames,
+            self.count_label,
+            self.count,
+            self.dissolve,
+            self.dissolve_note,
+            self.submit,
+            self.cancel
+        );
+        if let Some(alert) = &self.alert {
+            alert.render(graphics, mouse);
+        }
+    }
+
+    fn on_key_up(&mut self, key: KeyCode, _: &MouseData, held: &FxHashSet<KeyCode>) {
+        if self.alert.is_some() {
+            return;
+        }
+        match key {
+            KeyCode::Escape => self.result = Pop(None),
+            KeyCode::Enter => self.verify(),
+            _ => self.count.on_key_press(key, held),
+        }
+    }
+
+    fn on_mouse_click(
+        &mut self,
+        down_at: Coord,
+        mouse: &MouseData,
+        mouse_button: MouseButton,
+        _: &FxHashSet<KeyCode>,
+    ) {
+        if mouse_button == MouseButton::Left {
+            if let Some(alert) = &mut self.alert {
+                if alert.on_mouse_click(down_at, mouse.xy).is_some() {
+                    self.alert = None;
+                }
+            } else {
+                self.count.on_mouse_click(down_at, mouse.xy);
+                let _ = self.dissolve.on_mouse_click(down_at, mouse.xy);
+                if self.submit.on_mouse_click(down_at, mouse.xy) {
+                    self.verify();
+                }
+                if self.cancel.on_mouse_click(down_at, mouse.xy) {
+                    self.result = Pop(None);
+                }
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        timing: &Timing,
+        _: &MouseData,
+        _: &FxHashSet<KeyCode>,
+        _: &Window,
+    ) -> SceneUpdateResult<SceneResult, SceneName> {
+        self.count.update(timing);
+
+        self.result.clone()
+    }
+
+    fn is_dialog(&self) -> bool {
+        true
+    }
+}
@@ -1,7 +1,13 @@
+use crate::image::layers::overlay;
+use crate::image::trim::get_transparent_colors;
 use crate::ui::edit_history::EditHistory;
+use crate::ui::image_fill::fill_pixels;
+use crate::ui::reference::Reference;
+use crate::ui::selection::{get_clipboard, set_clipboard, Clip, Floating, Selection};
 use log::error;
 use pixels_graphics_lib::prelude::*;
 use pixels_graphics_lib::ui::prelude::*;
+use std::cmp::Ordering;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Tool {
@@ -11,6 +17,41 @@ pub enum Tool {
     Fill,
     Circle,
     Ellipse,
+    Select,
+    Wand,
+}
+
+const MAX_ZOOM: usize = 64;
+const SCROLLBAR_SIZE: usize = 3;
+/// The pixel grid is hidden below this zoom level as it would cover most of the image
+const MIN_PIXEL_GRID_ZOOM: usize = 4;
+/// Seconds between each step of the selection outline animation
+const ANTS_STEP: f64 = 0.15;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ViewDrag {
+    /// Last mouse position
+    Pan(Coord),
+    HorzScrollbar,
+    VertScrollbar,
+}
+
+/// How a new selection is combined with the current one
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SelectMode {
+    Replace,
+    /// Shift held
+    Add,
+    /// Alt held
+    Subtract,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SelectDrag {
+    /// Image position the selection started at
+    Marquee(Coord, SelectMode),
+    /// Last image position while moving floating pixels
+    Move(Coord),
 }
 
 #[derive(Debug)]
@@ -23,9 +64,36 @@ pub struct Canvas {
     cursor_color: Color,
     selected_color_idx: u8,
     tool: Tool,
-    first_click_at: Option<(u8, u8)>,
+    first_click_at: Option<Coord>,
     state: ViewState,
     shift_pressed: bool,
+    alt_pressed: bool,
+    /// Set while drawing with the keyboard, replaces the mouse position
+    keyboard_cursor: Option<(u8, u8)>,
+    last_mouse_xy: Coord,
+    /// Position of the image's top left relative to `bounds`
+    view_offset: Coord,
+    view_drag: Option<ViewDrag>,
+    pixel_grid: Option<Color>,
+    tile_grid: Option<((u8, u8), Color)>,
+    /// Frames drawn under transparent pixels, furthest first
+    onion_skin: Vec<(IndexedImage, Color)>,
+    /// Visible layers below and above the image being edited
+    layers_around: (Option<IndexedImage>, Option<IndexedImage>),
+    /// If the image being edited is shown, it can still be edited when hidden
+    layer_visible: bool,
+    /// If drawing past an edge continues on the opposite side
+    wrap: bool,
+    /// When editing a tilemap each pixel is drawn as a tile, 0 is empty otherwise it's the tile index + 1
+    tiles: Vec<IndexedImage>,
+    reference: Option<Reference>,
+    selection: Option<Selection>,
+    /// Selection from before the current marquee, the marquee is added to or subtracted from this
+    marquee_base: Option<Selection>,
+    floating: Option<Floating>,
+    select_drag: Option<SelectDrag>,
+    ants_phase: usize,
+    ants_timer: f64,
 }
 
 impl Canvas {
@@ -42,31 +110,256 @@ impl Canvas {
             first_click_at: None,
             state: ViewState::Normal,
             shift_pressed: false,
+            alt_pressed: false,
+            keyboard_cursor: None,
+            last_mouse_xy: Coord::default(),
+            view_offset: Coord::default(),
+            view_drag: None,
+            pixel_grid: None,
+            tile_grid: None,
+            onion_skin: vec![],
+            layers_around: (None, None),
+            layer_visible: true,
+            wrap: false,
+            tiles: vec![],
+            reference: None,
+            selection: None,
+            marquee_base: None,
+            floating: None,
+            select_drag: None,
+            ants_phase: 0,
+            ants_timer: 0.0,
         }
     }
 }
 
 impl Canvas {
+    /// Zoom and pan are kept unless the image size has changed, in which case the image is fit to the canvas
     pub fn set_image(&mut self, image: IndexedImage) {
-        let width = image.width() as usize;
-        let height = image.height() as usize;
+        let size_changed =
+            image.width() != self.image.width() || image.height() != self.image.height();
         self.image = image;
-        let side = width.max(height);
-        let length = self.bounds.width().min(self.bounds.height());
-        self.screen_px_per_image_px = length / side;
-        self.inner_bounds = Rect::new_with_size(
-            (0, 0),
-            width * self.screen_px_per_image_px - 1,
-            height * self.screen_px_per_image_px - 1,
+        if size_changed {
+            self.selection = None;
+            self.floating = None;
+            self.fit();
+        }
+    }
+
+    /// Sets the zoom so the whole image is visible and centers it
+    pub fn fit(&mut self) {
+        let width = self.image.width() as usize;
+        let height = self.image.height() as usize;
+        self.screen_px_per_image_px = (self.bounds.width() / width)
+            .min(self.bounds.height() / height)
+            .clamp(1, MAX_ZOOM);
+        self.view_offset = Coord::default();
+        self.update_view();
+    }
+
+    /// Zoom in, keeping the image pixel under `anchor` in place
+    ///
+    /// If `anchor` is None or outside the canvas then the center of the canvas is used
+    pub fn zoom_in(&mut self, anchor: Option<Coord>) {
+        self.set_zoom(self.screen_px_per_image_px + 1, anchor);
+    }
+
+    /// Zoom out, keeping the image pixel under `anchor` in place
+    ///
+    /// If `anchor` is None or outside the canvas then the center of the canvas is used
+    pub fn zoom_out(&mut self, anchor: Option<Coord>) {
+        self.set_zoom(self.screen_px_per_image_px.saturating_sub(1), anchor);
+    }
+
+    pub fn on_scroll(&mut self, mouse_xy: Coord, y_diff: isize) {
+        if !self.bounds.contains(mouse_xy) {
+            return;
+        }
+        match y_diff.cmp(&0) {
+            Ordering::Less => self.zoom_in(Some(mouse_xy)),
+            Ordering::Greater => self.zoom_out(Some(mouse_xy)),
+            Ordering::Equal => {}
+        }
+    }
+
+    /// Handles panning (middle drag or `pan_held` and left drag) and dragging the scrollbars
+    ///
+    /// Returns true if the view is being moved, in which case the mouse shouldn't be used for drawing
+    pub fn on_view_drag(&mut self, mouse: &MouseData, pan_held: bool) -> bool {
+        let left = mouse.is_down(MouseButton::Left);
+        let middle = mouse.is_down(MouseButton::Middle);
+        if left.is_none() && middle.is_none() {
+            self.view_drag = None;
+            return false;
+        }
+        if self.view_drag.is_none() {
+            self.view_drag = match (middle, left) {
+                (Some(down_at), _) if self.bounds.contains(down_at) => {
+                    Some(ViewDrag::Pan(mouse.xy))
+                }
+                (_, Some(down_at)) if self.bounds.contains(down_at) => {
+                    if pan_held {
+                        Some(ViewDrag::Pan(mouse.xy))
+                    } else if self
+                        .horz_scrollbar()
+                        .map(|(track, _)| track.contains(down_at))
+                        .unwrap_or_default()
+                    {
+                        Some(ViewDrag::HorzScrollbar)
+                    } else if self
+                        .vert_scrollbar()
+                        .map(|(track, _)| track.contains(down_at))
+                        .unwrap_or_default()
+                    {
+                        Some(ViewDrag::VertScrollbar)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+        }
+        match self.view_drag {
+            Some(ViewDrag::Pan(last)) => {
+                self.view_offset = self.view_offset + (mouse.xy - last);
+                self.view_drag = Some(ViewDrag::Pan(mouse.xy));
+                self.update_view();
+            }
+            Some(ViewDrag::HorzScrollbar) => {
+                if let Some((track, _)) = self.horz_scrollbar() {
+                    let image_width = self.image_screen_size().0;
+                    let pos = (mouse.xy.x - track.left()) * image_width / track.width() as isize;
+                    self.view_offset.x = self.bounds.width() as isize / 2 - pos;
+                    self.update_view();
+                }
+            }
+            Some(ViewDrag::VertScrollbar) => {
+                if let Some((track, _)) = self.vert_scrollbar() {
+                    let image_height = self.image_screen_size().1;
+                    let pos = (mouse.xy.y - track.top()) * image_height / track.height() as isize;
+                    self.view_offset.y = self.bounds.height() as isize / 2 - pos;
+                    self.update_view();
+                }
+            }
+            None => return false,
+        }
+        true
+    }
+
+    fn set_zoom(&mut self, zoom: usize, anchor: Option<Coord>) {
+        let zoom = zoom.clamp(1, MAX_ZOOM);
+        if zoom == self.screen_px_per_image_px {
+            return;
+        }
+        let anchor = anchor
+            .filter(|xy| self.bounds.contains(*xy))
+            .unwrap_or(self.bounds.center())
+            - self.bounds.top_left();
+        let scale = zoom as f32 / self.screen_px_per_image_px as f32;
+        let anchor_offset = anchor - self.view_offset;
+        self.view_offset = anchor
+            - coord!(
+                (anchor_offset.x as f32 * scale) as isize,
+                (anchor_offset.y as f32 * scale) as isize
+            );
+        self.screen_px_per_image_px = zoom;
+        self.update_view();
+    }
+
+    /// Size of the whole image on screen
+    fn image_screen_size(&self) -> (isize, isize) {
+        (
+            (self.image.width() as usize * self.screen_px_per_image_px) as isize,
+            (self.image.height() as usize * self.screen_px_per_image_px) as isize,
+        )
+    }
+
+    /// Screen position of the image's top left, this may be outside of `bounds`
+    fn image_origin(&self) -> Coord {
+        self.bounds.top_left() + self.view_offset
+    }
+
+    /// Keeps the image on screen (centering it if it's smaller than the canvas) and
+    /// updates `inner_bounds` to the visible part of the image
+    fn update_view(&mut self) {
+        let (image_width, image_height) = self.image_screen_size();
+        let width = self.bounds.width() as isize;
+        let height = self.bounds.height() as isize;
+        self.view_offset.x = if image_width <= width {
+            (width - image_width) / 2
+        } else {
+            self.view_offset.x.clamp(width - image_width, 0)
+        };
+        self.view_offset.y = if image_height <= height {
+            (height - image_height) / 2
+        } else {
+            self.view_offset.y.clamp(height - image_height, 0)
+        };
+        let origin = self.image_origin();
+        self.inner_bounds = Rect::new(
+            coord!(
+                origin.x.max(self.bounds.left()),
+                origin.y.max(self.bounds.top())
+            ),
+            coord!(
+                (origin.x + image_width - 1).min(self.bounds.right()),
+                (origin.y + image_height - 1).min(self.bounds.bottom())
+            ),
         );
-        let max_width = self.bounds.width() / 2;
-        let max_height = self.bounds.height() / 2;
-        let image_width = self.inner_bounds.width() / 2;
-        let image_height = self.inner_bounds.height() / 2;
-        self.inner_bounds = self.inner_bounds.move_to(coord!(
-            self.bounds.top_left().x + ((max_width - image_width) as isize),
-            self.bounds.top_left().y + ((max_height - image_height) as isize),
-        ));
+    }
+
+    /// Track and thumb of the horizontal scrollbar, if the image is wider than the canvas
+    fn horz_scrollbar(&self) -> Option<(Rect, Rect)> {
+        let image_width = self.image_screen_size().0;
+        let width = self.bounds.width() as isize;
+        if image_width <= width {
+            return None;
+        }
+        let track = Rect::new(
+            (
+                self.bounds.left(),
+                self.bounds.bottom() - SCROLLBAR_SIZE as isize,
+            ),
+            self.bounds.bottom_right(),
+        );
+        let track_len = track.width() as isize;
+        let start = -self.view_offset.x * track_len / image_width;
+        let len = (width * track_len / image_width).max(SCROLLBAR_SIZE as isize);
+        let thumb = Rect::new(
+            (track.left() + start, track.top()),
+            (
+                (track.left() + start + len).min(track.right()),
+                track.bottom(),
+            ),
+        );
+        Some((track, thumb))
+    }
+
+    /// Track and thumb of the vertical scrollbar, if the image is taller than the canvas
+    fn vert_scrollbar(&self) -> Option<(Rect, Rect)> {
+        let image_height = self.image_screen_size().1;
+        let height = self.bounds.height() as isize;
+        if image_height <= height {
+            return None;
+        }
+        let track = Rect::new(
+            (
+                self.bounds.right() - SCROLLBAR_SIZE as isize,
+                self.bounds.top(),
+            ),
+            self.bounds.bottom_right(),
+        );
+        let track_len = track.height() as isize;
+        let start = -self.view_offset.y * track_len / image_height;
+        let len = (height * track_len / image_height).max(SCROLLBAR_SIZE as isize);
+        let thumb = Rect::new(
+            (track.left(), track.top() + start),
+            (
+                track.right(),
+                (track.top() + start + len).min(track.bottom()),
+            ),
+        );
+        Some((track, thumb))
     }
 
     pub fn get_image(&self) -> &IndexedImage {
@@ -74,50 +367,332 @@ impl Canvas {
     }
 
     pub fn on_mouse_down(&mut self, mouse_xy: Coord, edit_history: &mut EditHistory) -> bool {
-        if self.inner_bounds.contains(mouse_xy) && self.state == ViewState::Normal {
-            let (x, y) = self.mouse_to_image(mouse_xy);
+        if self.tool == Tool::Select {
+            return self.on_select_drag(mouse_xy, edit_history);
+        }
+        if self.can_draw_at(mouse_xy) && self.state == ViewState::Normal {
+            let xy = self.mouse_to_image(mouse_xy);
             if self.tool == Tool::Pencil {
                 edit_history
-                    .add_pencil((x, y), self.selected_color_idx)
+                    .add_pencil(self.wrap_coord(xy), self.selected_color_idx)
                     .unwrap();
                 return true;
             } else if self.first_click_at.is_none() {
-                self.first_click_at = Some((x, y));
+                self.first_click_at = Some(xy);
             }
         }
         false
     }
 
     pub fn on_mouse_up(&mut self, mouse_xy: Coord, edit_history: &mut EditHistory) {
-        if self.inner_bounds.contains(mouse_xy) && self.state == ViewState::Normal {
-            let (x, y) = self.mouse_to_image(mouse_xy);
-            let result = match (self.tool, self.first_click_at) {
-                (Tool::Line, Some(start)) => {
-                    edit_history.add_line(start, (x, y), self.selected_color_idx)
-                }
-                (Tool::Rect, Some(start)) => {
-                    edit_history.add_rect(start, (x, y), self.selected_color_idx)
+        if let Some(SelectDrag::Marquee(start, SelectMode::Replace)) = self.select_drag.take() {
+            if self.mouse_to_image(mouse_xy) == start {
+                self.selection = None;
+            }
+        }
+        if self.can_draw_at(mouse_xy) && self.state == ViewState::Normal {
+            let xy = self.mouse_to_image(mouse_xy);
+            self.finish_shape(xy, edit_history);
+        }
+        self.first_click_at = None;
+    }
+
+    /// Moves the keyboard cursor, starting it in the middle of the image if it's not visible
+    pub fn move_keyboard_cursor(&mut self, x_diff: isize, y_diff: isize) {
+        if self.state != ViewState::Normal {
+            return;
+        }
+        let (x, y) = match self.keyboard_cursor {
+            Some((x, y)) => (
+                (x as isize + x_diff).clamp(0, self.image.width() as isize - 1) as u8,
+                (y as isize + y_diff).clamp(0, self.image.height() as isize - 1) as u8,
+            ),
+            None => (self.image.width() / 2, self.image.height() / 2),
+        };
+        self.keyboard_cursor = Some((x, y));
+    }
+
+    /// Acts like a click at the keyboard cursor
+    ///
+    /// Pencil, fill and wand are used straight away, for the other tools the first press starts
+    /// a shape and the second press finishes it
+    ///
+    /// Returns true if the image was changed
+    pub fn on_keyboard_paint(&mut self, edit_history: &mut EditHistory) -> bool {
+        if self.state != ViewState::Normal {
+            return false;
+        }
+        if let Some(xy) = self.keyboard_cursor {
+            if self.tool == Tool::Pencil {
+                if let Err(e) = edit_history.add_pencil(xy, self.selected_color_idx) {
+                    error!("Error drawing {:?} at {xy:?}: {e:?}", self.tool);
                 }
-                (Tool::Fill, Some(start)) => edit_history.add_fill(start, self.selected_color_idx),
-                (Tool::Ellipse, Some(start)) => edit_history.add_ellipse(
-                    start,
-                    (x, y),
-                    self.shift_pressed,
-                    self.selected_color_idx,
-                ),
-                (Tool::Circle, Some(start)) => edit_history.add_circle(
-                    start,
-                    (x, y),
-                    self.shift_pressed,
-                    self.selected_color_idx,
-                ),
-                _ => Ok(()),
-            };
-            if let Err(e) = result {
-                error!("Error drawing {:?} at {x},{y}: {e:?}", self.tool);
+                return true;
+            } else if matches!(self.tool, Tool::Fill | Tool::Wand) {
+                self.first_click_at = Some(coord!(xy));
+                self.finish_shape(coord!(xy), edit_history);
+                self.first_click_at = None;
+                return self.tool == Tool::Fill;
+            } else if self.first_click_at.is_none() {
+                self.first_click_at = Some(coord!(xy));
+            } else {
+                self.finish_shape(coord!(xy), edit_history);
+                self.first_click_at = None;
+                return true;
             }
         }
+        false
+    }
+
+    /// Leaves keyboard cursor mode and drops any unfinished shape, floating pixels and selection
+    pub fn cancel(&mut self) {
+        self.keyboard_cursor = None;
         self.first_click_at = None;
+        self.selection = None;
+        self.cancel_floating();
+    }
+
+    /// Starts or continues selecting or moving pixels
+    ///
+    /// Pressing inside floating pixels moves them, pressing inside the selection lifts the
+    /// pixels so they can be moved and pressing anywhere else starts a new selection
+    /// (or changes the current selection if shift or alt is held)
+    ///
+    /// Returns true if floating pixels were committed
+    fn on_select_drag(&mut self, mouse_xy: Coord, edit_history: &mut EditHistory) -> bool {
+        if self.state != ViewState::Normal {
+            return false;
+        }
+        let xy = self.mouse_to_image(mouse_xy);
+        match self.select_drag {
+            Some(SelectDrag::Marquee(start, mode)) => {
+                let rect = Selection::from_rect(self.image.width(), self.image.height(), start, xy);
+                self.selection = combine_selection(self.marquee_base.clone(), rect, mode);
+            }
+            Some(SelectDrag::Move(last)) => {
+                if let Some(floating) = &mut self.floating {
+                    floating.pos = floating.pos + (xy - last);
+                }
+                self.select_drag = Some(SelectDrag::Move(xy));
+            }
+            None => {
+                if !self.can_draw_at(mouse_xy) {
+                    return false;
+                }
+                let mode = self.select_mode();
+                if self.floating.as_ref().is_some_and(|f| f.contains(xy)) {
+                    self.select_drag = Some(SelectDrag::Move(xy));
+                } else if let Some(selection) = self
+                    .selection
+                    .take_if(|s| mode == SelectMode::Replace && s.contains(xy))
+                {
+                    self.floating = Floating::lift(&self.image, &selection);
+                    self.select_drag = Some(SelectDrag::Move(xy));
+                } else {
+                    let committed = self.commit_floating(edit_history);
+                    self.select_drag = Some(SelectDrag::Marquee(xy, mode));
+                    self.marquee_base = self.selection.clone();
+                    let rect =
+                        Selection::from_rect(self.image.width(), self.image.height(), xy, xy);
+                    self.selection = combine_selection(self.marquee_base.clone(), rect, mode);
+                    return committed;
+                }
+            }
+        }
+        false
+    }
+
+    fn select_mode(&self) -> SelectMode {
+        if self.alt_pressed {
+            SelectMode::Subtract
+        } else if self.shift_pressed {
+            SelectMode::Add
+        } else {
+            SelectMode::Replace
+        }
+    }
+
+    /// Selects the contiguous area of the same palette index as `xy`
+    fn wand_select(&mut self, xy: (u8, u8)) -> Result<(), IndexedImageError> {
+        let idxs = fill_pixels(&self.image, xy, self.wrap)?;
+        let area = Selection::from_idxs(self.image.width(), self.image.height(), &idxs);
+        self.selection = combine_selection(self.selection.take(), area, self.select_mode());
+        Ok(())
+    }
+
+    /// Selects every pixel using palette index `color`, shift and alt work as with the other
+    /// selection tools
+    ///
+    /// Floating pixels must be committed first
+    pub fn select_color(&mut self, color: u8) {
+        let pixels = Selection::from_color(&self.image, color);
+        self.selection = combine_selection(self.selection.take(), pixels, self.select_mode());
+    }
+
+    pub fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+
+    /// Writes the floating pixels to the image, they stay selected
+    ///
+    /// Returns true if there were floating pixels
+    pub fn commit_floating(&mut self, edit_history: &mut EditHistory) -> bool {
+        let Some(floating) = self.floating.take() else {
+            return false;
+        };
+        self.select_drag = None;
+        let (width, height) = (self.image.width(), self.image.height());
+        self.selection = Some(floating.selection(width, height)).filter(|s| !s.is_empty());
+        if let Err(e) =
+            edit_history.add_paste(floating.cleared(), &floating.image_pixels(width, height))
+        {
+            error!("Error committing floating pixels: {e:?}");
+        }
+        true
+    }
+
+    /// Drops the floating pixels without changing the image
+    pub fn cancel_floating(&mut self) {
+        self.floating = None;
+        self.select_drag = None;
+    }
+
+    /// Copies the floating or selected pixels to the clipboard
+    ///
+    /// Returns true if anything was copied
+    pub fn copy_selection(&self) -> bool {
+        let clip = match (&self.floating, &self.selection) {
+            (Some(floating), _) => Some(floating.to_clip(self.image.get_palette())),
+            (None, Some(selection)) => Clip::from_selection(&self.image, selection),
+            (None, None) => None,
+        };
+        match clip {
+            Some(clip) => {
+                set_clipboard(clip);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies the floating or selected pixels to the clipboard and then clears them
+    ///
+    /// Returns true if the image was changed
+    pub fn cut_selection(&mut self, edit_history: &mut EditHistory) -> bool {
+        if !self.copy_selection() {
+            return false;
+        }
+        let result = if let Some(floating) = self.floating.take() {
+            edit_history.add_cut(floating.cleared().to_vec())
+        } else if let Some(selection) = self.selection.take() {
+            edit_history.add_cut(selection.idxs())
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            error!("Error cutting selection: {e:?}");
+        }
+        true
+    }
+
+    /// Adds the clipboard as floating pixels at the top left of the selection or view
+    ///
+    /// Colors are converted to the nearest in this image's palette if the palettes are different
+    ///
+    /// Returns true if the image was changed (by committing existing floating pixels)
+    pub fn paste(&mut self, edit_history: &mut EditHistory) -> bool {
+        let Some(clip) = get_clipboard() else {
+            return false;
+        };
+        let committed = self.commit_floating(edit_history);
+        let pos = self
+            .selection
+            .take()
+            .and_then(|s| s.bounds())
+            .map(|(top_left, _)| top_left)
+            .unwrap_or_else(|| self.mouse_to_image(self.inner_bounds.top_left()));
+        self.floating = Some(Floating::paste(pos, &clip, self.image.get_palette()));
+        committed
+    }
+
+    pub fn on_mouse_move(&mut self, mouse_xy: Coord) {
+        if mouse_xy != self.last_mouse_xy && self.inner_bounds.contains(mouse_xy) {
+            self.keyboard_cursor = None;
+        }
+        self.last_mouse_xy = mouse_xy;
+    }
+
+    fn finish_shape(&mut self, end: Coord, edit_history: &mut EditHistory) {
+        let result = match (self.tool, self.first_click_at) {
+            (Tool::Line, Some(start)) => edit_history.add_line(start, end, self.selected_color_idx),
+            (Tool::Rect, Some(start)) => edit_history.add_rect(start, end, self.selected_color_idx),
+            (Tool::Fill, Some(start)) => {
+                edit_history.add_fill(self.wrap_coord(start), self.selected_color_idx)
+            }
+            (Tool::Ellipse, Some(start)) => {
+                edit_history.add_ellipse(start, end, self.shift_pressed, self.selected_color_idx)
+            }
+            (Tool::Circle, Some(start)) => {
+                edit_history.add_circle(start, end, self.shift_pressed, self.selected_color_idx)
+            }
+            (Tool::Select, Some(start)) => {
+                self.commit_floating(edit_history);
+                let rect =
+                    Selection::from_rect(self.image.width(), self.image.height(), start, end);
+                self.selection = combine_selection(self.selection.take(), rect, self.select_mode());
+                Ok(())
+            }
+            (Tool::Wand, Some(start)) => {
+                self.commit_floating(edit_history);
+                self.wand_select(self.wrap_coord(start))
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("Error drawing {:?} at {end:?}: {e:?}", self.tool);
+        }
+    }
+
+    pub fn set_reference(&mut self, reference: Option<Reference>) {
+        self.reference = reference;
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    pub fn set_pixel_grid(&mut self, color: Option<Color>) {
+        self.pixel_grid = color;
+    }
+
+    /// Lines are drawn every `size` image pixels
+    pub fn set_tile_grid(&mut self, size: Option<(u8, u8)>, color: Color) {
+        self.tile_grid = size.map(|size| (size, color));
+    }
+
+    /// `frames` are drawn in order under transparent pixels, mixed with their tint color
+    ///
+    /// The tint's alpha is used for the frame's opacity
+    pub fn set_onion_skin(&mut self, frames: Vec<(IndexedImage, Color)>) {
+        self.onion_skin = frames;
+    }
+
+    pub fn set_layers(
+        &mut self,
+        (below, above): (Option<IndexedImage>, Option<IndexedImage>),
+        visible: bool,
+    ) {
+        self.layers_around = (below, above);
+        self.layer_visible = visible;
+    }
+
+    /// Draws each pixel of the image as the matching tile, an empty list draws the image normally
+    pub fn set_tiles(&mut self, tiles: Vec<IndexedImage>) {
+        self.tiles = tiles;
     }
 
     pub fn set_trans_background_colors(&mut self, trans_background_colors: (Color, Color)) {
@@ -131,16 +706,77 @@ impl Canvas {
         }
     }
 
-    fn mouse_to_image(&self, mouse_xy: Coord) -> (u8, u8) {
-        let offset_xy = mouse_xy - self.inner_bounds.top_left();
-        let img_coord = offset_xy / self.screen_px_per_image_px;
-        let x = img_coord.x.clamp(0, 255) as u8;
-        let y = img_coord.y.clamp(0, 255) as u8;
-        (x, y)
+    /// When wrapping this can be outside of the image
+    fn mouse_to_image(&self, mouse_xy: Coord) -> Coord {
+        let offset_xy = mouse_xy - self.image_origin();
+        let px_size = self.screen_px_per_image_px as isize;
+        let img_coord = coord!(
+            offset_xy.x.div_euclid(px_size),
+            offset_xy.y.div_euclid(px_size)
+        );
+        if self.wrap {
+            img_coord
+        } else {
+            coord!(
+                img_coord.x.clamp(0, self.image.width() as isize - 1),
+                img_coord.y.clamp(0, self.image.height() as isize - 1)
+            )
+        }
+    }
+
+    /// Image pixel the next click or keyboard paint would draw at
+    fn cursor_end(&self, mouse_xy: Coord) -> Option<Coord> {
+        if let Some(xy) = self.keyboard_cursor {
+            Some(coord!(xy))
+        } else if self.can_draw_at(mouse_xy) {
+            Some(self.mouse_to_image(mouse_xy))
+        } else {
+            None
+        }
+    }
+
+    /// Image pixel under the keyboard cursor or mouse
+    pub fn cursor_position(&self, mouse_xy: Coord) -> Option<(u8, u8)> {
+        self.cursor_end(mouse_xy).map(|xy| self.wrap_coord(xy))
+    }
+
+    /// Width and height (in image pixels) of the shape currently being drawn
+    pub fn shape_size(&self, mouse_xy: Coord) -> Option<(usize, usize)> {
+        if self.tool == Tool::Fill {
+            return None;
+        }
+        let selecting = self.tool == Tool::Select && self.first_click_at.is_none();
+        if selecting || self.tool == Tool::Wand {
+            let (top_left, bottom_right) = match &self.floating {
+                Some(floating) => floating.bounds(),
+                None => self.selection.as_ref()?.bounds()?,
+            };
+            return Some((
+                top_left.x.abs_diff(bottom_right.x) + 1,
+                top_left.y.abs_diff(bottom_right.y) + 1,
+            ));
+        }
+        let start = self.first_click_at?;
+        let end = self.cursor_end(mouse_xy)?;
+        Some((start.x.abs_diff(end.x) + 1, start.y.abs_diff(end.y) + 1))
+    }
+
+    /// When wrapping the whole canvas can be drawn on, otherwise only the image
+    fn can_draw_at(&self, mouse_xy: Coord) -> bool {
+        self.inner_bounds.contains(mouse_xy) || (self.wrap && self.bounds.contains(mouse_xy))
+    }
+
+    /// Moves `xy` back into the image, if it's outside then it's wrapped to the opposite side
+    fn wrap_coord(&self, xy: Coord) -> (u8, u8) {
+        (
+            xy.x.rem_euclid(self.image.width() as isize) as u8,
+            xy.y.rem_euclid(self.image.height() as isize) as u8,
+        )
     }
 
     pub fn set_tool(&mut self, tool: Tool) {
         self.tool = tool;
+        self.first_click_at = None;
     }
 
     pub fn get_palette(&self) -> &[Color] {
@@ -160,22 +796,26 @@ impl Canvas {
     pub fn set_shift_pressed(&mut self, shift_pressed: bool) {
         self.shift_pressed = shift_pressed;
     }
+
+    pub fn set_alt_pressed(&mut self, alt_pressed: bool) {
+        self.alt_pressed = alt_pressed;
+    }
 }
 
 impl Canvas {
-    fn draw_mouse_highlight(&self, graphics: &mut Graphics, mouse_xy: Coord) {
-        if self.inner_bounds.contains(mouse_xy) {
-            let xy = self.mouse_to_image(mouse_xy);
-            self.draw_cursor_on_image(graphics, xy);
+    fn draw_cursor_on_image<P: Into<Coord>>(&self, graphics: &mut Graphics, xy: P) {
+        let mut xy = xy.into();
+        if self.wrap {
+            xy = coord!(self.wrap_coord(xy));
         }
-    }
-
-    fn draw_cursor_on_image(&self, graphics: &mut Graphics, xy: (u8, u8)) {
-        let top_left =
-            (Coord::from(xy) * self.screen_px_per_image_px) + self.inner_bounds.top_left();
-        if !self.inner_bounds.contains(top_left) {
+        if xy.x < 0
+            || xy.y < 0
+            || xy.x >= self.image.width() as isize
+            || xy.y >= self.image.height() as isize
+        {
             return;
         }
+        let top_left = (xy * self.screen_px_per_image_px) + self.image_origin();
 
         if self.cursor_color.is_transparent() {
             let mut color = BLACK;
@@ -206,112 +846,227 @@ impl Canvas {
         }
     }
 
-    fn draw_img_px(
-        &self,
-        graphics: &mut Graphics,
-        image: &IndexedImage,
-        img_x: u8,
-        img_y: u8,
-        trans_color: Color,
-    ) {
-        let img_i = image.get_pixel_index(img_x, img_y).unwrap();
-        let color_idx = image.get_pixel(img_i).unwrap();
-        let color = image.get_color(color_idx).unwrap();
-        let px_size = self.screen_px_per_image_px as isize;
-        let scr_x = img_x as isize * px_size;
-        let scr_y = img_y as isize * px_size;
+    /// Image pixels with any floating pixels placed on top, combined with the other layers
+    fn displayed_pixels(&self) -> Vec<u8> {
+        let mut pixels = self.image.get_pixels().to_vec();
+        if let Some(floating) = &self.floating {
+            let blank = get_transparent_colors(&self.image)
+                .first()
+                .copied()
+                .unwrap_or_default();
+            for i in floating.cleared() {
+                if let Some(px) = pixels.get_mut(*i) {
+                    *px = blank;
+                }
+            }
+            for (i, idx) in floating.image_pixels(self.image.width(), self.image.height()) {
+                pixels[i] = idx;
+            }
+        }
+        let (below, above) = &self.layers_around;
+        if below.is_none() && above.is_none() && self.layer_visible {
+            return pixels;
+        }
+        let layer = IndexedImage::new(
+            self.image.width(),
+            self.image.height(),
+            self.image.get_palette().to_vec(),
+            pixels,
+        )
+        .expect("Creating layer image");
+        let mut output = match below {
+            Some(below) => below.get_pixels().to_vec(),
+            None => {
+                let trans = self.image.get_palette().iter().position(|c| c.a == 0);
+                vec![trans.unwrap_or_default() as u8; layer.get_pixels().len()]
+            }
+        };
+        if self.layer_visible {
+            overlay(&mut output, &layer);
+        }
+        if let Some(above) = above {
+            overlay(&mut output, above);
+        }
+        output
+    }
+
+    /// Draws the transparency background and onion skin under transparent pixels
+    fn draw_img_background_px(&self, graphics: &mut Graphics, pixels: &[u8], img_x: u8, img_y: u8) {
+        let img_i = self.image.get_pixel_index(img_x, img_y).unwrap();
+        let color_idx = pixels[img_i];
+        let color = self.image.get_color(color_idx).unwrap();
         if color.is_transparent() {
-            match self.screen_px_per_image_px {
-                1 => graphics.set_pixel(scr_x, scr_y, trans_color),
-                _ => graphics.draw_rect(
-                    Rect::new_with_size(
-                        (scr_x, scr_y),
-                        self.screen_px_per_image_px - 1,
-                        self.screen_px_per_image_px - 1,
-                    ),
-                    fill(trans_color),
-                ),
+            let trans_color = if (img_x as usize + img_y as usize).is_multiple_of(2) {
+                self.trans_background_colors.0
+            } else {
+                self.trans_background_colors.1
+            };
+            self.fill_img_px(graphics, img_x, img_y, trans_color);
+            for (frame, tint) in &self.onion_skin {
+                let color = frame.get_color(frame.get_pixel(img_i).unwrap()).unwrap();
+                if !color.is_transparent() {
+                    self.fill_img_px(graphics, img_x, img_y, onion_color(color, *tint));
+                }
+            }
+        }
+    }
+
+    fn draw_img_color_px(&self, graphics: &mut Graphics, pixels: &[u8], img_x: u8, img_y: u8) {
+        let img_i = self.image.get_pixel_index(img_x, img_y).unwrap();
+        let color_idx = pixels[img_i];
+        let color = self.image.get_color(color_idx).unwrap();
+        if !color.is_transparent() {
+            match (color_idx as usize)
+                .checked_sub(1)
+                .and_then(|i| self.tiles.get(i))
+            {
+                Some(tile) => self.draw_tile_px(graphics, tile, img_x, img_y),
+                None => self.fill_img_px(graphics, img_x, img_y, color),
             }
+        }
+    }
+
+    /// Draws `tile` scaled to fill the image pixel, transparent parts show the background
+    fn draw_tile_px(&self, graphics: &mut Graphics, tile: &IndexedImage, img_x: u8, img_y: u8) {
+        let px_size = self.screen_px_per_image_px;
+        let scr_x = img_x as isize * px_size as isize;
+        let scr_y = img_y as isize * px_size as isize;
+        let background = if (img_x as usize + img_y as usize).is_multiple_of(2) {
+            self.trans_background_colors.0
         } else {
-            match self.screen_px_per_image_px {
-                1 => graphics.set_pixel(scr_x, scr_y, color),
-                _ => graphics.draw_rect(
-                    Rect::new_with_size(
-                        (scr_x, scr_y),
-                        self.screen_px_per_image_px - 1,
-                        self.screen_px_per_image_px - 1,
-                    ),
-                    fill(color),
-                ),
+            self.trans_background_colors.1
+        };
+        for y in 0..px_size {
+            let tile_y = (y * tile.height() as usize / px_size) as u8;
+            for x in 0..px_size {
+                let tile_x = (x * tile.width() as usize / px_size) as u8;
+                let color = tile
+                    .get_pixel_index(tile_x, tile_y)
+                    .and_then(|i| tile.get_pixel(i))
+                    .and_then(|idx| tile.get_color(idx))
+                    .unwrap_or(TRANSPARENT);
+                let color = if color.is_transparent() {
+                    background
+                } else {
+                    color
+                };
+                graphics.set_pixel(scr_x + x as isize, scr_y + y as isize, color);
             }
         }
     }
 
-    fn temp_line(&self, graphics: &mut Graphics, start: (u8, u8), mouse_xy: Coord) {
-        let end = self.mouse_to_image(mouse_xy);
+    /// Draws the reference image over the visible part of the image
+    fn draw_reference(&self, graphics: &mut Graphics, reference: &Reference) {
+        let origin = self.image_origin();
+        let px_size = self.screen_px_per_image_px as f32;
+        for y in self.inner_bounds.top()..=self.inner_bounds.bottom() {
+            for x in self.inner_bounds.left()..=self.inner_bounds.right() {
+                let img_xy = (
+                    (x - origin.x) as f32 / px_size,
+                    (y - origin.y) as f32 / px_size,
+                );
+                if let Some(color) = reference.color_at(img_xy) {
+                    graphics.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
 
+    fn fill_img_px(&self, graphics: &mut Graphics, img_x: u8, img_y: u8, color: Color) {
+        let px_size = self.screen_px_per_image_px as isize;
+        let scr_x = img_x as isize * px_size;
+        let scr_y = img_y as isize * px_size;
+        match self.screen_px_per_image_px {
+            1 => graphics.set_pixel(scr_x, scr_y, color),
+            _ => graphics.draw_rect(
+                Rect::new_with_size(
+                    (scr_x, scr_y),
+                    self.screen_px_per_image_px - 1,
+                    self.screen_px_per_image_px - 1,
+                ),
+                fill(color),
+            ),
+        }
+    }
+
+    fn temp_line(&self, graphics: &mut Graphics, start: Coord, end: Coord) {
         let points = Line::new(start, end).outline_pixels();
         for point in points {
-            self.draw_cursor_on_image(graphics, (point.x as u8, point.y as u8));
+            self.draw_cursor_on_image(graphics, point);
         }
     }
 
-    fn temp_rect(&self, graphics: &mut Graphics, start: (u8, u8), mouse_xy: Coord) {
-        let end = self.mouse_to_image(mouse_xy);
-        let top_left = (start.0.min(end.0), start.1.min(end.1));
-        let bottom_right = (start.0.max(end.0), start.1.max(end.1));
+    fn temp_rect(&self, graphics: &mut Graphics, start: Coord, end: Coord) {
+        let top_left = coord!(start.x.min(end.x), start.y.min(end.y));
+        let bottom_right = coord!(start.x.max(end.x), start.y.max(end.y));
 
-        for x in top_left.0..bottom_right.0 {
-            self.draw_cursor_on_image(graphics, (x, top_left.1));
-            self.draw_cursor_on_image(graphics, (x, bottom_right.1));
+        for x in top_left.x..bottom_right.x {
+            self.draw_cursor_on_image(graphics, (x, top_left.y));
+            self.draw_cursor_on_image(graphics, (x, bottom_right.y));
         }
 
-        for y in top_left.1..=bottom_right.1 {
-            self.draw_cursor_on_image(graphics, (top_left.0, y));
-            self.draw_cursor_on_image(graphics, (bottom_right.0, y));
+        for y in top_left.y..=bottom_right.y {
+            self.draw_cursor_on_image(graphics, (top_left.x, y));
+            self.draw_cursor_on_image(graphics, (bottom_right.x, y));
         }
     }
 
-    fn temp_circle(
-        &self,
-        graphics: &mut Graphics,
-        start: (u8, u8),
-        mouse_xy: Coord,
-        shift_pressed: bool,
-    ) {
+    fn temp_circle(&self, graphics: &mut Graphics, start: Coord, end: Coord, shift_pressed: bool) {
         let circle = if shift_pressed {
-            Circle::new(start, coord!(start).distance(self.mouse_to_image(mouse_xy)))
+            Circle::new(start, start.distance(end))
         } else {
-            Rect::new(coord!(start), self.mouse_to_image(mouse_xy)).as_inner_circle()
+            Rect::new(start, end).as_inner_circle()
         };
         for px in circle.outline_pixels() {
-            self.draw_cursor_on_image(graphics, (px.x as u8, px.y as u8));
+            self.draw_cursor_on_image(graphics, px);
         }
     }
 
-    fn temp_ellipse(
-        &self,
-        graphics: &mut Graphics,
-        start: (u8, u8),
-        mouse_xy: Coord,
-        shift_pressed: bool,
-    ) {
-        let end = self.mouse_to_image(mouse_xy);
+    fn temp_ellipse(&self, graphics: &mut Graphics, start: Coord, end: Coord, shift_pressed: bool) {
         let ellipse = if shift_pressed {
             Ellipse::new(
                 start,
-                start.0.abs_diff(end.0) as usize / 2,
-                start.1.abs_diff(end.1) as usize / 2,
+                start.x.abs_diff(end.x) / 2,
+                start.y.abs_diff(end.y) / 2,
             )
         } else {
-            Rect::new(coord!(start), self.mouse_to_image(mouse_xy)).as_outer_ellipse()
+            Rect::new(start, end).as_outer_ellipse()
         };
         for px in ellipse.outline_pixels() {
-            self.draw_cursor_on_image(graphics, (px.x as u8, px.y as u8));
+            self.draw_cursor_on_image(graphics, px);
         }
     }
 }
 
+fn combine_selection(
+    current: Option<Selection>,
+    new: Selection,
+    mode: SelectMode,
+) -> Option<Selection> {
+    let selection = match (mode, current) {
+        (SelectMode::Add, Some(mut current)) => {
+            current.add(&new);
+            current
+        }
+        (SelectMode::Subtract, Some(mut current)) => {
+            current.subtract(&new);
+            current
+        }
+        (SelectMode::Subtract, None) => return None,
+        (_, _) => new,
+    };
+    (!selection.is_empty()).then_some(selection)
+}
+
+fn onion_color(color: Color, tint: Color) -> Color {
+    Color::new(
+        ((color.r as u16 + tint.r as u16) / 2) as u8,
+        ((color.g as u16 + tint.g as u16) / 2) as u8,
+        ((color.b as u16 + tint.b as u16) / 2) as u8,
+        tint.a,
+    )
+}
+
 impl PixelView for Canvas {
     fn set_position(&mut self, _top_left: Coord) {
         unimplemented!("Does not support moving")
@@ -322,44 +1077,58 @@ impl PixelView for Canvas {
     }
 
     fn render(&self, graphics: &mut Graphics, mouse: &MouseData) {
-        let mut trans_color = self.trans_background_colors.0;
-        let swap_color = |color: &mut Color| {
-            if color == &self.trans_background_colors.0 {
-                *color = self.trans_background_colors.1;
-            } else {
-                *color = self.trans_background_colors.0;
-            }
-        };
-
-        let orig_trans = graphics.set_translate(self.inner_bounds.top_left());
+        graphics.clip_mut().set_valid_rect(self.bounds.clone());
+        let orig_trans = graphics.set_translate(self.image_origin());
 
-        for img_y in 0..self.image.height() {
-            for img_x in 0..self.image.width() {
-                self.draw_img_px(graphics, &self.image, img_x, img_y, trans_color);
-                swap_color(&mut trans_color);
+        let px_size = self.screen_px_per_image_px as isize;
+        let start_x = (-self.view_offset.x / px_size).max(0) as u8;
+        let start_y = (-self.view_offset.y / px_size).max(0) as u8;
+        let end_x = ((self.bounds.width() as isize - self.view_offset.x) / px_size + 1)
+            .min(self.image.width() as isize) as u8;
+        let end_y = ((self.bounds.height() as isize - self.view_offset.y) / px_size + 1)
+            .min(self.image.height() as isize) as u8;
+        let pixels = self.displayed_pixels();
+        for img_y in start_y..end_y {
+            for img_x in start_x..end_x {
+                self.draw_img_background_px(graphics, &pixels, img_x, img_y);
             }
-            if self.image.width() % 2 == 0 {
-                swap_color(&mut trans_color);
+        }
+        graphics.set_translate(orig_trans);
+        if let Some(reference) = self.reference.as_ref().filter(|r| !r.settings.above) {
+            self.draw_reference(graphics, reference);
+        }
+        graphics.set_translate(self.image_origin());
+        for img_y in start_y..end_y {
+            for img_x in start_x..end_x {
+                self.draw_img_color_px(graphics, &pixels, img_x, img_y);
             }
         }
-
         graphics.set_translate(orig_trans);
-        if self.inner_bounds.contains(mouse.xy) && self.state == ViewState::Normal {
-            match (self.tool, self.first_click_at) {
-                (Tool::Line, Some(start)) => self.temp_line(graphics, start, mouse.xy),
-                (Tool::Rect, Some(start)) => self.temp_rect(graphics, start, mouse.xy),
-                (Tool::Circle, Some(start)) => {
-                    self.temp_circle(graphics, start, mouse.xy, self.shift_pressed)
-                }
-                (Tool::Ellipse, Some(start)) => {
-                    self.temp_ellipse(graphics, start, mouse.xy, self.shift_pressed)
-                }
-                _ => self.draw_mouse_highlight(graphics, mouse.xy),
+        if let Some(reference) = self.reference.as_ref().filter(|r| r.settings.above) {
+            self.draw_reference(graphics, reference);
+        }
+
+        if let Some(color) = self.pixel_grid {
+            if self.screen_px_per_image_px >= MIN_PIXEL_GRID_ZOOM {
+                self.render_grid(graphics, (1, 1), color);
             }
         }
+        if let Some((size, color)) = self.tile_grid {
+            self.render_grid(graphics, size, color);
+        }
+        self.render_selection(graphics);
+        self.render_cursor(graphics, mouse);
+        self.render_scrollbars(graphics);
+        graphics.clip_mut().set_all_valid();
     }
 
-    fn update(&mut self, _: &Timing) {}
+    fn update(&mut self, timing: &Timing) {
+        self.ants_timer += timing.fixed_time_step;
+        if self.ants_timer >= ANTS_STEP {
+            self.ants_timer = 0.0;
+            self.ants_phase = (self.ants_phase + 1) % 8;
+        }
+    }
 
     fn set_state(&mut self, state: ViewState) {
         self.state = state;
@@ -369,3 +1138,155 @@ impl PixelView for Canvas {
         self.state
     }
 }
+
+impl Canvas {
+    fn render_cursor(&self, graphics: &mut Graphics, mouse: &MouseData) {
+        if self.state != ViewState::Normal || self.view_drag.is_some() {
+            return;
+        }
+        let Some(end) = self.cursor_end(mouse.xy) else {
+            return;
+        };
+        match (self.tool, self.first_click_at) {
+            (Tool::Line, Some(start)) => self.temp_line(graphics, start, end),
+            (Tool::Rect | Tool::Select, Some(start)) => self.temp_rect(graphics, start, end),
+            (Tool::Circle, Some(start)) => {
+                self.temp_circle(graphics, start, end, self.shift_pressed)
+            }
+            (Tool::Ellipse, Some(start)) => {
+                self.temp_ellipse(graphics, start, end, self.shift_pressed)
+            }
+            _ => self.draw_cursor_on_image(graphics, end),
+        }
+    }
+
+    /// Draws a line every `spacing` image pixels, the image edges are skipped
+    fn render_grid(&self, graphics: &mut Graphics, spacing: (u8, u8), color: Color) {
+        let origin = self.image_origin();
+        let px_size = self.screen_px_per_image_px as isize;
+        for x in (spacing.0 as usize..self.image.width() as usize).step_by(spacing.0 as usize) {
+            let screen_x = origin.x + x as isize * px_size;
+            if screen_x >= self.inner_bounds.left() && screen_x <= self.inner_bounds.right() {
+                graphics.draw_line(
+                    (screen_x, self.inner_bounds.top()),
+                    (screen_x, self.inner_bounds.bottom()),
+                    color,
+                );
+            }
+        }
+        for y in (spacing.1 as usize..self.image.height() as usize).step_by(spacing.1 as usize) {
+            let screen_y = origin.y + y as isize * px_size;
+            if screen_y >= self.inner_bounds.top() && screen_y <= self.inner_bounds.bottom() {
+                graphics.draw_line(
+                    (self.inner_bounds.left(), screen_y),
+                    (self.inner_bounds.right(), screen_y),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Draws an animated dashed outline around the floating or selected pixels
+    fn render_selection(&self, graphics: &mut Graphics) {
+        if self.state != ViewState::Normal {
+            return;
+        }
+        if let Some(floating) = &self.floating {
+            self.render_marching_ants(graphics, floating.bounds(), |xy| floating.contains(xy));
+        } else if let Some(selection) = &self.selection {
+            if let Some(bounds) = selection.bounds() {
+                self.render_marching_ants(graphics, bounds, |xy| selection.contains(xy));
+            }
+        }
+    }
+
+    fn render_marching_ants<F: Fn(Coord) -> bool>(
+        &self,
+        graphics: &mut Graphics,
+        (top_left, bottom_right): (Coord, Coord),
+        contains: F,
+    ) {
+        let origin = self.image_origin();
+        let last = self.screen_px_per_image_px as isize - 1;
+        let ant = |graphics: &mut Graphics, x: isize, y: isize| {
+            let color = if (x + y + self.ants_phase as isize).rem_euclid(8) < 4 {
+                BLACK
+            } else {
+                WHITE
+            };
+            graphics.set_pixel(x, y, color);
+        };
+        for y in top_left.y..=bottom_right.y {
+            for x in top_left.x..=bottom_right.x {
+                if !contains(coord!(x, y)) {
+                    continue;
+                }
+                let screen = origin + coord!(x, y) * self.screen_px_per_image_px;
+                for i in 0..=last {
+                    if !contains(coord!(x, y - 1)) {
+                        ant(graphics, screen.x + i, screen.y);
+                    }
+                    if !contains(coord!(x, y + 1)) {
+                        ant(graphics, screen.x + i, screen.y + last);
+                    }
+                    if !contains(coord!(x - 1, y)) {
+                        ant(graphics, screen.x, screen.y + i);
+                    }
+                    if !contains(coord!(x + 1, y)) {
+                        ant(graphics, screen.x + last, screen.y + i);
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_scrollbars(&self, graphics: &mut Graphics) {
+        for (track, thumb) in [self.horz_scrollbar(), self.vert_scrollbar()]
+            .into_iter()
+            .flatten()
+        {
+            graphics.draw_rect(track, fill(DARK_GRAY));
+            graphics.draw_rect(thumb, fill(LIGHT_GRAY));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn canvas(tool: Tool, pixels: Vec<u8>) -> Canvas {
+        let mut canvas = Canvas::new(coord!(0, 0), (100, 100), (WHITE, BLACK));
+        canvas.set_image(IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], pixels).unwrap());
+        canvas.set_usage_state((tool, BLUE, 1));
+        canvas.move_keyboard_cursor(0, 0);
+        canvas
+    }
+
+    #[test]
+    fn keyboard_fill() {
+        let mut canvas = canvas(Tool::Fill, vec![1, 0, 0, 0]);
+        let mut history = EditHistory::new(vec![canvas.get_image().clone()]);
+        assert!(canvas.on_keyboard_paint(&mut history));
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn keyboard_shape() {
+        let mut canvas = canvas(Tool::Line, vec![0; 4]);
+        let mut history = EditHistory::new(vec![canvas.get_image().clone()]);
+        assert!(!canvas.on_keyboard_paint(&mut history));
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 0, 0]);
+        canvas.move_keyboard_cursor(-2, 0);
+        assert!(canvas.on_keyboard_paint(&mut history));
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn keyboard_pencil() {
+        let mut canvas = canvas(Tool::Pencil, vec![0; 4]);
+        let mut history = EditHistory::new(vec![canvas.get_image().clone()]);
+        assert!(canvas.on_keyboard_paint(&mut history));
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 1, 0]);
+    }
+}
@@ -1,9 +1,14 @@
+use crate::image::effects::{drop_shadow, outline_inside, outline_outside};
+use crate::image::layers::{flatten, Layer};
+use crate::image::trim::{content_bounds, get_transparent_colors};
+use crate::image::tween::{dissolve_pixels, shift_pixels, TweenKind};
 use crate::ui::image_fill::fill_pixels;
+use crate::ui::selection::Selection;
 use fnv::FnvHashSet;
 #[cfg(not(test))]
 use log::debug;
 use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
-use std::mem::swap;
+use std::mem::{swap, take};
 #[cfg(test)]
 use std::println as debug;
 use std::ptr::swap_nonoverlapping;
@@ -17,44 +22,152 @@ pub enum EditEvent {
     },
     /// Full edit, such as flip or rotate
     Full(Vec<u8>),
-    /// Add new animation frame
-    FrameAdd { idx: usize, content: Vec<u8> },
+    /// Flip, rotate or shift of the selected pixels, the selection moves with them so the
+    /// selection before and after is kept to be restored by undo and redo
+    SelectionTransform {
+        content: Vec<u8>,
+        before: Selection,
+        after: Option<Selection>,
+    },
+    /// Add new animation frame, content is the pixels of every layer
+    FrameAdd {
+        idx: usize,
+        content: Vec<u8>,
+        duration: u32,
+    },
     /// Delete animation frame
     FrameRemove(usize),
     /// Change active animation frame
     FrameSelect(usize),
+    /// Move animation frame (with all its layers) to a new position, it becomes the active frame
+    FrameMove { from: usize, to: usize },
+    /// Change how long each frame is shown for
+    FrameDurations(Vec<u32>),
     /// Palette change
     PaletteChange(Vec<Color>),
+    /// Palette change where every pixel of every frame is updated, `mapping` contains the new
+    /// index for each old palette index
+    PaletteRemap {
+        palette: Vec<Color>,
+        mapping: Vec<u8>,
+    },
+    /// Edits to several animation frames at once, such as an outline on every frame
+    Frames(Vec<(usize, EditEvent)>),
+    /// Add new blank layer to every frame
+    LayerAdd { idx: usize, name: String },
+    /// Delete layer from every frame
+    LayerRemove(usize),
+    /// Change layer order
+    LayerMove { from: usize, to: usize },
+    /// Rename or show/hide layer
+    LayerUpdate { idx: usize, layer: Layer },
+    /// Change active layer
+    LayerSelect(usize),
+}
+
+impl EditEvent {
+    /// The selection before and after this edit, if it moves the selection
+    ///
+    /// For edits to several frames this is the selection before the first and after the last
+    fn selection_change(&self) -> Option<(Selection, Option<Selection>)> {
+        match self {
+            EditEvent::SelectionTransform { before, after, .. } => {
+                Some((before.clone(), after.clone()))
+            }
+            EditEvent::Frames(events) => {
+                let changes: Vec<(Selection, Option<Selection>)> = events
+                    .iter()
+                    .filter_map(|(_, event)| event.selection_change())
+                    .collect();
+                let before = changes.first()?.0.clone();
+                let after = changes.last()?.1.clone();
+                Some((before, after))
+            }
+            _ => None,
+        }
+    }
 }
 
+/// Limits which pixels the drawing tools are allowed to change
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PaintFilter {
+    /// Only paint over pixels currently using this palette index
+    pub replace_only: Option<u8>,
+    /// Never paint over pixels whose palette color is transparent
+    pub lock_transparency: bool,
+    /// Only paint inside of the selection
+    pub selection: Option<Selection>,
+}
+
+/// Milliseconds each frame is shown for, unless set otherwise
+pub const DEFAULT_FRAME_DURATION: u32 = 100;
+
 #[derive(Debug)]
 pub struct EditHistory {
+    /// Image for each layer of each frame, ordered by frame then layer (bottom to top)
     base_images: Vec<IndexedImage>,
     edited_images: Vec<IndexedImage>,
+    base_layers: Vec<Layer>,
+    layers: Vec<Layer>,
+    /// Milliseconds each frame is shown for
+    base_durations: Vec<u32>,
+    durations: Vec<u32>,
     events: Vec<EditEvent>,
     /// current position in events, should be events.len() unless undo is used
     index: usize,
     active_frame: usize,
+    active_layer: usize,
+    paint_filter: PaintFilter,
+    /// If shapes that go past an edge continue on the opposite side
+    wrap: bool,
 }
 
 impl EditHistory {
     pub fn new(base_images: Vec<IndexedImage>) -> Self {
+        Self::with_layers(base_images, vec![Layer::default()])
+    }
+
+    /// `base_images` must contain an image for each layer of each frame, ordered by frame then layer
+    pub fn with_layers(base_images: Vec<IndexedImage>, base_layers: Vec<Layer>) -> Self {
+        debug_assert!(
+            !base_layers.is_empty() && base_images.len().is_multiple_of(base_layers.len())
+        );
         let edited_images = base_images.clone();
+        let layers = base_layers.clone();
+        let durations = vec![DEFAULT_FRAME_DURATION; base_images.len() / base_layers.len()];
         Self {
             base_images,
             edited_images,
+            base_layers,
+            layers,
+            base_durations: durations.clone(),
+            durations,
             events: vec![],
             index: 0,
             active_frame: 0,
+            active_layer: 0,
+            paint_filter: PaintFilter::default(),
+            wrap: false,
         }
     }
+
+    /// `durations` must contain the milliseconds for each frame
+    pub fn with_durations(mut self, durations: Vec<u32>) -> Self {
+        debug_assert_eq!(durations.len(), self.frame_count());
+        self.base_durations = durations.clone();
+        self.durations = durations;
+        self
+    }
 }
 
 impl EditHistory {
     pub fn undo(&mut self) -> Result<(), IndexedImageError> {
         if self.index >= 1 {
             self.index -= 1;
-            return self.rebuild_current_image();
+            self.rebuild_current_image()?;
+            if let Some((before, _)) = self.events[self.index].selection_change() {
+                self.paint_filter.selection = Some(before);
+            }
         }
         Ok(())
     }
@@ -68,25 +181,59 @@ impl EditHistory {
                 self.index,
                 self.events.len()
             );
-            return self.handle_edit_event(&event);
+            self.handle_edit_event(&event)?;
+            if let Some((_, after)) = event.selection_change() {
+                self.paint_filter.selection = after;
+            }
         }
         Ok(())
     }
 
+    /// The active layer of the active frame, this is the image that is edited
     pub fn get_current_image(&self) -> &IndexedImage {
-        &self.edited_images[self.active_frame]
+        &self.edited_images[self.active_idx()]
+    }
+
+    /// The active frame with its visible layers combined
+    pub fn get_current_frame(&self) -> IndexedImage {
+        self.get_image(self.active_frame)
     }
 
-    pub fn get_image(&self, idx: usize) -> &IndexedImage {
-        &self.edited_images[idx]
+    /// Frame `idx` with its visible layers combined
+    pub fn get_image(&self, idx: usize) -> IndexedImage {
+        flatten(self.frame_cels(idx), &self.layers)
     }
 
     pub fn is_empty(&self) -> bool {
         self.events.is_empty()
     }
 
+    /// Every frame with its visible layers combined
     pub fn get_images(&self) -> Vec<IndexedImage> {
-        self.edited_images.clone()
+        (0..self.frame_count())
+            .map(|frame| self.get_image(frame))
+            .collect()
+    }
+
+    /// Image for each layer of each frame, ordered by frame then layer
+    pub fn get_cels(&self) -> &[IndexedImage] {
+        &self.edited_images
+    }
+
+    /// Visible layers below and above the active layer of the active frame, combined
+    pub fn get_layers_around(&self) -> (Option<IndexedImage>, Option<IndexedImage>) {
+        let cels = self.frame_cels(self.active_frame);
+        let combine = |range: std::ops::Range<usize>| {
+            let layers = &self.layers[range.clone()];
+            layers
+                .iter()
+                .any(|layer| layer.visible)
+                .then(|| flatten(&cels[range], layers))
+        };
+        (
+            combine(0..self.active_layer),
+            combine(self.active_layer + 1..self.layers.len()),
+        )
     }
 
     pub fn active_frame(&self) -> usize {
@@ -94,7 +241,36 @@ impl EditHistory {
     }
 
     pub fn frame_count(&self) -> usize {
-        self.edited_images.len()
+        self.edited_images.len() / self.layers.len()
+    }
+
+    /// Milliseconds each frame is shown for
+    pub fn durations(&self) -> &[u32] {
+        &self.durations
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn active_layer(&self) -> usize {
+        self.active_layer
+    }
+
+    pub fn paint_filter(&self) -> &PaintFilter {
+        &self.paint_filter
+    }
+
+    pub fn set_paint_filter(&mut self, paint_filter: PaintFilter) {
+        self.paint_filter = paint_filter;
+    }
+
+    pub fn is_wrapping(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn set_wrapping(&mut self, wrap: bool) {
+        self.wrap = wrap;
     }
 
     pub fn is_first_event(&self) -> Option<Color> {
@@ -104,7 +280,7 @@ impl EditHistory {
                 color_idx,
             } = &self.events[0]
             {
-                let color = self.edited_images[self.active_frame]
+                let color = self.edited_images[self.active_idx()]
                     .get_color(*color_idx)
                     .unwrap();
                 if pixel_idxs.len() == 1 {
@@ -116,11 +292,53 @@ impl EditHistory {
     }
 }
 
+impl EditHistory {
+    fn cel_idx(&self, frame: usize, layer: usize) -> usize {
+        frame * self.layers.len() + layer
+    }
+
+    fn active_idx(&self) -> usize {
+        self.cel_idx(self.active_frame, self.active_layer)
+    }
+
+    /// First transparent color (or the first color if there isn't one), used for erased pixels
+    fn blank_idx(&self) -> u8 {
+        get_transparent_colors(&self.edited_images[self.active_idx()])
+            .first()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn frame_cels(&self, frame: usize) -> &[IndexedImage] {
+        let start = self.cel_idx(frame, 0);
+        &self.edited_images[start..start + self.layers.len()]
+    }
+
+    /// Applies `update` to the layer images of each frame, used when adding, removing or moving
+    /// layers
+    fn restack<F: Fn(&mut Vec<IndexedImage>)>(&mut self, update: F) {
+        let layer_count = self.layers.len();
+        let mut cels = vec![];
+        swap(&mut self.edited_images, &mut cels);
+        let mut frame = vec![];
+        for cel in cels {
+            frame.push(cel);
+            if frame.len() == layer_count {
+                update(&mut frame);
+                self.edited_images.append(&mut frame);
+            }
+        }
+    }
+}
+
 impl EditHistory {
     pub fn flip_h(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, (w, _)| coord!(w - 1 - xy.x, xy.y));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
         let half_width = (width as f32 / 2.).floor() as usize;
         for y in 0..height {
             for x in 0..half_width {
@@ -135,9 +353,12 @@ impl EditHistory {
     }
 
     pub fn flip_v(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, (_, h)| coord!(xy.x, h - 1 - xy.y));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
         let half_height = (height as f32 / 2.).floor() as usize;
         for y in 0..half_height {
             unsafe {
@@ -152,40 +373,118 @@ impl EditHistory {
     }
 
     pub fn rotate_cw_90(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(1);
+        }
         self.rot_cw()
     }
 
     pub fn rotate_cw_180(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(2);
+        }
         self.rot_cw()?;
         self.rot_cw()
     }
 
     pub fn rotate_cw_270(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(3);
+        }
         self.rot_cw()?;
         self.rot_cw()?;
         self.rot_cw()
     }
 
     pub fn rotate_ccw_90(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(3);
+        }
         self.rot_ccw()
     }
 
     pub fn rotate_ccw_180(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(2);
+        }
         self.rot_ccw()?;
         self.rot_ccw()
     }
 
     pub fn rotate_ccw_270(&mut self) -> Result<(), IndexedImageError> {
+        if self.paint_filter.selection.is_some() {
+            return self.rotate_selection(1);
+        }
         self.rot_ccw()?;
         self.rot_ccw()?;
         self.rot_ccw()
     }
 
+    /// Rotates the selected pixels clockwise by 90° per turn around the center of the selection
+    fn rotate_selection(&mut self, quarter_turns: usize) -> Result<(), IndexedImageError> {
+        self.transform_selection(|xy, (w, h)| match quarter_turns % 4 {
+            1 => coord!(h - 1 - xy.y + (w - h) / 2, xy.x + (h - w) / 2),
+            2 => coord!(w - 1 - xy.x, h - 1 - xy.y),
+            3 => coord!(xy.y + (w - h) / 2, w - 1 - xy.x + (h - w) / 2),
+            _ => xy,
+        })
+    }
+
+    /// Moves each selected pixel to the position returned by `transform` as a single edit,
+    /// the selection is moved with the pixels
+    ///
+    /// `transform` is given the position relative to the top left of the selection and the
+    /// selection size, pixels moved outside of the image are lost and vacated pixels are cleared
+    /// to the first transparent color (or the first color if there isn't one)
+    fn transform_selection<F: Fn(Coord, (isize, isize)) -> Coord>(
+        &mut self,
+        transform: F,
+    ) -> Result<(), IndexedImageError> {
+        let Some(selection) = self.paint_filter.selection.take() else {
+            return Ok(());
+        };
+        let Some((top_left, bottom_right)) = selection.bounds() else {
+            return Ok(());
+        };
+        let size = (
+            bottom_right.x - top_left.x + 1,
+            bottom_right.y - top_left.y + 1,
+        );
+        let image = &self.edited_images[self.active_idx()];
+        let width = image.width() as isize;
+        let height = image.height() as isize;
+        let source = image.get_pixels();
+        let mut output = source.to_vec();
+        let blank = self.blank_idx();
+        let idxs = selection.idxs();
+        for i in &idxs {
+            output[*i] = blank;
+        }
+        let mut moved = vec![];
+        for i in idxs {
+            let xy = coord!(i as isize % width, i as isize / width);
+            let new_xy = top_left + transform(xy - top_left, size);
+            if new_xy.x >= 0 && new_xy.y >= 0 && new_xy.x < width && new_xy.y < height {
+                let new_i = (new_xy.x + new_xy.y * width) as usize;
+                output[new_i] = source[i];
+                moved.push(new_i);
+            }
+        }
+        let after =
+            Some(Selection::from_idxs(width as u8, height as u8, &moved)).filter(|s| !s.is_empty());
+        self.paint_filter.selection = after.clone();
+        self.add_event(EditEvent::SelectionTransform {
+            content: output,
+            before: selection,
+            after,
+        })
+    }
+
     fn rot_cw(&mut self) -> Result<(), IndexedImageError> {
-        let source = self.edited_images[self.active_frame].get_pixels();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
+        let source = self.edited_images[self.active_idx()].get_pixels();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
 
         for y in 0..height {
             for x in 0..width {
@@ -201,10 +500,10 @@ impl EditHistory {
     }
 
     fn rot_ccw(&mut self) -> Result<(), IndexedImageError> {
-        let source = self.edited_images[self.active_frame].get_pixels();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
+        let source = self.edited_images[self.active_idx()].get_pixels();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
 
         for y in 0..height {
             for x in 0..width {
@@ -220,8 +519,11 @@ impl EditHistory {
     }
 
     pub fn move_up(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, _| xy + (0, -1));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
 
         let mut removed = vec![];
         for _ in 0..width {
@@ -233,8 +535,11 @@ impl EditHistory {
     }
 
     pub fn move_down(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, _| xy + (0, 1));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
 
         let mut removed = vec![];
         for _ in 0..width {
@@ -248,9 +553,12 @@ impl EditHistory {
     }
 
     pub fn move_left(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, _| xy + (-1, 0));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
 
         for i in 0..height {
             let take = i * width;
@@ -263,9 +571,12 @@ impl EditHistory {
     }
 
     pub fn move_right(&mut self) -> Result<(), IndexedImageError> {
-        let mut output: Vec<u8> = self.edited_images[self.active_frame].get_pixels().to_vec();
-        let width = self.edited_images[self.active_frame].width() as usize;
-        let height = self.edited_images[self.active_frame].height() as usize;
+        if self.paint_filter.selection.is_some() {
+            return self.transform_selection(|xy, _| xy + (1, 0));
+        }
+        let mut output: Vec<u8> = self.edited_images[self.active_idx()].get_pixels().to_vec();
+        let width = self.edited_images[self.active_idx()].width() as usize;
+        let height = self.edited_images[self.active_idx()].height() as usize;
 
         for i in 0..height {
             let insert = i * width;
@@ -277,151 +588,177 @@ impl EditHistory {
         self.add_event(EditEvent::Full(output))
     }
 
-    pub fn add_line(
+    pub fn add_line<P: Into<Coord>>(
         &mut self,
-        start: (u8, u8),
-        end: (u8, u8),
+        start: P,
+        end: P,
         color: u8,
     ) -> Result<(), IndexedImageError> {
         let points = Line::new(start, end).outline_pixels();
-        let mut pixels = vec![];
-
-        for point in points {
-            let i = self.edited_images[self.active_frame]
-                .get_pixel_index(point.x as u8, point.y as u8)?;
-            pixels.push(i);
-        }
-
-        let event = EditEvent::PixelsChange {
-            pixel_idxs: pixels,
-            color_idx: color,
-        };
-        self.add_event(event)
+        let pixels = self.points_to_idxs(points);
+        self.add_paint(pixels, color)
     }
 
-    pub fn add_circle(
+    pub fn add_circle<P: Into<Coord>>(
         &mut self,
-        start: (u8, u8),
-        end: (u8, u8),
+        start: P,
+        end: P,
         shift_held: bool,
         color: u8,
     ) -> Result<(), IndexedImageError> {
+        let start = start.into();
+        let end = end.into();
         let points = if shift_held {
-            Circle::new(start, coord!(start).distance(end)).outline_pixels()
+            Circle::new(start, start.distance(end)).outline_pixels()
         } else {
             Rect::new(start, end).as_inner_circle().outline_pixels()
         };
 
-        let mut pixels = vec![];
-        for point in points {
-            if let Ok(i) =
-                self.edited_images[self.active_frame].get_pixel_index(point.x as u8, point.y as u8)
-            {
-                pixels.push(i);
-            }
-        }
-        let event = EditEvent::PixelsChange {
-            pixel_idxs: pixels,
-            color_idx: color,
-        };
-        self.add_event(event)
+        let pixels = self.points_to_idxs(points);
+        self.add_paint(pixels, color)
     }
 
-    pub fn add_ellipse(
+    pub fn add_ellipse<P: Into<Coord>>(
         &mut self,
-        start: (u8, u8),
-        end: (u8, u8),
+        start: P,
+        end: P,
         shift_held: bool,
         color: u8,
     ) -> Result<(), IndexedImageError> {
+        let start = start.into();
+        let end = end.into();
         let points = if shift_held {
             Ellipse::new(
                 start,
-                start.0.abs_diff(end.0) as usize / 2,
-                start.1.abs_diff(end.1) as usize / 2,
+                start.x.abs_diff(end.x) / 2,
+                start.y.abs_diff(end.y) / 2,
             )
             .outline_pixels()
         } else {
             Rect::new(start, end).as_outer_ellipse().outline_pixels()
         };
 
-        let mut pixels = vec![];
-        for point in points {
-            if let Ok(i) =
-                self.edited_images[self.active_frame].get_pixel_index(point.x as u8, point.y as u8)
-            {
-                pixels.push(i);
-            }
-        }
-        let event = EditEvent::PixelsChange {
-            pixel_idxs: pixels,
-            color_idx: color,
-        };
-        self.add_event(event)
+        let pixels = self.points_to_idxs(points);
+        self.add_paint(pixels, color)
     }
 
-    pub fn add_rect(
+    pub fn add_rect<P: Into<Coord>>(
         &mut self,
-        start: (u8, u8),
-        end: (u8, u8),
+        start: P,
+        end: P,
         color: u8,
     ) -> Result<(), IndexedImageError> {
-        let mut pixels = FnvHashSet::default();
-        let top_left = ((start.0).min(end.0), (start.1).min(end.1));
-        let bottom_right = ((start.0).max(end.0), (start.1).max(end.1));
-
-        for x in top_left.0..bottom_right.0 {
-            let i = self.edited_images[self.active_frame].get_pixel_index(x, top_left.1)?;
-            pixels.insert(i);
-            let i = self.edited_images[self.active_frame].get_pixel_index(x, bottom_right.1)?;
-            pixels.insert(i);
+        let start = start.into();
+        let end = end.into();
+        let mut points = vec![];
+        let top_left = coord!(start.x.min(end.x), start.y.min(end.y));
+        let bottom_right = coord!(start.x.max(end.x), start.y.max(end.y));
+
+        for x in top_left.x..bottom_right.x {
+            points.push(coord!(x, top_left.y));
+            points.push(coord!(x, bottom_right.y));
         }
 
-        for y in top_left.1..=bottom_right.1 {
-            let i = self.edited_images[self.active_frame].get_pixel_index(top_left.0, y)?;
-            pixels.insert(i);
-            let i = self.edited_images[self.active_frame].get_pixel_index(bottom_right.0, y)?;
-            pixels.insert(i);
+        for y in top_left.y..=bottom_right.y {
+            points.push(coord!(top_left.x, y));
+            points.push(coord!(bottom_right.x, y));
         }
 
-        let event = EditEvent::PixelsChange {
-            pixel_idxs: pixels.into_iter().collect(),
-            color_idx: color,
-        };
-        self.add_event(event)
+        let pixels: FnvHashSet<usize> = self.points_to_idxs(points).into_iter().collect();
+        self.add_paint(pixels.into_iter().collect(), color)
     }
 
     pub fn add_fill(&mut self, xy: (u8, u8), color: u8) -> Result<(), IndexedImageError> {
-        let pixels = fill_pixels(&self.edited_images[self.active_frame], xy)?;
-        let event = EditEvent::PixelsChange {
-            pixel_idxs: pixels,
-            color_idx: color,
-        };
-        self.add_event(event)
+        let pixels = fill_pixels(&self.edited_images[self.active_idx()], xy, self.wrap)?;
+        self.add_paint(pixels, color)
     }
 
     pub fn add_pencil(&mut self, xy: (u8, u8), color: u8) -> Result<(), IndexedImageError> {
-        let i = self.edited_images[self.active_frame].get_pixel_index(xy.0, xy.1)?;
-        if self.edited_images[self.active_frame].get_pixel(i).unwrap() != color {
-            let event = EditEvent::PixelsChange {
-                pixel_idxs: vec![i],
-                color_idx: color,
-            };
-            self.add_event(event)
+        let i = self.edited_images[self.active_idx()].get_pixel_index(xy.0, xy.1)?;
+        if self.edited_images[self.active_idx()].get_pixel(i).unwrap() != color {
+            self.add_paint(vec![i], color)
         } else {
             Ok(())
         }
     }
 
+    /// Clears the selected pixels (or the whole image if nothing is selected) to the first
+    /// transparent color
     pub fn add_clear(&mut self) -> Result<(), IndexedImageError> {
         let size = self.edited_images[0].width() as usize * self.edited_images[0].height() as usize;
+        let pixel_idxs = match &self.paint_filter.selection {
+            Some(selection) => selection.idxs(),
+            None => (0..size).collect(),
+        };
+        if pixel_idxs.is_empty() {
+            return Ok(());
+        }
         let event = EditEvent::PixelsChange {
-            pixel_idxs: (0..size).collect(),
-            color_idx: 0,
+            pixel_idxs,
+            color_idx: self.blank_idx(),
         };
         self.add_event(event)
     }
 
+    /// Clears the pixels to the first transparent color, the paint filter is ignored
+    pub fn add_cut(&mut self, pixel_idxs: Vec<usize>) -> Result<(), IndexedImageError> {
+        if pixel_idxs.is_empty() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::PixelsChange {
+            pixel_idxs,
+            color_idx: self.blank_idx(),
+        })
+    }
+
+    /// Clears `cleared` to the first transparent color and then sets `pixels` (pixel index, palette index) as one edit
+    pub fn add_paste(
+        &mut self,
+        cleared: &[usize],
+        pixels: &[(usize, u8)],
+    ) -> Result<(), IndexedImageError> {
+        let blank = self.blank_idx();
+        let image = &self.edited_images[self.active_idx()];
+        let mut output = image.get_pixels().to_vec();
+        for i in cleared {
+            if let Some(px) = output.get_mut(*i) {
+                *px = blank;
+            }
+        }
+        for (i, color) in pixels {
+            if let Some(px) = output.get_mut(*i) {
+                *px = *color;
+            }
+        }
+        if output == image.get_pixels() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::Full(output))
+    }
+
+    pub fn add_outline(
+        &mut self,
+        inside: bool,
+        diagonals: bool,
+        color: u8,
+    ) -> Result<(), IndexedImageError> {
+        self.add_effect(color, |image| {
+            if inside {
+                outline_inside(image, diagonals)
+            } else {
+                outline_outside(image, diagonals)
+            }
+        })
+    }
+
+    pub fn add_drop_shadow(
+        &mut self,
+        offset: (isize, isize),
+        color: u8,
+    ) -> Result<(), IndexedImageError> {
+        self.add_effect(color, |image| drop_shadow(image, offset))
+    }
+
     pub fn add_palette_change(&mut self, colors: &[Color]) -> Result<(), IndexedImageError> {
         if self.edited_images[0].get_palette() == colors {
             return Ok(());
@@ -430,6 +767,50 @@ impl EditHistory {
         self.add_event(event)
     }
 
+    pub fn add_palette_remap(
+        &mut self,
+        palette: Vec<Color>,
+        mapping: Vec<u8>,
+    ) -> Result<(), IndexedImageError> {
+        self.add_event(EditEvent::PaletteRemap { palette, mapping })
+    }
+
+    /// Runs `edit` for each of `frames` (on the active layer) and records it as one edit
+    ///
+    /// Every frame starts with the same selection, after this the selection is the result of the
+    /// last frame
+    pub fn add_frames_edit<F: FnMut(&mut EditHistory) -> Result<(), IndexedImageError>>(
+        &mut self,
+        frames: &[usize],
+        mut edit: F,
+    ) -> Result<(), IndexedImageError> {
+        if frames == [self.active_frame] {
+            return edit(self);
+        }
+        self.events.truncate(self.index);
+        let previous = take(&mut self.events);
+        let active_frame = self.active_frame;
+        let selection = self.paint_filter.selection.clone();
+        let mut events = vec![];
+        let mut result = Ok(());
+        for frame in frames {
+            self.active_frame = *frame;
+            self.paint_filter.selection = selection.clone();
+            result = edit(self);
+            events.extend(take(&mut self.events).into_iter().map(|ev| (*frame, ev)));
+            if result.is_err() {
+                break;
+            }
+        }
+        self.active_frame = active_frame;
+        self.events = previous;
+        if !events.is_empty() {
+            self.events.push(EditEvent::Frames(events));
+        }
+        self.index = self.events.len();
+        result
+    }
+
     pub fn add_blank_frame(&mut self) -> Result<(), IndexedImageError> {
         self.add_event(EditEvent::FrameAdd {
             idx: self.active_frame,
@@ -437,18 +818,75 @@ impl EditHistory {
                 0;
                 self.edited_images[0].width() as usize
                     * self.edited_images[0].height() as usize
+                    * self.layers.len()
             ],
+            duration: self.durations[self.active_frame],
         })
     }
 
     pub fn add_duplicate_frame(&mut self) -> Result<(), IndexedImageError> {
-        let pixels = self.edited_images[self.active_frame].get_pixels().to_vec();
+        let pixels = self
+            .frame_cels(self.active_frame)
+            .iter()
+            .flat_map(|cel| cel.get_pixels().to_vec())
+            .collect();
         self.add_event(EditEvent::FrameAdd {
             idx: self.active_frame,
             content: pixels,
+            duration: self.durations[self.active_frame],
         })
     }
 
+    /// Inserts `count` frames generated from the `start` and `end` frames (on every layer) before
+    /// `end` as one edit, the new frames use the duration of `start`
+    pub fn add_tweens(
+        &mut self,
+        start: usize,
+        end: usize,
+        count: usize,
+        kind: TweenKind,
+    ) -> Result<(), IndexedImageError> {
+        if count == 0 || start >= end || end >= self.frame_count() {
+            return Ok(());
+        }
+        let start_cels = self.frame_cels(start);
+        let end_cels = self.frame_cels(end);
+        let offset = match (content_bounds(start_cels), content_bounds(end_cels)) {
+            (Some(from), Some(to)) => (
+                to.0 as isize - from.0 as isize,
+                to.1 as isize - from.1 as isize,
+            ),
+            _ => (0, 0),
+        };
+        let events = (1..=count)
+            .map(|step| {
+                let progress = step as f64 / (count + 1) as f64;
+                let content = start_cels
+                    .iter()
+                    .zip(end_cels)
+                    .flat_map(|(start, end)| match kind {
+                        TweenKind::Move => shift_pixels(
+                            start,
+                            (offset.0 as f64 * progress).round() as isize,
+                            (offset.1 as f64 * progress).round() as isize,
+                        ),
+                        TweenKind::Dissolve => dissolve_pixels(start, end, progress),
+                    })
+                    .collect();
+                let idx = end + step - 2;
+                (
+                    idx,
+                    EditEvent::FrameAdd {
+                        idx,
+                        content,
+                        duration: self.durations[start],
+                    },
+                )
+            })
+            .collect();
+        self.add_event(EditEvent::Frames(events))
+    }
+
     pub fn remove_frame(&mut self) -> Result<(), IndexedImageError> {
         self.add_event(EditEvent::FrameRemove(self.active_frame))
     }
@@ -459,9 +897,191 @@ impl EditHistory {
         }
         self.add_event(EditEvent::FrameSelect(idx))
     }
+
+    /// Moves the frame at `from` to `to`, shifting the frames in between
+    pub fn move_frame(&mut self, from: usize, to: usize) -> Result<(), IndexedImageError> {
+        if from == to || from >= self.frame_count() || to >= self.frame_count() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::FrameMove { from, to })
+    }
+
+    pub fn move_frame_left(&mut self) -> Result<(), IndexedImageError> {
+        match self.active_frame.checked_sub(1) {
+            Some(idx) => self.move_frame(self.active_frame, idx),
+            None => Ok(()),
+        }
+    }
+
+    pub fn move_frame_right(&mut self) -> Result<(), IndexedImageError> {
+        self.move_frame(self.active_frame, self.active_frame + 1)
+    }
+
+    pub fn set_frame_duration(&mut self, idx: usize, millis: u32) -> Result<(), IndexedImageError> {
+        let mut durations = self.durations.clone();
+        durations[idx] = millis;
+        self.set_durations(durations)
+    }
+
+    pub fn set_all_durations(&mut self, millis: u32) -> Result<(), IndexedImageError> {
+        self.set_durations(vec![millis; self.frame_count()])
+    }
+
+    fn set_durations(&mut self, durations: Vec<u32>) -> Result<(), IndexedImageError> {
+        if self.durations == durations {
+            return Ok(());
+        }
+        self.add_event(EditEvent::FrameDurations(durations))
+    }
+
+    /// Adds a blank layer above the active layer
+    pub fn add_layer(&mut self) -> Result<(), IndexedImageError> {
+        let mut number = self.layers.len() + 1;
+        while self
+            .layers
+            .iter()
+            .any(|layer| layer.name == format!("Layer {number}"))
+        {
+            number += 1;
+        }
+        self.add_event(EditEvent::LayerAdd {
+            idx: self.active_layer + 1,
+            name: format!("Layer {number}"),
+        })
+    }
+
+    /// Deletes the active layer, the last layer can't be removed
+    pub fn remove_layer(&mut self) -> Result<(), IndexedImageError> {
+        if self.layers.len() < 2 {
+            return Ok(());
+        }
+        self.add_event(EditEvent::LayerRemove(self.active_layer))
+    }
+
+    /// Moves the active layer up (towards the top) or down
+    pub fn move_layer(&mut self, up: bool) -> Result<(), IndexedImageError> {
+        let to = if up {
+            self.active_layer + 1
+        } else {
+            match self.active_layer.checked_sub(1) {
+                Some(to) => to,
+                None => return Ok(()),
+            }
+        };
+        if to >= self.layers.len() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::LayerMove {
+            from: self.active_layer,
+            to,
+        })
+    }
+
+    pub fn rename_layer(&mut self, name: &str) -> Result<(), IndexedImageError> {
+        let mut layer = self.layers[self.active_layer].clone();
+        if layer.name == name {
+            return Ok(());
+        }
+        layer.name = name.to_string();
+        self.add_event(EditEvent::LayerUpdate {
+            idx: self.active_layer,
+            layer,
+        })
+    }
+
+    pub fn set_layer_visible(&mut self, visible: bool) -> Result<(), IndexedImageError> {
+        let mut layer = self.layers[self.active_layer].clone();
+        if layer.visible == visible {
+            return Ok(());
+        }
+        layer.visible = visible;
+        self.add_event(EditEvent::LayerUpdate {
+            idx: self.active_layer,
+            layer,
+        })
+    }
+
+    pub fn add_layer_select(&mut self, idx: usize) -> Result<(), IndexedImageError> {
+        if self.active_layer == idx || idx >= self.layers.len() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::LayerSelect(idx))
+    }
 }
 
 impl EditHistory {
+    /// Converts shape points to pixel indexes for the active frame
+    ///
+    /// Points outside the image are wrapped to the opposite side if wrapping, otherwise dropped
+    fn points_to_idxs(&self, points: Vec<Coord>) -> Vec<usize> {
+        let width = self.edited_images[self.active_idx()].width() as isize;
+        let height = self.edited_images[self.active_idx()].height() as isize;
+        points
+            .into_iter()
+            .map(|point| {
+                if self.wrap {
+                    coord!(point.x.rem_euclid(width), point.y.rem_euclid(height))
+                } else {
+                    point
+                }
+            })
+            .filter(|point| point.x >= 0 && point.y >= 0 && point.x < width && point.y < height)
+            .map(|point| (point.x + point.y * width) as usize)
+            .collect()
+    }
+
+    /// Adds a tool edit, skipping any pixels excluded by the paint filter
+    fn add_paint(&mut self, pixels: Vec<usize>, color: u8) -> Result<(), IndexedImageError> {
+        let image = &self.edited_images[self.active_idx()];
+        let trans_idxs = if self.paint_filter.lock_transparency {
+            get_transparent_colors(image)
+        } else {
+            vec![]
+        };
+        let mut pixel_idxs = vec![];
+        for i in pixels {
+            let current = image.get_pixel(i)?;
+            if trans_idxs.contains(&current) {
+                continue;
+            }
+            if let Some(idx) = self.paint_filter.replace_only {
+                if current != idx {
+                    continue;
+                }
+            }
+            if let Some(selection) = &self.paint_filter.selection {
+                if !selection.contains_idx(i) {
+                    continue;
+                }
+            }
+            pixel_idxs.push(i);
+        }
+        if pixel_idxs.is_empty() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::PixelsChange {
+            pixel_idxs,
+            color_idx: color,
+        })
+    }
+
+    /// Adds an edit that colors the pixels returned by `effect` for the active frame
+    fn add_effect<F: Fn(&IndexedImage) -> Vec<usize>>(
+        &mut self,
+        color: u8,
+        effect: F,
+    ) -> Result<(), IndexedImageError> {
+        let pixel_idxs =
+            effect(&self.edited_images[self.cel_idx(self.active_frame, self.active_layer)]);
+        if pixel_idxs.is_empty() {
+            return Ok(());
+        }
+        self.add_event(EditEvent::PixelsChange {
+            pixel_idxs,
+            color_idx: color,
+        })
+    }
+
     fn add_event(&mut self, event: EditEvent) -> Result<(), IndexedImageError> {
         debug!("Adding {event:?}");
         if (self.index as isize) < self.events.len() as isize {
@@ -529,14 +1149,19 @@ impl EditHistory {
                 pixel_idxs,
                 color_idx,
             } => {
+                let image = self.active_idx();
                 for idx in pixel_idxs {
-                    self.edited_images[self.active_frame].set_pixel(*idx, *color_idx)?;
+                    self.edited_images[image].set_pixel(*idx, *color_idx)?;
                 }
             }
-            EditEvent::Full(pixels) => {
-                let img = self.edited_images.remove(self.active_frame);
+            EditEvent::Full(pixels)
+            | EditEvent::SelectionTransform {
+                content: pixels, ..
+            } => {
+                let idx = self.active_idx();
+                let img = self.edited_images.remove(idx);
                 self.edited_images.insert(
-                    self.active_frame,
+                    idx,
                     IndexedImage::new(
                         img.width(),
                         img.height(),
@@ -551,25 +1176,110 @@ impl EditHistory {
                     image.set_palette_replace_id(colors, 0)?;
                 }
             }
-            EditEvent::FrameAdd { idx, content } => {
+            EditEvent::PaletteRemap { palette, mapping } => {
+                for image in &mut self.edited_images {
+                    let pixels = image
+                        .get_pixels()
+                        .iter()
+                        .map(|px| mapping[*px as usize])
+                        .collect();
+                    *image =
+                        IndexedImage::new(image.width(), image.height(), palette.clone(), pixels)?;
+                }
+            }
+            EditEvent::FrameAdd {
+                idx,
+                content,
+                duration,
+            } => {
                 self.active_frame = idx + 1;
-                let image = IndexedImage::new(
-                    self.edited_images[0].width(),
-                    self.edited_images[0].height(),
-                    self.edited_images[0].get_palette().to_vec(),
-                    content.clone(),
-                )?;
-                self.edited_images.insert(self.active_frame, image);
+                self.durations.insert(self.active_frame, *duration);
+                let image = &self.edited_images[0];
+                let size = image.width() as usize * image.height() as usize;
+                let cels = content
+                    .chunks(size)
+                    .map(|pixels| {
+                        IndexedImage::new(
+                            image.width(),
+                            image.height(),
+                            image.get_palette().to_vec(),
+                            pixels.to_vec(),
+                        )
+                    })
+                    .collect::<Result<Vec<IndexedImage>, IndexedImageError>>()?;
+                let start = self.cel_idx(self.active_frame, 0);
+                self.edited_images.splice(start..start, cels);
             }
             EditEvent::FrameRemove(idx) => {
-                self.edited_images.remove(*idx);
-                if self.active_frame >= self.edited_images.len() {
-                    self.active_frame = self.edited_images.len() - 1;
+                let start = self.cel_idx(*idx, 0);
+                self.edited_images.drain(start..start + self.layers.len());
+                self.durations.remove(*idx);
+                if self.active_frame >= self.frame_count() {
+                    self.active_frame = self.frame_count() - 1;
                 }
             }
             EditEvent::FrameSelect(idx) => {
                 self.active_frame = *idx;
             }
+            EditEvent::FrameMove { from, to } => {
+                let start = self.cel_idx(*from, 0);
+                let cels: Vec<IndexedImage> = self
+                    .edited_images
+                    .drain(start..start + self.layers.len())
+                    .collect();
+                let start = self.cel_idx(*to, 0);
+                self.edited_images.splice(start..start, cels);
+                let duration = self.durations.remove(*from);
+                self.durations.insert(*to, duration);
+                self.active_frame = *to;
+            }
+            EditEvent::FrameDurations(durations) => {
+                self.durations = durations.clone();
+            }
+            EditEvent::LayerAdd { idx, name } => {
+                let image = &self.edited_images[0];
+                let blank = IndexedImage::new(
+                    image.width(),
+                    image.height(),
+                    image.get_palette().to_vec(),
+                    vec![0; image.width() as usize * image.height() as usize],
+                )?;
+                self.restack(|cels| cels.insert(*idx, blank.clone()));
+                self.layers.insert(*idx, Layer::new(name));
+                self.active_layer = *idx;
+            }
+            EditEvent::LayerRemove(idx) => {
+                self.restack(|cels| {
+                    cels.remove(*idx);
+                });
+                self.layers.remove(*idx);
+                if self.active_layer >= self.layers.len() {
+                    self.active_layer = self.layers.len() - 1;
+                }
+            }
+            EditEvent::LayerMove { from, to } => {
+                self.restack(|cels| {
+                    let cel = cels.remove(*from);
+                    cels.insert(*to, cel);
+                });
+                let layer = self.layers.remove(*from);
+                self.layers.insert(*to, layer);
+                self.active_layer = *to;
+            }
+            EditEvent::LayerUpdate { idx, layer } => {
+                self.layers[*idx] = layer.clone();
+            }
+            EditEvent::LayerSelect(idx) => {
+                self.active_layer = *idx;
+            }
+            EditEvent::Frames(events) => {
+                let active_frame = self.active_frame;
+                for (frame, event) in events {
+                    self.active_frame = *frame;
+                    self.handle_edit_event(event)?;
+                }
+                self.active_frame = active_frame;
+            }
         }
         Ok(())
     }
@@ -577,7 +1287,10 @@ impl EditHistory {
     fn rebuild_current_image(&mut self) -> Result<(), IndexedImageError> {
         debug!("Rebuilding image");
         self.edited_images = self.base_images.clone();
+        self.layers = self.base_layers.clone();
+        self.durations = self.base_durations.clone();
         self.active_frame = 0;
+        self.active_layer = 0;
         debug!(
             "Replaying {} events, history has {} in total",
             self.index,
@@ -596,6 +1309,7 @@ impl EditHistory {
 mod test {
     use super::*;
     use crate::ui::edit_history::EditEvent::*;
+    use crate::ui::selection::{Clip, Floating, Selection};
 
     fn q_pc(idx: usize, color: u8) -> EditEvent {
         PixelsChange {
@@ -615,6 +1329,7 @@ mod test {
         FrameAdd {
             idx,
             content: image.get_pixels().to_vec(),
+            duration: DEFAULT_FRAME_DURATION,
         }
     }
 
@@ -827,4 +1542,436 @@ mod test {
         assert_eq!(history.edited_images[0].get_color(1).unwrap(), RED);
         assert_eq!(history.edited_images[1].get_color(1).unwrap(), RED);
     }
+
+    #[test]
+    fn lock_transparency() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image = IndexedImage::new(3, 1, palette, vec![0, 1, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.set_paint_filter(PaintFilter {
+            replace_only: None,
+            lock_transparency: true,
+            selection: None,
+        });
+        history.add_line((0, 0), (2, 0), 2).unwrap();
+        assert_eq!(history.events, vec![q_pc(1, 2)]);
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 2, 0]);
+        history.add_pencil((0, 0), 2).unwrap();
+        assert_eq!(history.events, vec![q_pc(1, 2)]);
+    }
+
+    #[test]
+    fn replace_only() {
+        let palette = vec![TRANSPARENT, BLUE, RED, GREEN];
+        let image = IndexedImage::new(2, 2, palette, vec![0, 1, 2, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.set_paint_filter(PaintFilter {
+            replace_only: Some(1),
+            lock_transparency: false,
+            selection: None,
+        });
+        history.add_rect((0, 0), (1, 1), 3).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 3, 2, 3]);
+        assert_eq!(history.index, 1);
+    }
+
+    #[test]
+    fn outline_all_frames() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image1 = IndexedImage::new(3, 1, palette.clone(), vec![0, 1, 0]).unwrap();
+        let image2 = IndexedImage::new(3, 1, palette, vec![1, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image1, image2]);
+        history
+            .add_frames_edit(&[0, 1], |history| history.add_outline(false, false, 2))
+            .unwrap();
+        assert_eq!(
+            history.events,
+            vec![Frames(vec![(0, q_mpc(&[0, 2], 2)), (1, q_pc(1, 2))])]
+        );
+        assert_eq!(history.get_image(0).get_pixels(), &[2, 1, 2]);
+        assert_eq!(history.get_image(1).get_pixels(), &[1, 2, 0]);
+        assert_eq!(history.active_frame, 0);
+        history.undo().unwrap();
+        assert_eq!(history.get_image(0).get_pixels(), &[0, 1, 0]);
+        assert_eq!(history.get_image(1).get_pixels(), &[1, 0, 0]);
+    }
+
+    #[test]
+    fn drop_shadow_single_frame() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image = IndexedImage::new(3, 2, palette, vec![1, 1, 0, 0, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_drop_shadow((1, 1), 2).unwrap();
+        assert_eq!(history.events, vec![q_mpc(&[4, 5], 2)]);
+        assert_eq!(
+            history.get_current_image().get_pixels(),
+            &[1, 1, 0, 0, 2, 2]
+        );
+    }
+
+    #[test]
+    fn fill_max_size_image() {
+        let image =
+            IndexedImage::new(255, 255, vec![TRANSPARENT, BLUE], vec![0; 255 * 255]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_fill((254, 254), 1).unwrap();
+        assert!(history
+            .get_current_image()
+            .get_pixels()
+            .iter()
+            .all(|&c| c == 1));
+    }
+
+    #[test]
+    fn circle_clipped_to_image() {
+        let image = IndexedImage::new(4, 4, vec![TRANSPARENT, BLUE], vec![0; 16]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_circle((0, 0), (3, 0), true, 1).unwrap();
+        let pixels = history.get_current_image().get_pixels();
+        assert_eq!(pixels[0], 0);
+        assert_eq!(pixels[3], 1);
+        assert_eq!(pixels[12], 1);
+    }
+
+    #[test]
+    fn wrapped_line() {
+        let image = IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], vec![0; 4]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_line((2, 0), (4, 0), 1).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 1, 1]);
+        history.set_wrapping(true);
+        history.add_line((-1, 0), (0, 0), 1).unwrap();
+        history.add_line((3, 0), (5, 0), 1).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn wrapped_fill() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image = IndexedImage::new(4, 1, palette, vec![0, 1, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_fill((0, 0), 2).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[2, 1, 0, 0]);
+        history.undo().unwrap();
+        history.set_wrapping(true);
+        history.add_fill((0, 0), 2).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[2, 1, 2, 2]);
+    }
+
+    #[test]
+    fn move_selection() {
+        let image = IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], vec![1, 1, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        let selection = Selection::from_rect(4, 1, (0, 0), (1, 0));
+        let mut floating = Floating::lift(history.get_current_image(), &selection).unwrap();
+        floating.pos = coord!(1, 0);
+        history
+            .add_paste(floating.cleared(), &floating.image_pixels(4, 1))
+            .unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 1, 1, 0]);
+        history.undo().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn clear_to_transparent() {
+        let palette = vec![BLUE, TRANSPARENT, RED];
+        let image = IndexedImage::new(4, 1, palette, vec![2, 2, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        select(&mut history, Selection::from_rect(4, 1, (1, 0), (2, 0)));
+        history.add_clear().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[2, 1, 1, 0]);
+        history.set_paint_filter(PaintFilter::default());
+        history.add_clear().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn cut_to_transparent() {
+        let palette = vec![BLUE, TRANSPARENT, RED];
+        let image = IndexedImage::new(4, 1, palette, vec![2, 2, 0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_cut(vec![0, 3]).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 2, 0, 1]);
+        history.add_paste(&[1], &[(2, 2)]).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn cut_ignores_paint_filter() {
+        let image = IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], vec![1, 1, 1, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.set_paint_filter(PaintFilter {
+            replace_only: Some(0),
+            lock_transparency: false,
+            selection: None,
+        });
+        history.add_cut(vec![1, 2]).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn paste_remaps_palette() {
+        let source = IndexedImage::new(2, 1, vec![TRANSPARENT, RED], vec![0, 1]).unwrap();
+        let selection = Selection::from_rect(2, 1, (0, 0), (1, 0));
+        let clip = Clip::from_selection(&source, &selection).unwrap();
+        let palette = vec![BLUE, Color::new(250, 0, 0, 255), TRANSPARENT];
+        let image = IndexedImage::new(2, 1, palette.clone(), vec![0, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        let floating = Floating::paste(coord!(0, 0), &clip, &palette);
+        history
+            .add_paste(floating.cleared(), &floating.image_pixels(2, 1))
+            .unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[2, 1]);
+    }
+
+    #[test]
+    fn paint_inside_selection() {
+        let image = IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], vec![0; 4]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        let mut selection = Selection::from_rect(4, 1, (0, 0), (2, 0));
+        selection.subtract(&Selection::from_idxs(4, 1, &[1]));
+        history.set_paint_filter(PaintFilter {
+            selection: Some(selection),
+            ..PaintFilter::default()
+        });
+        history.add_fill((3, 0), 1).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 0, 1, 0]);
+        history.add_clear().unwrap();
+        history.add_pencil((3, 0), 1).unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 0, 0]);
+    }
+
+    fn select(history: &mut EditHistory, selection: Selection) {
+        history.set_paint_filter(PaintFilter {
+            selection: Some(selection),
+            ..PaintFilter::default()
+        });
+    }
+
+    #[test]
+    fn flip_selection() {
+        let image =
+            IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE, RED], vec![1, 2, 0, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        select(&mut history, Selection::from_rect(4, 1, (0, 0), (2, 0)));
+        history.flip_h().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 2, 1, 1]);
+    }
+
+    #[test]
+    fn rotate_selection() {
+        #[rustfmt::skip]
+        let pixels = vec![
+            0, 0, 0,
+            1, 2, 1,
+            0, 0, 0,
+        ];
+        let image = IndexedImage::new(3, 3, vec![TRANSPARENT, BLUE, RED], pixels).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        select(&mut history, Selection::from_rect(3, 3, (0, 1), (1, 1)));
+        history.rotate_cw_90().unwrap();
+        #[rustfmt::skip]
+        assert_eq!(history.get_current_image().get_pixels(), &[
+            0, 0, 0,
+            1, 0, 1,
+            2, 0, 0,
+        ]);
+        history.rotate_cw_270().unwrap();
+        #[rustfmt::skip]
+        assert_eq!(history.get_current_image().get_pixels(), &[
+            0, 0, 0,
+            1, 2, 1,
+            0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn nudge_selection() {
+        let image = IndexedImage::new(4, 1, vec![TRANSPARENT, BLUE], vec![1, 1, 0, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        select(&mut history, Selection::from_rect(4, 1, (0, 0), (1, 0)));
+        history.move_right().unwrap();
+        history.move_right().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 1, 1]);
+        history.undo().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn undo_restores_selection() {
+        let image = IndexedImage::new(4, 1, vec![BLUE, TRANSPARENT], vec![0, 0, 1, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        let selection = Selection::from_rect(4, 1, (0, 0), (1, 0));
+        let moved = Selection::from_rect(4, 1, (1, 0), (2, 0));
+        select(&mut history, selection.clone());
+        history.move_right().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 0, 0, 1]);
+        assert_eq!(history.paint_filter().selection, Some(moved.clone()));
+        history.undo().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 1, 1]);
+        assert_eq!(history.paint_filter().selection, Some(selection));
+        history.redo().unwrap();
+        assert_eq!(history.get_current_image().get_pixels(), &[1, 0, 0, 1]);
+        assert_eq!(history.paint_filter().selection, Some(moved));
+    }
+
+    #[test]
+    fn layers_flatten() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image = IndexedImage::new(3, 1, palette, vec![1, 1, 0]).unwrap();
+        let mut history = EditHistory::new(vec![image]);
+        history.add_layer().unwrap();
+        assert_eq!(history.active_layer(), 1);
+        assert_eq!(history.get_current_image().get_pixels(), &[0, 0, 0]);
+        history.add_line((1, 0), (2, 0), 2).unwrap();
+        assert_eq!(history.get_current_frame().get_pixels(), &[1, 2, 2]);
+        history.set_layer_visible(false).unwrap();
+        assert_eq!(history.get_current_frame().get_pixels(), &[1, 1, 0]);
+        history.undo().unwrap();
+        history.move_layer(false).unwrap();
+        assert_eq!(history.active_layer(), 0);
+        assert_eq!(history.get_current_frame().get_pixels(), &[1, 1, 2]);
+        history.undo().unwrap();
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert_eq!(history.layers().len(), 1);
+        assert_eq!(history.get_current_frame().get_pixels(), &[1, 1, 0]);
+    }
+
+    #[test]
+    fn layers_with_frames() {
+        let palette = vec![TRANSPARENT, BLUE, RED];
+        let image1 = IndexedImage::new(2, 1, palette.clone(), vec![1, 0]).unwrap();
+        let image2 = IndexedImage::new(2, 1, palette, vec![0, 1]).unwrap();
+        let mut history = EditHistory::new(vec![image1, image2]);
+        history.add_layer().unwrap();
+        history.add_pencil((1, 0), 2).unwrap();
+        history.add_duplicate_frame().unwrap();
+        assert_eq!(history.frame_count(), 3);
+        assert_eq!(history.get_cels().len(), 6);
+        assert_eq!(history.get_image(0).get_pixels(), &[1, 2]);
+        assert_eq!(history.get_image(1).get_pixels(), &[1, 2]);
+        assert_eq!(history.get_image(2).get_pixels(), &[0, 1]);
+        history.remove_layer().unwrap();
+        assert_eq!(history.get_cels().len(), 3);
+        assert_eq!(history.get_image(1).get_pixels(), &[1, 0]);
+        history.remove_layer().unwrap();
+        assert_eq!(history.layers().len(), 1);
+    }
+
+    #[test]
+    fn frame_durations() {
+        let image = IndexedImage::new(2, 1, vec![TRANSPARENT, BLUE], vec![1, 0]).unwrap();
+        let mut history =
+            EditHistory::new(vec![image.clone(), image]).with_durations(vec![50, 200]);
+        history.add_frame_select(1).unwrap();
+        history.add_duplicate_frame().unwrap();
+        assert_eq!(history.durations(), &[50, 200, 200]);
+        history.set_frame_duration(0, 80).unwrap();
+        assert_eq!(history.durations(), &[80, 200, 200]);
+        history.remove_frame().unwrap();
+        assert_eq!(history.durations(), &[80, 200]);
+        history.set_all_durations(30).unwrap();
+        assert_eq!(history.durations(), &[30, 30]);
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert_eq!(history.durations(), &[80, 200, 200]);
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert_eq!(history.durations(), &[50, 200]);
+    }
+
+    #[test]
+    fn move_frames() {
+        let frame = |color: u8| {
+            IndexedImage::new(1, 1, vec![TRANSPARENT, BLUE, WHITE], vec![color]).unwrap()
+        };
+        let mut history = EditHistory::with_layers(
+            vec![frame(0), frame(1), frame(1), frame(2), frame(2), frame(0)],
+            vec![Layer::new("Bottom"), Layer::new("Top")],
+        )
+        .with_durations(vec![10, 20, 30]);
+        history.move_frame(0, 2).unwrap();
+        assert_eq!(history.active_frame(), 2);
+        assert_eq!(history.durations(), &[20, 30, 10]);
+        let pixels = |history: &EditHistory| {
+            history
+                .get_cels()
+                .iter()
+                .map(|cel| cel.get_pixels()[0])
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(pixels(&history), vec![1, 2, 2, 0, 0, 1]);
+        history.move_frame_right().unwrap();
+        assert_eq!(history.active_frame(), 2);
+        history.move_frame_left().unwrap();
+        assert_eq!(pixels(&history), vec![1, 2, 0, 1, 2, 0]);
+        assert_eq!(history.durations(), &[20, 10, 30]);
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert_eq!(pixels(&history), vec![0, 1, 1, 2, 2, 0]);
+        assert_eq!(history.durations(), &[10, 20, 30]);
+        assert_eq!(history.active_frame(), 0);
+    }
+
+    #[test]
+    fn frames_edit() {
+        let palette = vec![TRANSPARENT, BLUE, WHITE];
+        let frames = vec![
+            IndexedImage::new(2, 1, palette.clone(), vec![1, 0]).unwrap(),
+            IndexedImage::new(2, 1, palette.clone(), vec![2, 0]).unwrap(),
+            IndexedImage::new(2, 1, palette, vec![1, 2]).unwrap(),
+        ];
+        let mut history = EditHistory::new(frames);
+        history
+            .add_frames_edit(&[0, 1, 2], EditHistory::flip_h)
+            .unwrap();
+        assert_eq!(history.get_image(0).get_pixels(), &[0, 1]);
+        assert_eq!(history.get_image(1).get_pixels(), &[0, 2]);
+        assert_eq!(history.get_image(2).get_pixels(), &[2, 1]);
+        history
+            .add_frames_edit(&[1, 2], EditHistory::add_clear)
+            .unwrap();
+        assert_eq!(history.get_image(0).get_pixels(), &[0, 1]);
+        assert_eq!(history.get_image(1).get_pixels(), &[0, 0]);
+        assert_eq!(history.get_image(2).get_pixels(), &[0, 0]);
+        assert_eq!(history.active_frame(), 0);
+        history.undo().unwrap();
+        assert_eq!(history.get_image(2).get_pixels(), &[2, 1]);
+        history.undo().unwrap();
+        assert_eq!(history.get_image(2).get_pixels(), &[1, 2]);
+
+        history
+            .add_palette_remap(vec![TRANSPARENT, WHITE], vec![0, 1, 1])
+            .unwrap();
+        assert_eq!(history.get_image(2).get_pixels(), &[1, 1]);
+        assert_eq!(history.get_image(0).get_palette(), &[TRANSPARENT, WHITE]);
+    }
+
+    #[test]
+    fn tweens() {
+        let palette = vec![TRANSPARENT, RED];
+        let mut history = EditHistory::new(vec![
+            IndexedImage::new(4, 1, palette.clone(), vec![1, 0, 0, 0]).unwrap(),
+            IndexedImage::new(4, 1, palette, vec![0, 0, 1, 0]).unwrap(),
+        ]);
+        history.set_frame_duration(0, 50).unwrap();
+
+        history.add_tweens(0, 1, 1, TweenKind::Move).unwrap();
+        assert_eq!(history.frame_count(), 3);
+        assert_eq!(history.get_image(1).get_pixels(), &[0, 1, 0, 0]);
+        assert_eq!(history.durations(), &[50, 50, 100]);
+
+        //every in-between is one undo step
+        history.undo().unwrap();
+        assert_eq!(history.frame_count(), 2);
+
+        let mut history = EditHistory::new(vec![
+            IndexedImage::new(4, 1, vec![TRANSPARENT, RED], vec![1; 4]).unwrap(),
+            IndexedImage::new(4, 1, vec![TRANSPARENT, RED], vec![0; 4]).unwrap(),
+        ]);
+        history.add_tweens(0, 1, 1, TweenKind::Dissolve).unwrap();
+        assert_eq!(history.get_image(1).get_pixels(), &[0, 1, 0, 1]);
+    }
 }
@@ -1,49 +1,69 @@
 use fnv::FnvHashSet;
 use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
 
-pub fn fill_pixels(image: &IndexedImage, start: (u8, u8)) -> Result<Vec<usize>, IndexedImageError> {
+/// If `wrap` is true then the fill continues past the edges onto the opposite side
+pub fn fill_pixels(
+    image: &IndexedImage,
+    start: (u8, u8),
+    wrap: bool,
+) -> Result<Vec<usize>, IndexedImageError> {
     let i = image.get_pixel_index(start.0, start.1)?;
     let replace_color = image.get_pixel(i)?;
-    let to_replace = get_valid_neighbours(image, FnvHashSet::default(), start, replace_color)?;
+    let to_replace = get_valid_neighbours(image, start, replace_color, wrap)?;
 
     Ok(to_replace.into_iter().collect())
 }
 
+/// Walks the image from `start` with a stack rather than recursion so large images can't overflow
 fn get_valid_neighbours(
     image: &IndexedImage,
-    set: FnvHashSet<usize>,
     start: (u8, u8),
     replace_color: u8,
+    wrap: bool,
 ) -> Result<FnvHashSet<usize>, IndexedImageError> {
-    let start = (start.0 as isize, start.1 as isize);
-    let set = check_and_set(image, set, start, replace_color, (-1, 0))?;
-    let set = check_and_set(image, set, start, replace_color, (1, 0))?;
-    let set = check_and_set(image, set, start, replace_color, (0, -1))?;
-    check_and_set(image, set, start, replace_color, (0, 1))
+    let mut set = FnvHashSet::default();
+    set.insert(image.get_pixel_index(start.0, start.1)?);
+    let mut pending = vec![(start.0 as isize, start.1 as isize)];
+    while let Some(start) = pending.pop() {
+        for diff in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            if let Some(target) = check_and_set(image, &mut set, start, replace_color, diff, wrap)?
+            {
+                pending.push(target);
+            }
+        }
+    }
+    Ok(set)
 }
 
+/// Returns the target if it was added to `set`
 fn check_and_set(
     image: &IndexedImage,
-    mut set: FnvHashSet<usize>,
+    set: &mut FnvHashSet<usize>,
     start: (isize, isize),
     replace_color: u8,
     diff: (isize, isize),
-) -> Result<FnvHashSet<usize>, IndexedImageError> {
-    let target = (start.0 + diff.0, start.1 + diff.1);
+    wrap: bool,
+) -> Result<Option<(isize, isize)>, IndexedImageError> {
+    let mut target = (start.0 + diff.0, start.1 + diff.1);
+    if wrap {
+        target = (
+            target.0.rem_euclid(image.width() as isize),
+            target.1.rem_euclid(image.height() as isize),
+        );
+    }
     if target.0 >= 0
         && target.0 < image.width() as isize
         && target.1 >= 0
         && target.1 < image.height() as isize
     {
-        let start = (target.0 as u8, target.1 as u8);
-        let i = image.get_pixel_index(start.0, start.1)?;
+        let i = image.get_pixel_index(target.0 as u8, target.1 as u8)?;
         if !set.contains(&i) {
             let color = image.get_pixel(i)?;
             if color == replace_color {
                 set.insert(i);
-                return get_valid_neighbours(image, set, start, replace_color);
+                return Ok(Some(target));
             }
         }
     }
-    Ok(set)
+    Ok(None)
 }
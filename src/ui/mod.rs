@@ -3,4 +3,7 @@ pub mod edit_history;
 pub mod image_fill;
 pub mod palette;
 pub mod preview;
+pub mod reference;
+pub mod selection;
+pub mod tile_picker;
 pub mod timeline;
@@ -11,6 +11,7 @@ pub struct PaletteView {
     bounds: Rect,
     colors: Vec<Color>,
     selected: u8,
+    replace_only: Option<u8>,
     cols: usize,
     offset: isize,
     state: ViewState,
@@ -22,6 +23,7 @@ impl PaletteView {
             bounds: Rect::new_with_size(xy, width, height),
             colors: vec![TRANSPARENT],
             selected: 0,
+            replace_only: None,
             cols: 0,
             offset: 0,
             state: ViewState::Normal,
@@ -37,12 +39,18 @@ impl PaletteView {
 
     pub fn set_color_index(&mut self, idx: u8) {
         self.selected = idx;
+        self.scroll_to_selected();
     }
 
     pub fn get_selected_idx(&self) -> u8 {
         self.selected
     }
 
+    /// Marks the color that painting is restricted to
+    pub fn set_replace_only(&mut self, idx: Option<u8>) {
+        self.replace_only = idx;
+    }
+
     pub fn on_mouse_click(&mut self, mouse_xy: Coord) -> bool {
         if self.bounds.contains(mouse_xy) && self.state == ViewState::Normal {
             let xy = mouse_xy - self.bounds.top_left();
@@ -57,6 +65,15 @@ impl PaletteView {
         false
     }
 
+    fn scroll_to_selected(&mut self) {
+        if self.cols == 0 {
+            return;
+        }
+        let top = ((self.selected as usize / self.cols) * PER_SQUARE) as isize;
+        let bottom = top + PER_SQUARE as isize - self.bounds.height() as isize;
+        self.offset = self.offset.min(top).max(bottom).clamp(0, 100);
+    }
+
     pub fn on_scroll(&mut self, xy: Coord, y_diff: isize) {
         if self.bounds.contains(xy) && self.state == ViewState::Normal {
             self.offset += y_diff;
@@ -95,6 +112,18 @@ impl PixelView for PaletteView {
                 );
             }
 
+            if self.replace_only == Some(idx as u8) {
+                graphics.draw_rect(
+                    Rect::new_with_size(top_left + (SQUARE_SIZE - 3, SQUARE_SIZE - 3), 3, 3),
+                    fill(WHITE),
+                );
+                graphics.set_pixel(
+                    top_left.x + SQUARE_SIZE as isize - 2,
+                    top_left.y + SQUARE_SIZE as isize - 2,
+                    BLACK,
+                );
+            }
+
             if idx == self.selected as usize {
                 graphics.draw_rect(
                     Rect::new_with_size(top_left - 1, SQUARE_SIZE + 2, SQUARE_SIZE + 2),
@@ -1,3 +1,4 @@
+use crate::image::thumbnail::scale_to_fit;
 use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
 use pixels_graphics_lib::prelude::*;
 use pixels_graphics_lib::ui::prelude::*;
@@ -9,25 +10,44 @@ const COLORS: [Color; 4] = [WHITE, BLACK, LIGHT_GRAY, DARK_GRAY];
 #[derive(Debug)]
 pub struct Preview {
     bounds: Rect,
+    /// Unscaled image
+    source: IndexedImage,
     image: IndexedImage,
     background: usize,
     show_bg_options: bool,
+    /// Repeat the image 3x3 to check it tiles seamlessly
+    tiled: bool,
 }
 
 impl Preview {
     pub fn new(bounds: Rect, show_bg_options: bool) -> Self {
         Self {
             bounds,
+            source: IndexedImage::new(4, 4, vec![TRANSPARENT], vec![0; 16]).unwrap(),
             image: IndexedImage::new(4, 4, vec![TRANSPARENT], vec![0; 16]).unwrap(),
             background: 0,
             show_bg_options,
+            tiled: false,
         }
     }
 }
 
 impl Preview {
+    /// Images bigger than the preview are shrunk to fit
     pub fn set_image(&mut self, image: IndexedImage) {
-        self.image = image;
+        let max_height = if self.show_bg_options {
+            self.bounds.height().saturating_sub(COLOR_BUTTON_HEIGHT + 1)
+        } else {
+            self.bounds.height()
+        };
+        let repeats = if self.tiled { 3 } else { 1 };
+        self.image = scale_to_fit(&image, self.bounds.width() / repeats, max_height / repeats);
+        self.source = image;
+    }
+
+    pub fn set_tiled(&mut self, tiled: bool) {
+        self.tiled = tiled;
+        self.set_image(self.source.clone());
     }
 
     pub fn on_mouse_click(&mut self, xy: Coord) -> Color {
@@ -81,18 +101,34 @@ impl PixelView for Preview {
             0
         };
 
-        let x = if self.image.width() as usize >= self.bounds.width() {
+        let repeats = if self.tiled { 3 } else { 1 };
+        let width = self.image.width() as usize * repeats;
+        let height = self.image.height() as usize * repeats;
+
+        let x = if width >= self.bounds.width() {
             0
         } else {
-            (self.bounds.width() / 2) - (self.image.width() as usize / 2)
+            (self.bounds.width() / 2) - (width / 2)
         };
 
-        let y = if self.image.height() as usize >= (self.bounds.height() - y_offset) {
+        let y = if height >= (self.bounds.height() - y_offset) {
             y_offset
         } else {
-            ((self.bounds.height() - y_offset) / 2 - (self.image.height() as usize / 2)) + y_offset
+            ((self.bounds.height() - y_offset) / 2 - (height / 2)) + y_offset
         };
-        graphics.draw_indexed_image(self.bounds.top_left() + (x, y + 1), &self.image);
+        for tile_y in 0..repeats {
+            for tile_x in 0..repeats {
+                graphics.draw_indexed_image(
+                    self.bounds.top_left()
+                        + (x, y + 1)
+                        + (
+                            tile_x * self.image.width() as usize,
+                            tile_y * self.image.height() as usize,
+                        ),
+                    &self.image,
+                );
+            }
+        }
     }
 
     fn update(&mut self, _: &Timing) {}
@@ -0,0 +1,56 @@
+use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// How a reference image is shown on the canvas, stored per document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceSettings {
+    pub path: PathBuf,
+    /// Percentage
+    pub opacity: u8,
+    /// Percentage, at 100 one reference pixel covers one image pixel
+    pub scale: u16,
+    /// In image pixels
+    pub offset: (isize, isize),
+    /// Draw over the image instead of under it
+    pub above: bool,
+}
+
+impl ReferenceSettings {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            opacity: 50,
+            scale: 100,
+            offset: (0, 0),
+            above: false,
+        }
+    }
+}
+
+/// Image loaded from [ReferenceSettings::path], it's only ever drawn and never saved
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub image: Image,
+    pub settings: ReferenceSettings,
+}
+
+impl Reference {
+    pub fn open(settings: ReferenceSettings) -> Result<Self, String> {
+        let image = open_image(&settings.path).map_err(|e| format!("{e:?}"))?;
+        Ok(Self { image, settings })
+    }
+
+    /// Color to draw at image position `xy`, where `xy` can be a fraction of a pixel
+    pub fn color_at(&self, xy: (f32, f32)) -> Option<Color> {
+        let scale = self.settings.scale.max(1) as f32 / 100.0;
+        let x = ((xy.0 - self.settings.offset.0 as f32) / scale).floor();
+        let y = ((xy.1 - self.settings.offset.1 as f32) / scale).floor();
+        if x < 0.0 || y < 0.0 || x >= self.image.width() as f32 || y >= self.image.height() as f32 {
+            return None;
+        }
+        let mut color = self.image.get_pixel(x as usize, y as usize);
+        color.a = (color.a as usize * self.settings.opacity.min(100) as usize / 100) as u8;
+        Some(color)
+    }
+}
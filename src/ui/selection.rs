@@ -0,0 +1,301 @@
+use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
+use std::sync::Mutex;
+
+/// Shared by every open image so pixels can be pasted between them
+static CLIPBOARD: Mutex<Option<Clip>> = Mutex::new(None);
+
+pub fn set_clipboard(clip: Clip) {
+    *CLIPBOARD.lock().expect("locking clipboard") = Some(clip);
+}
+
+pub fn get_clipboard() -> Option<Clip> {
+    CLIPBOARD.lock().expect("locking clipboard").clone()
+}
+
+/// Selected pixels of an image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Selection {
+    width: usize,
+    height: usize,
+    mask: Vec<bool>,
+}
+
+impl Selection {
+    /// Selects every pixel between `start` and `end` (inclusive), clipped to the image
+    pub fn from_rect<P: Into<Coord>>(width: u8, height: u8, start: P, end: P) -> Self {
+        let start = start.into();
+        let end = end.into();
+        let width = width as usize;
+        let height = height as usize;
+        let mut mask = vec![false; width * height];
+        let left = start.x.min(end.x).max(0);
+        let top = start.y.min(end.y).max(0);
+        let right = start.x.max(end.x).min(width as isize - 1);
+        let bottom = start.y.max(end.y).min(height as isize - 1);
+        for y in top..=bottom {
+            for x in left..=right {
+                mask[x as usize + y as usize * width] = true;
+            }
+        }
+        Self {
+            width,
+            height,
+            mask,
+        }
+    }
+
+    /// Selects the pixel indexes, such as the pixels returned by `fill_pixels`
+    pub fn from_idxs(width: u8, height: u8, idxs: &[usize]) -> Self {
+        let width = width as usize;
+        let height = height as usize;
+        let mut mask = vec![false; width * height];
+        for i in idxs {
+            if let Some(selected) = mask.get_mut(*i) {
+                *selected = true;
+            }
+        }
+        Self {
+            width,
+            height,
+            mask,
+        }
+    }
+
+    /// Selects every pixel using palette index `color`
+    pub fn from_color(image: &IndexedImage, color: u8) -> Self {
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            mask: image.get_pixels().iter().map(|&c| c == color).collect(),
+        }
+    }
+
+    /// Adds the pixels selected in `other`
+    pub fn add(&mut self, other: &Selection) {
+        for (selected, other) in self.mask.iter_mut().zip(&other.mask) {
+            *selected |= *other;
+        }
+    }
+
+    /// Removes the pixels selected in `other`
+    pub fn subtract(&mut self, other: &Selection) {
+        for (selected, other) in self.mask.iter_mut().zip(&other.mask) {
+            *selected &= !*other;
+        }
+    }
+
+    pub fn contains_idx(&self, i: usize) -> bool {
+        self.mask.get(i).copied().unwrap_or_default()
+    }
+
+    pub fn contains<P: Into<Coord>>(&self, xy: P) -> bool {
+        let xy = xy.into();
+        xy.x >= 0
+            && xy.y >= 0
+            && (xy.x as usize) < self.width
+            && (xy.y as usize) < self.height
+            && self.mask[xy.x as usize + xy.y as usize * self.width]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.mask.contains(&true)
+    }
+
+    /// Pixel indexes of the selected pixels
+    pub fn idxs(&self) -> Vec<usize> {
+        self.mask
+            .iter()
+            .enumerate()
+            .filter_map(|(i, selected)| selected.then_some(i))
+            .collect()
+    }
+
+    /// Top left and bottom right (inclusive) of the selected pixels
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        let idxs = self.idxs();
+        if idxs.is_empty() {
+            return None;
+        }
+        let xs = idxs.iter().map(|i| (i % self.width) as isize);
+        let ys = idxs.iter().map(|i| (i / self.width) as isize);
+        Some((
+            coord!(xs.clone().min()?, ys.clone().min()?),
+            coord!(xs.max()?, ys.max()?),
+        ))
+    }
+}
+
+/// Copied pixels with the palette they were copied from
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Clip {
+    width: usize,
+    height: usize,
+    /// None for pixels that weren't selected
+    pixels: Vec<Option<u8>>,
+    palette: Vec<Color>,
+}
+
+impl Clip {
+    pub fn from_selection(image: &IndexedImage, selection: &Selection) -> Option<Self> {
+        let (top_left, bottom_right) = selection.bounds()?;
+        let width = (bottom_right.x - top_left.x + 1) as usize;
+        let height = (bottom_right.y - top_left.y + 1) as usize;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in top_left.y..=bottom_right.y {
+            for x in top_left.x..=bottom_right.x {
+                if selection.contains((x, y)) {
+                    let i = image.get_pixel_index(x as u8, y as u8).ok()?;
+                    pixels.push(image.get_pixel(i).ok());
+                } else {
+                    pixels.push(None);
+                }
+            }
+        }
+        Some(Self {
+            width,
+            height,
+            pixels,
+            palette: image.get_palette().to_vec(),
+        })
+    }
+
+    /// Converts the pixels to the nearest colors in `palette`
+    pub fn remap(&self, palette: &[Color]) -> Vec<Option<u8>> {
+        if self.palette == palette {
+            return self.pixels.clone();
+        }
+        self.pixels
+            .iter()
+            .map(|idx| {
+                idx.map(|idx| {
+                    let color = self
+                        .palette
+                        .get(idx as usize)
+                        .copied()
+                        .unwrap_or(TRANSPARENT);
+                    nearest_color(palette, color)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Index of the closest color in `palette`, transparent colors only match transparent colors
+/// (if the palette has any)
+pub(crate) fn nearest_color(palette: &[Color], color: Color) -> u8 {
+    if color.is_transparent() {
+        if let Some(i) = palette.iter().position(|c| c.is_transparent()) {
+            return i as u8;
+        }
+    }
+    let diff = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| {
+            diff(c.r, color.r) + diff(c.g, color.g) + diff(c.b, color.b) + diff(c.a, color.a)
+        })
+        .map(|(i, _)| i as u8)
+        .unwrap_or_default()
+}
+
+/// Pixels that have been lifted or pasted and can be moved before being committed to the image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Floating {
+    /// Image position of the top left, may be outside of the image
+    pub pos: Coord,
+    width: usize,
+    height: usize,
+    pixels: Vec<Option<u8>>,
+    /// Pixel indexes the pixels were lifted from, these are cleared when committed
+    cleared: Vec<usize>,
+}
+
+impl Floating {
+    /// Lifts the selected pixels out of `image`
+    pub fn lift(image: &IndexedImage, selection: &Selection) -> Option<Self> {
+        let clip = Clip::from_selection(image, selection)?;
+        let (pos, _) = selection.bounds()?;
+        Some(Self {
+            pos,
+            width: clip.width,
+            height: clip.height,
+            pixels: clip.pixels,
+            cleared: selection.idxs(),
+        })
+    }
+
+    pub fn paste(pos: Coord, clip: &Clip, palette: &[Color]) -> Self {
+        Self {
+            pos,
+            width: clip.width,
+            height: clip.height,
+            pixels: clip.remap(palette),
+            cleared: vec![],
+        }
+    }
+
+    /// Palette index at image position `xy`
+    pub fn get<P: Into<Coord>>(&self, xy: P) -> Option<u8> {
+        let xy = xy.into() - self.pos;
+        if xy.x < 0 || xy.y < 0 || xy.x as usize >= self.width || xy.y as usize >= self.height {
+            return None;
+        }
+        self.pixels[xy.x as usize + xy.y as usize * self.width]
+    }
+
+    pub fn contains<P: Into<Coord>>(&self, xy: P) -> bool {
+        self.get(xy).is_some()
+    }
+
+    /// Top left and bottom right (inclusive) in image coords
+    pub fn bounds(&self) -> (Coord, Coord) {
+        (
+            self.pos,
+            self.pos + (self.width as isize - 1, self.height as isize - 1),
+        )
+    }
+
+    pub fn cleared(&self) -> &[usize] {
+        &self.cleared
+    }
+
+    /// Pixel indexes and palette indexes for the pixels inside of an image of `width`x`height`
+    pub fn image_pixels(&self, width: u8, height: u8) -> Vec<(usize, u8)> {
+        let mut output = vec![];
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let xy = self.pos + (x, y);
+                if xy.x < 0 || xy.y < 0 || xy.x >= width as isize || xy.y >= height as isize {
+                    continue;
+                }
+                if let Some(idx) = self.pixels[x as usize + y as usize * self.width] {
+                    output.push((xy.x as usize + xy.y as usize * width as usize, idx));
+                }
+            }
+        }
+        output
+    }
+
+    /// Selection covering the pixels inside of an image of `width`x`height`
+    pub fn selection(&self, width: u8, height: u8) -> Selection {
+        let mut mask = vec![false; width as usize * height as usize];
+        for (i, _) in self.image_pixels(width, height) {
+            mask[i] = true;
+        }
+        Selection {
+            width: width as usize,
+            height: height as usize,
+            mask,
+        }
+    }
+
+    pub fn to_clip(&self, palette: &[Color]) -> Clip {
+        Clip {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.clone(),
+            palette: palette.to_vec(),
+        }
+    }
+}
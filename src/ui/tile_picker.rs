@@ -0,0 +1,151 @@
+use crate::image::thumbnail::scale_to_fit;
+use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
+use pixels_graphics_lib::prelude::*;
+use pixels_graphics_lib::ui::prelude::*;
+
+const SQUARE_SIZE: usize = 16;
+const SPACING: usize = 2;
+const PER_SQUARE: usize = SPACING + SQUARE_SIZE;
+
+/// Tiles for painting a tilemap, the first square is the empty tile
+#[derive(Debug)]
+pub struct TilePicker {
+    bounds: Rect,
+    thumbnails: Vec<IndexedImage>,
+    /// 0 is empty otherwise it's the tile index + 1
+    selected: u8,
+    cols: usize,
+    offset: isize,
+    state: ViewState,
+}
+
+impl TilePicker {
+    pub fn new(xy: Coord, (width, height): (usize, usize)) -> Self {
+        Self {
+            bounds: Rect::new_with_size(xy, width, height),
+            thumbnails: vec![],
+            selected: 1,
+            cols: (width / PER_SQUARE).max(1),
+            offset: 0,
+            state: ViewState::Normal,
+        }
+    }
+}
+
+impl TilePicker {
+    pub fn set_tiles(&mut self, tiles: &[IndexedImage]) {
+        self.thumbnails = tiles
+            .iter()
+            .map(|tile| scale_to_fit(tile, SQUARE_SIZE, SQUARE_SIZE))
+            .collect();
+        self.selected = self.selected.min(self.thumbnails.len() as u8);
+    }
+
+    /// Updates the thumbnail for a single tile, `idx` is the tile index
+    pub fn update_tile(&mut self, idx: usize, tile: &IndexedImage) {
+        if let Some(thumbnail) = self.thumbnails.get_mut(idx) {
+            *thumbnail = scale_to_fit(tile, SQUARE_SIZE, SQUARE_SIZE);
+        }
+    }
+
+    pub fn set_selected(&mut self, selected: u8) {
+        self.selected = selected.min(self.thumbnails.len() as u8);
+        self.scroll_to_selected();
+    }
+
+    pub fn get_selected(&self) -> u8 {
+        self.selected
+    }
+
+    pub fn on_mouse_click(&mut self, mouse_xy: Coord) -> bool {
+        if self.bounds.contains(mouse_xy) && self.state == ViewState::Normal {
+            let xy = mouse_xy - self.bounds.top_left();
+            let x = xy.x / PER_SQUARE as isize;
+            let y = (xy.y + self.offset) / PER_SQUARE as isize;
+            let i = x + y * (self.cols as isize);
+            if x < self.cols as isize && i >= 0 && i <= self.thumbnails.len() as isize {
+                self.selected = i as u8;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn max_offset(&self) -> isize {
+        let rows = (self.thumbnails.len() + 1).div_ceil(self.cols);
+        (rows * PER_SQUARE) as isize - self.bounds.height() as isize
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let top = ((self.selected as usize / self.cols) * PER_SQUARE) as isize;
+        let bottom = top + PER_SQUARE as isize - self.bounds.height() as isize;
+        self.offset = self
+            .offset
+            .min(top)
+            .max(bottom)
+            .clamp(0, self.max_offset().max(0));
+    }
+
+    pub fn on_scroll(&mut self, xy: Coord, y_diff: isize) {
+        if self.bounds.contains(xy) && self.state == ViewState::Normal {
+            self.offset += y_diff;
+            self.offset = self.offset.clamp(0, self.max_offset().max(0));
+        }
+    }
+}
+
+impl PixelView for TilePicker {
+    fn set_position(&mut self, top_left: Coord) {
+        self.bounds = self.bounds.move_to(top_left);
+    }
+
+    fn bounds(&self) -> &Rect {
+        &self.bounds
+    }
+
+    fn render(&self, graphics: &mut Graphics, _mouse_xy: &MouseData) {
+        let orig_trans = graphics.set_translate(self.bounds.top_left() + (0, -self.offset));
+        graphics.clip_mut().set_valid_rect(self.bounds.clone());
+
+        for idx in 0..=self.thumbnails.len() {
+            let top_left = Coord::from((idx % self.cols, idx / self.cols)) * PER_SQUARE;
+            match idx.checked_sub(1).and_then(|i| self.thumbnails.get(i)) {
+                Some(thumbnail) => {
+                    let xy = top_left
+                        + (
+                            (SQUARE_SIZE - thumbnail.width() as usize) / 2,
+                            (SQUARE_SIZE - thumbnail.height() as usize) / 2,
+                        );
+                    graphics.draw_indexed_image(xy, thumbnail);
+                }
+                None => {
+                    graphics.draw_rect(
+                        Rect::new_with_size(top_left, SQUARE_SIZE, SQUARE_SIZE),
+                        stroke(WHITE),
+                    );
+                    graphics.draw_line(top_left, top_left + (SQUARE_SIZE, SQUARE_SIZE), WHITE);
+                }
+            }
+
+            if idx == self.selected as usize {
+                graphics.draw_rect(
+                    Rect::new_with_size(top_left - 1, SQUARE_SIZE + 2, SQUARE_SIZE + 2),
+                    stroke(WHITE),
+                );
+            }
+        }
+
+        graphics.set_translate(orig_trans);
+        graphics.clip_mut().set_all_valid();
+    }
+
+    fn update(&mut self, _: &Timing) {}
+
+    fn set_state(&mut self, state: ViewState) {
+        self.state = state;
+    }
+
+    fn get_state(&self) -> ViewState {
+        self.state
+    }
+}
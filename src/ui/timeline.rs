@@ -1,15 +1,37 @@
+use crate::image::thumbnail::scale_to_fit;
 use pixels_graphics_lib::buffer_graphics_lib::prelude::*;
 use pixels_graphics_lib::prelude::*;
 use pixels_graphics_lib::ui::prelude::*;
 
 const PADDING: isize = 2;
+/// Frames bigger than this are shrunk to fit
+pub const MAX_FRAME_SIZE: usize = 64;
+/// Space below each frame for its duration
+pub const DURATION_HEIGHT: usize = 7;
+/// Frames are spaced at least this far apart so the duration fits
+const MIN_CELL_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimelineClick {
+    Frame(usize),
+    Duration(usize),
+    /// A frame was dragged to a new position
+    Move {
+        from: usize,
+        to: usize,
+    },
+}
 
 #[derive(Debug)]
 pub struct Timeline {
     bounds: Rect,
     frames: Vec<IndexedImage>,
+    /// Milliseconds for each frame
+    durations: Vec<u32>,
     offset: usize,
     selected: usize,
+    /// First and last frame (inclusive) to highlight
+    range: Option<(usize, usize)>,
     frame_size: (usize, usize),
     background: Color,
     state: ViewState,
@@ -20,8 +42,10 @@ impl Timeline {
         Self {
             bounds,
             frames: vec![],
+            durations: vec![],
             offset: 0,
             selected: 0,
+            range: None,
             frame_size: (0, 0),
             background: WHITE,
             state: ViewState::Normal,
@@ -30,41 +54,55 @@ impl Timeline {
 }
 
 impl Timeline {
-    pub fn on_mouse_click(&mut self, mouse_xy: Coord) -> Option<usize> {
-        if self.bounds.contains(mouse_xy) && self.state == ViewState::Normal {
-            let x = mouse_xy.x - self.bounds.left() + self.offset as isize - 2;
-            let selected = (x / (self.frame_size.0 as isize + 2)) as usize;
-            if selected < self.frames.len() {
-                self.selected = selected;
-                Some(self.selected)
-            } else {
-                None
-            }
+    pub fn on_mouse_click(&mut self, down_at: Coord, mouse_xy: Coord) -> Option<TimelineClick> {
+        if self.state != ViewState::Normal {
+            return None;
+        }
+        let from = self.frame_at(down_at)?;
+        let to = self.frame_at(mouse_xy)?;
+        self.selected = to;
+        if from != to {
+            return Some(TimelineClick::Move { from, to });
+        }
+        let y = mouse_xy.y - self.bounds.top() - PADDING;
+        if y >= self.frame_size.1 as isize {
+            Some(TimelineClick::Duration(to))
         } else {
-            None
+            Some(TimelineClick::Frame(to))
         }
     }
 
     pub fn on_scroll(&mut self, mouse_xy: Coord, x_diff: isize) {
         if self.bounds.contains(mouse_xy) && self.state == ViewState::Normal {
-            let max_visible_count = self.bounds.width() / (self.frame_size.0 + 2);
+            let max_visible_count = self.bounds.width() / (self.cell_width() + 2);
             let last_frame = self.frames.len() as isize - max_visible_count as isize + 2;
-            let maximum = self.frame_size.0 as isize * last_frame.max(0);
+            let maximum = self.cell_width() as isize * last_frame.max(0);
             self.offset = ((self.offset as isize) + x_diff).max(0).min(maximum) as usize
         }
     }
 
     pub fn set_frames(&mut self, frames: Vec<IndexedImage>, active: usize) {
         assert!(!frames.is_empty());
-        self.frame_size = (frames[0].width() as usize, frames[0].height() as usize);
-        self.frames = frames;
+        self.frames = frames.iter().map(thumbnail).collect();
+        self.frame_size = (
+            self.frames[0].width() as usize,
+            self.frames[0].height() as usize,
+        );
         self.selected = active;
         self.center_on_frame(active);
     }
 
     pub fn update_frame(&mut self, frame: IndexedImage) {
         self.frames.remove(self.selected);
-        self.frames.insert(self.selected, frame);
+        self.frames.insert(self.selected, thumbnail(&frame));
+    }
+
+    pub fn set_durations(&mut self, durations: Vec<u32>) {
+        self.durations = durations;
+    }
+
+    pub fn set_range(&mut self, range: Option<(usize, usize)>) {
+        self.range = range;
     }
 
     pub fn set_background(&mut self, background: Color) {
@@ -78,11 +116,33 @@ impl Timeline {
 }
 
 impl Timeline {
+    fn cell_width(&self) -> usize {
+        self.frame_size.0.max(MIN_CELL_WIDTH)
+    }
+
+    fn frame_at(&self, xy: Coord) -> Option<usize> {
+        if !self.bounds.contains(xy) {
+            return None;
+        }
+        let x = xy.x - self.bounds.left() + self.offset as isize - PADDING;
+        let idx = (x / (self.cell_width() as isize + PADDING)) as usize;
+        if x >= 0 && idx < self.frames.len() {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    fn frame_x(&self, idx: usize) -> isize {
+        (idx as isize) * (self.cell_width() as isize + PADDING) + self.bounds.left() + PADDING
+            - (self.offset as isize)
+    }
+
     fn is_frame_visible(&self, idx: usize) -> bool {
-        let x = (idx * self.frame_size.0) as isize;
+        let x = (idx * self.cell_width()) as isize;
         let frame_rect = Rect::new_with_size(
             (x - self.offset as isize, 0),
-            self.frame_size.0,
+            self.cell_width(),
             self.frame_size.1,
         );
         let visible_rect = Rect::new_with_size((0, 0), self.bounds.width(), self.frame_size.1);
@@ -90,19 +150,23 @@ impl Timeline {
     }
 
     fn center_on_frame(&mut self, idx: usize) {
-        let max_visible_count = self.bounds.width() / (self.frame_size.0 + 2);
+        let max_visible_count = self.bounds.width() / (self.cell_width() + 2);
         self.offset = if self.frames.len() <= max_visible_count || self.bounds.width() == 0 {
             0
         } else {
-            let mid_view = (self.bounds.width() / 2) - (self.frame_size.0 / 2);
-            let frame_start = (self.frame_size.0 + 2) * idx;
+            let mid_view = (self.bounds.width() / 2) - (self.cell_width() / 2);
+            let frame_start = (self.cell_width() + 2) * idx;
             let offset = mid_view + frame_start;
-            let limit = (self.frames.len() - max_visible_count) * (self.frame_size.0 + 2);
-            offset.max(0).min(limit)
+            let limit = (self.frames.len() - max_visible_count) * (self.cell_width() + 2);
+            offset.min(limit)
         };
     }
 }
 
+fn thumbnail(frame: &IndexedImage) -> IndexedImage {
+    scale_to_fit(frame, MAX_FRAME_SIZE, MAX_FRAME_SIZE)
+}
+
 impl PixelView for Timeline {
     fn set_position(&mut self, _top_left: Coord) {
         unimplemented!("Does not support moving")
@@ -112,14 +176,12 @@ impl PixelView for Timeline {
         &self.bounds
     }
 
-    fn render(&self, graphics: &mut Graphics, _: &MouseData) {
+    fn render(&self, graphics: &mut Graphics, mouse: &MouseData) {
         graphics.clip_mut().set_valid_rect(self.bounds.clone());
         let y = self.bounds.top() + PADDING;
-        let start_x = self.bounds.left() + PADDING;
         for (i, frame) in self.frames.iter().enumerate() {
             if self.is_frame_visible(i) {
-                let x = (i as isize) * (self.frame_size.0 as isize + PADDING) + start_x
-                    - (self.offset as isize);
+                let x = self.frame_x(i);
                 graphics.draw_rect(
                     Rect::new_with_size(
                         Coord::new(x, y),
@@ -129,6 +191,18 @@ impl PixelView for Timeline {
                     fill(self.background),
                 );
                 graphics.draw_indexed_image((x, y), frame);
+                if let Some((start, end)) = self.range {
+                    if (start..=end).contains(&i) {
+                        graphics.draw_line(
+                            (x, y + self.frame_size.1 as isize),
+                            (
+                                x + self.frame_size.0 as isize - 1,
+                                y + self.frame_size.1 as isize,
+                            ),
+                            YELLOW,
+                        );
+                    }
+                }
                 if self.selected == i {
                     graphics.draw_rect(
                         Rect::new_with_size(
@@ -139,6 +213,33 @@ impl PixelView for Timeline {
                         stroke(CYAN),
                     );
                 }
+                if let Some(duration) = self.durations.get(i) {
+                    graphics.draw_text(
+                        &duration.to_string(),
+                        TextPos::Px(x, y + self.frame_size.1 as isize + 1),
+                        (
+                            if self.selected == i { CYAN } else { WHITE },
+                            PixelFont::Limited3x5,
+                            WrappingStrategy::Cutoff(self.cell_width() / 4),
+                        ),
+                    );
+                }
+            }
+        }
+        if self.state == ViewState::Normal {
+            let dragging = mouse
+                .is_down(MouseButton::Left)
+                .and_then(|down_at| Some((self.frame_at(down_at)?, self.frame_at(mouse.xy)?)));
+            if let Some((from, to)) = dragging {
+                if from != to {
+                    //the dragged frame will be placed on the far side of the target
+                    let x = if to > from {
+                        self.frame_x(to) + self.cell_width() as isize
+                    } else {
+                        self.frame_x(to) - 1
+                    };
+                    graphics.draw_line((x, self.bounds.top()), (x, self.bounds.bottom()), CYAN);
+                }
             }
         }
         graphics.clip_mut().set_all_valid();